@@ -0,0 +1,209 @@
+//! Colors of blackbody (Planckian) radiators.
+//!
+//! [`blackbody_color`] returns the color of an ideal blackbody at a given
+//! temperature in any [`ColorSpace`], normalized to a luminance of 1. This is
+//! useful for lighting, for example to get the color of a 3200 K tungsten
+//! light in a rendering working space.
+use super::{
+    color::{color_spaces, Color, ColorSpace, RgbPrimaries},
+    conversion::ColorConversion,
+    spectral::{self, Observer, Spectrum, WAVELENGTH_MAX, WAVELENGTH_MIN},
+    transform::xyy_to_xyz,
+};
+use crate::{Float, Vec3};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// First radiation constant for spectral radiance, `2hc²`, in W·m²/sr.
+const C1L: Float = 1.191_042_972e-16;
+/// Second radiation constant, `hc/k`, in m·K.
+const C2: Float = 1.438_776_877e-2;
+
+/// How the chromaticity of a blackbody is calculated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BlackbodyMethod {
+    /// Integrate Planck's law against the CIE 1931 color matching functions.
+    #[default]
+    Planck,
+    /// The cubic spline approximation of the Planckian locus by Kim et al.,
+    /// "Design of Advanced Color Temperature Control System for HDTV
+    /// Applications" (2002). It is much faster, and within 0.001 in xy of
+    /// [`Planck`](BlackbodyMethod::Planck), but only valid from 1667 K to
+    /// 25000 K. Temperatures outside of that range are clamped.
+    Approximation,
+}
+
+/// Returned when a blackbody color can't be represented in the requested
+/// color space without negative components.
+#[derive(Debug, Copy, Clone)]
+pub struct OutOfGamut {
+    /// The unclipped color, in the linear version of the requested color
+    /// space.
+    pub color: Color,
+}
+
+impl core::fmt::Display for OutOfGamut {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:?} is outside of the gamut of {:?}",
+            self.color.value, self.color.space
+        )
+    }
+}
+
+impl std::error::Error for OutOfGamut {}
+
+/// Evaluates Planck's law, returning the spectral radiance of a blackbody at
+/// `temperature` (in kelvin) and `wavelength` (in nm), in W/(sr·m²·nm).
+pub fn planck(wavelength: Float, temperature: Float) -> Float {
+    let wavelength_m = wavelength * 1e-9;
+    let radiance = C1L / (wavelength_m.powi(5) * ((C2 / (wavelength_m * temperature)).exp() - 1.0));
+    radiance * 1e-9
+}
+
+/// Returns the spectral radiance of a blackbody at `temperature` (in kelvin),
+/// sampled every nanometer from [`WAVELENGTH_MIN`] to [`WAVELENGTH_MAX`].
+pub fn blackbody_spectrum(temperature: Float) -> Spectrum {
+    let len = (WAVELENGTH_MAX - WAVELENGTH_MIN) as usize + 1;
+    let values = (0..len)
+        .map(|i| planck(WAVELENGTH_MIN + i as Float, temperature))
+        .collect();
+    Spectrum::from_uniform(WAVELENGTH_MIN, 1.0, values)
+}
+
+/// Returns the CIE XYZ tristimulus values of a blackbody at `temperature` (in
+/// kelvin), normalized so that `Y` is 1.
+pub fn blackbody_xyz(temperature: Float, method: BlackbodyMethod) -> Vec3 {
+    match method {
+        BlackbodyMethod::Planck => {
+            let xyz = spectral::integrate(Observer::Cie1931, |wavelength| {
+                planck(wavelength, temperature)
+            });
+            xyz * (1.0 / xyz.y)
+        }
+        BlackbodyMethod::Approximation => {
+            let [x, y] = planckian_locus_approximation(temperature);
+            xyy_to_xyz(Vec3::new(x, y, 1.0), color_spaces::CIE_XYZ.white_point())
+        }
+    }
+}
+
+/// The Planckian locus in xy, as approximated by Kim et al.
+fn planckian_locus_approximation(temperature: Float) -> [Float; 2] {
+    let t = temperature.clamp(1667.0, 25000.0);
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.266_123_9e9 / t3 - 0.234_358_9e6 / t2 + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t3 + 2.107_037_9e6 / t2 + 0.222_634_7e3 / t + 0.240_390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.106_381_4 * x3 - 1.348_110_20 * x2 + 2.185_558_32 * x - 0.202_196_83
+    } else if t <= 4000.0 {
+        -0.954_947_6 * x3 - 1.374_185_93 * x2 + 2.091_370_15 * x - 0.167_488_67
+    } else {
+        3.081_758_0 * x3 - 5.873_386_70 * x2 + 3.751_129_97 * x - 0.370_014_83
+    };
+    [x, y]
+}
+
+/// Returns the color of a blackbody at `temperature` (in kelvin) in `space`,
+/// normalized to a luminance (CIE `Y`) of 1.
+///
+/// The color is relative to the white point of `space`, without chromatic
+/// adaptation, so a blackbody close to the white point appears neutral.
+///
+/// Returns [`OutOfGamut`] if the color has a negative component in the linear
+/// version of an RGB `space`.
+pub fn blackbody_color(
+    temperature: Float,
+    method: BlackbodyMethod,
+    space: ColorSpace,
+) -> Result<Color, OutOfGamut> {
+    let xyz = blackbody_xyz(temperature, method);
+    let xyz_space = color_spaces::CIE_XYZ.with_whitepoint(space.white_point());
+    let linear_space = space.as_linear();
+    let linear = ColorConversion::new(xyz_space, linear_space).convert(xyz);
+    if space.primaries() != RgbPrimaries::CieXyz
+        && (linear.x < 0.0 || linear.y < 0.0 || linear.z < 0.0)
+    {
+        return Err(OutOfGamut {
+            color: Color {
+                value: linear,
+                space: linear_space,
+            },
+        });
+    }
+    let value = ColorConversion::new(linear_space, space).convert(linear);
+    Ok(Color { value, space })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{details::color::WhitePoint, spaces};
+
+    fn xy(xyz: Vec3) -> [Float; 2] {
+        let sum = xyz.x + xyz.y + xyz.z;
+        [xyz.x / sum, xyz.y / sum]
+    }
+
+    #[test]
+    fn illuminant_a() {
+        // Illuminant A is defined as a blackbody at 2856 K with an older value
+        // of the second radiation constant.
+        let temperature = 2856.0 * C2 / 1.435e-2;
+        let xyz = blackbody_xyz(temperature, BlackbodyMethod::Planck);
+        let expected = Vec3::from_slice(WhitePoint::A.values());
+        assert!(
+            xyz.abs_diff_eq(expected, 0.003),
+            "{:?} != {:?}",
+            xyz,
+            expected
+        );
+    }
+
+    #[test]
+    fn approximation_matches_planck() {
+        for temperature in [1700.0, 2000.0, 3200.0, 5000.0, 6500.0, 10000.0, 20000.0] {
+            let [x0, y0] = xy(blackbody_xyz(temperature, BlackbodyMethod::Planck));
+            let [x1, y1] = xy(blackbody_xyz(temperature, BlackbodyMethod::Approximation));
+            assert!(
+                (x0 - x1).abs() < 0.001 && (y0 - y1).abs() < 0.001,
+                "{}K: ({}, {}) != ({}, {})",
+                temperature,
+                x0,
+                y0,
+                x1,
+                y1
+            );
+        }
+    }
+
+    #[test]
+    fn tungsten_in_srgb() {
+        let color = blackbody_color(3200.0, BlackbodyMethod::Planck, spaces::LINEAR_SRGB).unwrap();
+        let luminance = color.to(spaces::CIE_XYZ).value.y;
+        assert!((luminance - 1.0).abs() < 0.0001);
+        assert!(color.value.x > color.value.y && color.value.y > color.value.z);
+
+        let encoded =
+            blackbody_color(3200.0, BlackbodyMethod::Planck, spaces::ENCODED_SRGB).unwrap();
+        let expected = color.to(spaces::ENCODED_SRGB).value;
+        assert!(encoded.value.abs_diff_eq(expected, 0.0001));
+    }
+
+    #[test]
+    fn out_of_gamut() {
+        let result = blackbody_color(1000.0, BlackbodyMethod::Planck, spaces::LINEAR_SRGB);
+        let error = result.unwrap_err();
+        assert!(error.color.value.z < 0.0);
+        assert_eq!(error.color.space, spaces::LINEAR_SRGB);
+
+        let result = blackbody_color(1000.0, BlackbodyMethod::Planck, spaces::CIE_XYZ);
+        assert!(result.is_ok());
+    }
+}
@@ -4,12 +4,80 @@
 //! Chromatic Adaptation Transformation means transforming a linear color
 //! space's coordinate system from one white point reference to another.
 //!
-//! [`Sharp`(LmsConeSpace::Sharp) is used as the default for conversions by
-//! [`ColorConversion`][crate::details::conversion::ColorConversion].
-use crate::{Mat3, Vec3};
+//! [`Sharp`](LmsConeSpace::Sharp) is used as the default for conversions by
+//! [`ColorConversion`][crate::details::conversion::ColorConversion]. Use
+//! [`ConversionOptions`][crate::details::conversion::ConversionOptions] to
+//! choose a different method.
+//!
+//! By default adaptation is complete. Partial adaptation, as used by CIECAM02
+//! and CAM16 to model viewing conditions, can be requested with a
+//! [`DegreeOfAdaptation`].
+use crate::{Float, Mat3, Vec3};
+#[cfg(all(not(feature = "std"), not(test), feature = "libm"))]
+use num_traits::Float as _;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The surround of a viewing condition, as defined by CIECAM02.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Surround {
+    #[default]
+    Average,
+    Dim,
+    Dark,
+}
+
+impl Surround {
+    /// Returns the factor `F` determining the degree of adaptation.
+    pub fn factor(&self) -> Float {
+        match self {
+            Surround::Average => 1.0,
+            Surround::Dim => 0.9,
+            Surround::Dark => 0.8,
+        }
+    }
+}
+
+/// How completely the observer adapts to the destination white point.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DegreeOfAdaptation {
+    /// Complete adaptation, `D = 1`.
+    #[default]
+    Full,
+    /// An explicit degree of adaptation `D`, from 0 (no adaptation) to 1.
+    Explicit(Float),
+    /// `D` computed from the viewing conditions as in CIECAM02 and CAM16,
+    /// `D = F·(1 − (1/3.6)·e^((−L_A − 42)/92))`.
+    Computed {
+        /// The luminance of the adapting field `L_A`, in cd/m².
+        adapting_luminance: Float,
+        surround: Surround,
+    },
+}
+
+impl DegreeOfAdaptation {
+    /// Returns `D`, clamped to `0.0..=1.0`.
+    pub fn value(&self) -> Float {
+        let degree = match self {
+            DegreeOfAdaptation::Full => 1.0,
+            DegreeOfAdaptation::Explicit(degree) => *degree,
+            DegreeOfAdaptation::Computed {
+                adapting_luminance,
+                surround,
+            } => {
+                surround.factor()
+                    * (1.0 - (1.0 / 3.6) * ((-adapting_luminance - 42.0) / 92.0).exp())
+            }
+        };
+        degree.clamp(0.0, 1.0)
+    }
+}
 
 /// Supported conversion methods.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum LmsConeSpace {
     VonKries,
     Bradford,
@@ -17,6 +85,24 @@ pub enum LmsConeSpace {
     Sharp,
     CmcCat2000,
     Cat02,
+    /// The CAT16 transform from CAM16.
+    Cat16,
+    /// Scaling of the XYZ tristimulus values, without a cone space.
+    XyzScaling,
+    /// The Hunt-Pointer-Estevez cone space, normalized to equal-energy white,
+    /// as used by CIECAM02 and CAM16 after adaptation.
+    HuntPointerEstevez,
+    /// From S. Bianco and R. Schettini, "Two New von Kries Based Chromatic
+    /// Adaptation Transforms Found by Numerical Optimization" (2010).
+    BiancoSchettini,
+    /// The variant of [`BiancoSchettini`](LmsConeSpace::BiancoSchettini)
+    /// constrained to positive cone responses for colors in the spectral
+    /// locus.
+    BiancoSchettiniPc,
+    /// The sharpened cone space of M. D. Fairchild, "A Revision of CIECAM97s
+    /// for Practical Applications" (2001), named "Fairchild" in
+    /// colour-science.
+    Fairchild2001,
 }
 
 impl LmsConeSpace {
@@ -24,7 +110,7 @@ impl LmsConeSpace {
     // from S. Bianco. "Two New von Kries Based Chromatic Adapatation Transforms
     // Found by Numerical Optimization."
     #[rustfmt::skip]
-    pub fn matrix(&self) -> Mat3 {
+    pub const fn matrix(&self) -> Mat3 {
         match self {
             LmsConeSpace::VonKries => {
                 Mat3::from_cols_array(&[0.40024, -0.2263, 0.0, 0.7076, 1.16532, 0.0, -0.08081, 0.0457, 0.91822])
@@ -36,30 +122,268 @@ impl LmsConeSpace {
                 Mat3::from_cols_array(&[1.2694, -0.8364, 0.0297, -0.0988, 1.8006, -0.0315, -0.1706, 0.0357, 1.0018])
             }
             LmsConeSpace::CmcCat2000 => {
-                Mat3::from_cols_array(&[0.7982, -0.5918, 0.0008, 0.3389, 1.5512, 0.239, -0.1371, 0.0406, 0.9753])
+                Mat3::from_cols_array(&[0.7982, -0.5918, 0.0008, 0.3389, 1.5512, 0.0239, -0.1371, 0.0406, 0.9753])
             }
             LmsConeSpace::Cat02 => {
                 Mat3::from_cols_array(&[0.7328, -0.7036, 0.0030, 0.4296, 1.6975, 0.0136, -0.1624, 0.0061, 0.9834])
             }
+            LmsConeSpace::Cat16 => {
+                Mat3::from_cols_array(&[0.401288, -0.250268, -0.002079, 0.650173, 1.204414, 0.048952, -0.051461, 0.045854, 0.953127])
+            }
+            LmsConeSpace::XyzScaling => Mat3::IDENTITY,
+            LmsConeSpace::HuntPointerEstevez => {
+                Mat3::from_cols_array(&[0.38971, -0.22981, 0.0, 0.68898, 1.18340, 0.0, -0.07868, 0.04641, 1.0])
+            }
+            LmsConeSpace::BiancoSchettini => {
+                Mat3::from_cols_array(&[0.8752, -0.8904, -0.0061, 0.2787, 1.8709, 0.0162, -0.1539, 0.0195, 0.9899])
+            }
+            LmsConeSpace::BiancoSchettiniPc => {
+                Mat3::from_cols_array(&[0.6489, -0.3775, -0.0271, 0.3915, 1.3055, 0.0888, -0.0404, 0.0720, 0.9383])
+            }
+            LmsConeSpace::Fairchild2001 => {
+                Mat3::from_cols_array(&[0.8562, -0.8360, 0.0357, 0.3372, 1.8327, -0.0469, -0.1934, 0.0033, 1.0112])
+            }
         }
     }
 
     /// Calculate the CAT matrix for converting from one white point to another.
-    #[rustfmt::skip]
     pub fn chromatic_adaptation_transform(
         &self,
         src_illuminant: Vec3,
         dst_illuminant: Vec3,
+    ) -> Mat3 {
+        self.partial_chromatic_adaptation_transform(
+            src_illuminant,
+            dst_illuminant,
+            DegreeOfAdaptation::Full,
+        )
+    }
+
+    /// Calculate the CAT matrix for converting from one white point to
+    /// another with incomplete adaptation. The cone responses are scaled by
+    /// `D·(dst/src) + 1 − D`, so `D = 0` leaves colors unchanged.
+    ///
+    /// Both illuminants are expected to have the same luminance `Y`.
+    pub fn partial_chromatic_adaptation_transform(
+        &self,
+        src_illuminant: Vec3,
+        dst_illuminant: Vec3,
+        degree: DegreeOfAdaptation,
     ) -> Mat3 {
         let cone_space_transform = self.matrix();
         let src_cone_response = cone_space_transform * src_illuminant;
         let dst_cone_response = cone_space_transform * dst_illuminant;
-        let src_to_dst_cone = Mat3::from_cols_array(&[
-            dst_cone_response.x / src_cone_response.x, 0.0, 0.0,
-            0.0, dst_cone_response.y / src_cone_response.y, 0.0,
-            0.0, 0.0, dst_cone_response.z / src_cone_response.z,
-        ]);
+        let src_to_dst_cone = von_kries_gains(src_cone_response, dst_cone_response, degree.value());
 
         cone_space_transform.inverse() * src_to_dst_cone * cone_space_transform
     }
+
+    /// Calculate the two-step CAT matrix for converting from one white point
+    /// to another, adapting from the source to an equal-energy white with
+    /// `src_degree` and then from it to the destination with `dst_degree`.
+    ///
+    /// Unlike [`partial_chromatic_adaptation_transform`], chaining two-step
+    /// transforms is transitive, so `B → C` after `A → B` equals `A → C` when
+    /// the same degree is used for `B`. This was proposed for CAT16 by Li et
+    /// al., "Comprehensive color solutions: CAM16, CAT16, and CAM16-UCS"
+    /// (2017), and is usually called as `LmsConeSpace::Cat16.two_step_...`.
+    ///
+    /// Conversions only use the one-step transform, so this isn't reachable
+    /// through [`ConversionOptions`](crate::details::conversion::ConversionOptions).
+    ///
+    /// [`partial_chromatic_adaptation_transform`]: LmsConeSpace::partial_chromatic_adaptation_transform
+    pub fn two_step_chromatic_adaptation_transform(
+        &self,
+        src_illuminant: Vec3,
+        dst_illuminant: Vec3,
+        src_degree: DegreeOfAdaptation,
+        dst_degree: DegreeOfAdaptation,
+    ) -> Mat3 {
+        let cone_space_transform = self.matrix();
+        let src_cone_response = cone_space_transform * src_illuminant;
+        let dst_cone_response = cone_space_transform * dst_illuminant;
+        let equal_energy_response = cone_space_transform * Vec3::new(1.0, 1.0, 1.0);
+        let src_to_equal_energy =
+            von_kries_gains(src_cone_response, equal_energy_response, src_degree.value());
+        let dst_to_equal_energy =
+            von_kries_gains(dst_cone_response, equal_energy_response, dst_degree.value());
+
+        cone_space_transform.inverse()
+            * dst_to_equal_energy.inverse()
+            * src_to_equal_energy
+            * cone_space_transform
+    }
+}
+
+/// The diagonal matrix scaling cone responses from `src` to `dst` with a
+/// degree of adaptation `degree`.
+#[rustfmt::skip]
+fn von_kries_gains(src: Vec3, dst: Vec3, degree: Float) -> Mat3 {
+    let gain = |src: Float, dst: Float| degree * dst / src + 1.0 - degree;
+    Mat3::from_cols_array(&[
+        gain(src.x, dst.x), 0.0, 0.0,
+        0.0, gain(src.y, dst.y), 0.0,
+        0.0, 0.0, gain(src.z, dst.z),
+    ])
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod test {
+    use super::*;
+    use crate::details::color::WhitePoint;
+
+    const ALL: [LmsConeSpace; 11] = [
+        LmsConeSpace::VonKries,
+        LmsConeSpace::Bradford,
+        LmsConeSpace::Sharp,
+        LmsConeSpace::CmcCat2000,
+        LmsConeSpace::Cat02,
+        LmsConeSpace::Cat16,
+        LmsConeSpace::XyzScaling,
+        LmsConeSpace::HuntPointerEstevez,
+        LmsConeSpace::BiancoSchettini,
+        LmsConeSpace::BiancoSchettiniPc,
+        LmsConeSpace::Fairchild2001,
+    ];
+
+    fn d65_to_d50(method: LmsConeSpace) -> Mat3 {
+        method.chromatic_adaptation_transform(
+            Vec3::from_slice(WhitePoint::D65.values()),
+            Vec3::from_slice(WhitePoint::D50.values()),
+        )
+    }
+
+    #[test]
+    fn white_maps_to_white() {
+        let d65 = Vec3::from_slice(WhitePoint::D65.values());
+        let d50 = Vec3::from_slice(WhitePoint::D50.values());
+        for method in ALL {
+            let adapted = d65_to_d50(method) * d65;
+            assert!(adapted.abs_diff_eq(d50, 0.0001), "{:?}", method);
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn bruce_lindbloom_matrices() {
+        // http://www.brucelindbloom.com/index.html?Eqn_ChromAdapt.html
+        let bradford = Mat3::from_cols_array(&[
+            1.0478112, 0.0295424, -0.0092345,
+            0.0228866, 0.9904844, 0.0150436,
+            -0.0501270, -0.0170491, 0.7521316,
+        ]);
+        assert!(d65_to_d50(LmsConeSpace::Bradford).abs_diff_eq(bradford, 0.0001));
+
+        let xyz_scaling = Mat3::from_cols_array(&[
+            1.0144665, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 0.7578869,
+        ]);
+        assert!(d65_to_d50(LmsConeSpace::XyzScaling).abs_diff_eq(xyz_scaling, 0.0001));
+    }
+
+    #[test]
+    fn colour_science_adaptations() {
+        // The example of `colour.adaptation.chromatic_adaptation_VonKries`,
+        // with the `transform` named in each row.
+        let transforms = [
+            (LmsConeSpace::Cat02, "CAT02", [0.2163881, 0.1257, 0.0384749]),
+            (
+                LmsConeSpace::Cat16,
+                "CAT16",
+                [0.2119933, 0.1223699, 0.0381001],
+            ),
+            (
+                LmsConeSpace::CmcCat2000,
+                "CMCCAT2000",
+                [0.2154887, 0.1249115, 0.0383747],
+            ),
+            (
+                LmsConeSpace::HuntPointerEstevez,
+                "HPE",
+                [0.2139403, 0.1226231, 0.0389192],
+            ),
+            (
+                LmsConeSpace::BiancoSchettini,
+                "Bianco 2010",
+                [0.2161825, 0.1262873, 0.0388493],
+            ),
+            (
+                LmsConeSpace::BiancoSchettiniPc,
+                "Bianco PC 2010",
+                [0.2110819, 0.1224607, 0.0384345],
+            ),
+            (
+                LmsConeSpace::Fairchild2001,
+                "Fairchild",
+                [0.2179020, 0.1268461, 0.0382189],
+            ),
+        ];
+        for (method, name, expected) in transforms {
+            let transform = method.chromatic_adaptation_transform(
+                Vec3::new(0.95045593, 1.0, 1.08905775),
+                Vec3::new(0.96429568, 1.0, 0.82510460),
+            );
+            let result = transform * Vec3::new(0.20654008, 0.12197225, 0.05136952);
+            let expected = Vec3::from_slice(&expected);
+            assert!(
+                result.abs_diff_eq(expected, 0.000001),
+                "{}: {:?} != {:?}",
+                name,
+                result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn degree_of_adaptation() {
+        // The example of `colour.adaptation.degree_of_adaptation`.
+        let computed = DegreeOfAdaptation::Computed {
+            adapting_luminance: 318.31,
+            surround: Surround::Average,
+        };
+        assert!((computed.value() - 0.9944687).abs() < 0.00001);
+        assert_eq!(DegreeOfAdaptation::Explicit(1.5).value(), 1.0);
+
+        let d65 = Vec3::from_slice(WhitePoint::D65.values());
+        let d50 = Vec3::from_slice(WhitePoint::D50.values());
+        let none = LmsConeSpace::Cat16.partial_chromatic_adaptation_transform(
+            d65,
+            d50,
+            DegreeOfAdaptation::Explicit(0.0),
+        );
+        assert!(none.abs_diff_eq(Mat3::IDENTITY, 0.00001));
+
+        let half = LmsConeSpace::Cat16.partial_chromatic_adaptation_transform(
+            d65,
+            d50,
+            DegreeOfAdaptation::Explicit(0.5),
+        );
+        let adapted = half * d65;
+        assert!(adapted.abs_diff_eq((d65 + d50) * 0.5, 0.0001));
+    }
+
+    #[test]
+    fn two_step_cat16() {
+        let d65 = Vec3::from_slice(WhitePoint::D65.values());
+        let d50 = Vec3::from_slice(WhitePoint::D50.values());
+        let a = Vec3::from_slice(WhitePoint::A.values());
+        let full = DegreeOfAdaptation::Full;
+        let two_step =
+            LmsConeSpace::Cat16.two_step_chromatic_adaptation_transform(d65, d50, full, full);
+        assert!(two_step.abs_diff_eq(d65_to_d50(LmsConeSpace::Cat16), 0.00001));
+
+        let (d1, d2, d3) = (
+            DegreeOfAdaptation::Explicit(0.9),
+            DegreeOfAdaptation::Explicit(0.7),
+            DegreeOfAdaptation::Explicit(0.8),
+        );
+        let cat16 = LmsConeSpace::Cat16;
+        let chained = cat16.two_step_chromatic_adaptation_transform(d50, a, d2, d3)
+            * cat16.two_step_chromatic_adaptation_transform(d65, d50, d1, d2);
+        let direct = cat16.two_step_chromatic_adaptation_transform(d65, a, d1, d3);
+        assert!(chained.abs_diff_eq(direct, 0.00001));
+    }
 }
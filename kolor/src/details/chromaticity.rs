@@ -0,0 +1,286 @@
+//! Monochromatic colors and the spectral locus in chromaticity diagrams.
+//!
+//! The spectral locus is the curve traced by the chromaticities of
+//! monochromatic light from [`WAVELENGTH_MIN`] to [`WAVELENGTH_MAX`]. Together
+//! with the purple line, which connects its two ends, it encloses all
+//! physically realizable chromaticities.
+//!
+//! [`dominant_wavelength`] locates a color relative to the spectral locus, as
+//! reported for example when measuring display primaries.
+use super::{
+    color::{color_spaces, Color, ColorSpace, WhitePoint},
+    spectral::{Observer, WAVELENGTH_MAX, WAVELENGTH_MIN},
+    transform::{xyz_to_cie_1960_ucs_uvv, xyz_to_xyy},
+};
+use crate::{Float, Vec3};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A 2D chromaticity coordinate system.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ChromaticityCoordinates {
+    /// CIE 1931 xy, from the [`CieXyY`][super::color::TransformFn::CieXyY]
+    /// transform.
+    #[default]
+    Xy,
+    /// CIE 1976 u'v', derived from the CIE 1960 uv coordinates of the
+    /// [`Cie1960UcsUvV`][super::color::TransformFn::Cie1960UcsUvV] transform
+    /// as `u' = u`, `v' = 1.5·v`.
+    UvPrime,
+}
+
+impl ChromaticityCoordinates {
+    /// Returns the chromaticity of a CIE XYZ color.
+    pub fn from_xyz(&self, xyz: Vec3) -> [Float; 2] {
+        let white_point = color_spaces::CIE_XYZ.white_point();
+        match self {
+            ChromaticityCoordinates::Xy => {
+                let xyy = xyz_to_xyy(xyz, white_point);
+                [xyy.x, xyy.y]
+            }
+            ChromaticityCoordinates::UvPrime => {
+                let uvv = xyz_to_cie_1960_ucs_uvv(xyz, white_point);
+                [uvv.x, 1.5 * uvv.y]
+            }
+        }
+    }
+}
+
+/// Returns the CIE XYZ tristimulus values of monochromatic light at
+/// `wavelength` (in nm) with a radiant power of 1, which are the values of
+/// the color matching functions of `observer` at that wavelength.
+pub fn monochromatic_xyz(wavelength: Float, observer: Observer) -> Color {
+    let xyz = observer.cmf(wavelength);
+    Color::new(xyz.x, xyz.y, xyz.z, color_spaces::CIE_XYZ)
+}
+
+/// Returns the color of monochromatic light at `wavelength` (in nm) in
+/// `space`, for the CIE 1931 observer and a radiant power of 1.
+///
+/// The color is relative to the white point of `space`, without chromatic
+/// adaptation. Monochromatic colors are outside of the gamut of every RGB
+/// color space, so the result has negative components in linear RGB.
+pub fn monochromatic_color(wavelength: Float, space: ColorSpace) -> Color {
+    let xyz = Observer::Cie1931.cmf(wavelength);
+    let xyz_space = color_spaces::CIE_XYZ.with_whitepoint(space.white_point());
+    Color {
+        value: xyz,
+        space: xyz_space,
+    }
+    .to(space)
+}
+
+/// Returns the spectral locus as a polyline, with a vertex every `step` nm
+/// from [`WAVELENGTH_MIN`] up to [`WAVELENGTH_MAX`].
+pub fn spectral_locus(
+    observer: Observer,
+    coordinates: ChromaticityCoordinates,
+    step: Float,
+) -> Vec<[Float; 2]> {
+    assert!(step > 0.0, "step must be positive");
+    let count = ((WAVELENGTH_MAX - WAVELENGTH_MIN) / step) as usize + 1;
+    (0..count)
+        .map(|i| {
+            let wavelength = WAVELENGTH_MIN + step * i as Float;
+            coordinates.from_xyz(observer.cmf(wavelength))
+        })
+        .collect()
+}
+
+/// Returns the purple line, from the chromaticity at [`WAVELENGTH_MIN`] to
+/// the one at [`WAVELENGTH_MAX`].
+pub fn purple_line(observer: Observer, coordinates: ChromaticityCoordinates) -> [[Float; 2]; 2] {
+    [
+        coordinates.from_xyz(observer.cmf(WAVELENGTH_MIN)),
+        coordinates.from_xyz(observer.cmf(WAVELENGTH_MAX)),
+    ]
+}
+
+/// The dominant wavelength and excitation purity of a color, as returned by
+/// [`dominant_wavelength`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DominantWavelength {
+    /// The wavelength in nm. If `complementary` is true, this is the
+    /// complementary wavelength.
+    pub wavelength: Float,
+    /// True for purple colors, which have no dominant wavelength. `wavelength`
+    /// is then the wavelength opposite to the color across the white point.
+    pub complementary: bool,
+    /// The distance from the white point to the color, relative to the
+    /// distance from the white point to the spectral locus (or the purple
+    /// line) in the same direction, in xy. 0 for the white point and 1 for
+    /// monochromatic colors.
+    pub excitation_purity: Float,
+}
+
+/// Step in nm between the vertices of the spectral locus used by
+/// [`dominant_wavelength`].
+const LOCUS_STEP: Float = 1.0;
+
+/// Calculates the dominant (or complementary) wavelength and excitation
+/// purity of `color` relative to `white_point`, in CIE 1931 xy.
+///
+/// The color is interpreted colorimetrically in its own color space, so
+/// `white_point` is usually the white point of that space. Returns `None` if
+/// the color has the same chromaticity as `white_point`, or no chromaticity
+/// at all (black).
+pub fn dominant_wavelength(color: Color, white_point: WhitePoint) -> Option<DominantWavelength> {
+    let xyz_space = color_spaces::CIE_XYZ.with_whitepoint(color.space.white_point());
+    let xyz = color.to(xyz_space).value;
+    let sum = xyz.x + xyz.y + xyz.z;
+    if sum.abs() <= Float::EPSILON {
+        return None;
+    }
+    let sample = ChromaticityCoordinates::Xy.from_xyz(xyz);
+    let white = ChromaticityCoordinates::Xy.from_xyz(Vec3::from_slice(white_point.values()));
+    let direction = [sample[0] - white[0], sample[1] - white[1]];
+    if direction[0].hypot(direction[1]) < 1e-6 {
+        return None;
+    }
+
+    let locus = spectral_locus(Observer::Cie1931, ChromaticityCoordinates::Xy, LOCUS_STEP);
+    let on_locus = intersect_locus(&locus, white, direction);
+    let [purple_start, purple_end] = purple_line(Observer::Cie1931, ChromaticityCoordinates::Xy);
+    let on_purple_line =
+        intersect_segment(white, direction, purple_start, purple_end).map(|(t, _)| t);
+
+    let complementary = |t: Float| {
+        let opposite = [-direction[0], -direction[1]];
+        let (_, wavelength) = intersect_locus(&locus, white, opposite)?;
+        Some(DominantWavelength {
+            wavelength,
+            complementary: true,
+            excitation_purity: 1.0 / t,
+        })
+    };
+    match (on_locus, on_purple_line) {
+        (Some((t, _)), Some(purple)) if purple < t => complementary(purple),
+        (Some((t, wavelength)), _) => Some(DominantWavelength {
+            wavelength,
+            complementary: false,
+            excitation_purity: 1.0 / t,
+        }),
+        (None, Some(t)) => complementary(t),
+        (None, None) => None,
+    }
+}
+
+/// Intersects the ray `origin + t·direction` with the spectral locus, returning
+/// the closest intersection as `(t, wavelength)`.
+fn intersect_locus(
+    locus: &[[Float; 2]],
+    origin: [Float; 2],
+    direction: [Float; 2],
+) -> Option<(Float, Float)> {
+    locus
+        .windows(2)
+        .enumerate()
+        .filter_map(|(i, segment)| {
+            let (t, u) = intersect_segment(origin, direction, segment[0], segment[1])?;
+            Some((t, WAVELENGTH_MIN + (i as Float + u) * LOCUS_STEP))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// Intersects the ray `origin + t·direction` with the segment from `a` to `b`,
+/// returning `(t, u)` where `u` is the position along the segment.
+fn intersect_segment(
+    origin: [Float; 2],
+    direction: [Float; 2],
+    a: [Float; 2],
+    b: [Float; 2],
+) -> Option<(Float, Float)> {
+    let edge = [b[0] - a[0], b[1] - a[1]];
+    let denominator = direction[0] * edge[1] - direction[1] * edge[0];
+    if denominator.abs() <= Float::EPSILON {
+        return None;
+    }
+    let offset = [a[0] - origin[0], a[1] - origin[1]];
+    let t = (offset[0] * edge[1] - offset[1] * edge[0]) / denominator;
+    let u = (offset[0] * direction[1] - offset[1] * direction[0]) / denominator;
+    if t > 0.0 && (0.0..=1.0).contains(&u) {
+        Some((t, u))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    #[test]
+    fn spectral_locus_points() {
+        let locus = spectral_locus(Observer::Cie1931, ChromaticityCoordinates::Xy, 10.0);
+        assert_eq!(locus.len(), 41);
+        // 520 nm, from CIE 015.
+        let [x, y] = locus[14];
+        assert!((x - 0.0743).abs() < 0.0001 && (y - 0.8338).abs() < 0.0001);
+
+        let uv = spectral_locus(Observer::Cie1931, ChromaticityCoordinates::UvPrime, 10.0);
+        let [u, v] = uv[14];
+        assert!((u - 0.0231).abs() < 0.0001 && (v - 0.5837).abs() < 0.0001);
+
+        let [start, end] = purple_line(Observer::Cie1931, ChromaticityCoordinates::Xy);
+        assert_eq!(start, locus[0]);
+        assert_eq!(end, locus[40]);
+        assert!((end[0] - 0.7347).abs() < 0.0001 && (end[1] - 0.2653).abs() < 0.0001);
+    }
+
+    #[test]
+    fn monochromatic() {
+        let xyz = monochromatic_xyz(555.0, Observer::Cie1931);
+        assert!((xyz.value.y - 1.0).abs() < 0.0001);
+
+        let srgb = monochromatic_color(520.0, spaces::LINEAR_SRGB);
+        assert!(srgb.value.x < 0.0 && srgb.value.z < 0.0);
+        let back = srgb.to(spaces::CIE_XYZ);
+        assert!(back.value.abs_diff_eq(Observer::Cie1931.cmf(520.0), 0.0001));
+
+        let lab = monochromatic_color(600.0, spaces::CIE_XYZ.to_cie_lab());
+        assert_eq!(lab.space, spaces::CIE_XYZ.to_cie_lab());
+    }
+
+    #[test]
+    fn srgb_primaries() {
+        for (rgb, expected) in [
+            ([1.0, 0.0, 0.0], 611.3),
+            ([0.0, 1.0, 0.0], 549.1),
+            ([0.0, 0.0, 1.0], 464.3),
+        ] {
+            let color = Color::new(rgb[0], rgb[1], rgb[2], spaces::LINEAR_SRGB);
+            let dominant = dominant_wavelength(color, WhitePoint::D65).unwrap();
+            assert!(!dominant.complementary);
+            assert!(
+                (dominant.wavelength - expected).abs() < 0.5,
+                "{} != {}",
+                dominant.wavelength,
+                expected
+            );
+            assert!(dominant.excitation_purity > 0.5 && dominant.excitation_purity < 1.0);
+        }
+    }
+
+    #[test]
+    fn purple_and_monochromatic() {
+        let magenta = Color::new(1.0, 0.0, 1.0, spaces::LINEAR_SRGB);
+        let dominant = dominant_wavelength(magenta, WhitePoint::D65).unwrap();
+        assert!(dominant.complementary);
+        assert!(dominant.wavelength > 520.0 && dominant.wavelength < 560.0);
+
+        let laser = monochromatic_xyz(532.0, Observer::Cie1931);
+        let laser = Color {
+            value: laser.value,
+            space: spaces::CIE_XYZ,
+        };
+        let dominant = dominant_wavelength(laser, WhitePoint::D65).unwrap();
+        assert!((dominant.wavelength - 532.0).abs() < 0.01);
+        assert!((dominant.excitation_purity - 1.0).abs() < 0.001);
+
+        let white = Color::new(0.5, 0.5, 0.5, spaces::LINEAR_SRGB);
+        assert_eq!(dominant_wavelength(white, WhitePoint::D65), None);
+    }
+}
@@ -0,0 +1,1145 @@
+//! The Academy Common LUT Format (CLF), version 3.
+//!
+//! A CLF file is an XML `ProcessList` of process nodes which are applied in
+//! order. [`ProcessList::from_conversion`] expresses a [`ColorConversion`] as
+//! a process list for ACES interchange: the linear part becomes a `Matrix`
+//! node, and the sRGB, BT.601 and PQ transform functions become `Exponent`,
+//! `Range` and `LUT1D` nodes. Other transform functions, such as Oklab, mix
+//! the components of a color and can't be expressed without a 3D LUT.
+//!
+//! [`ProcessList::parse`] reads the `Matrix`, `Range`, `Log`, `Exponent`,
+//! `LUT1D` and `LUT3D` nodes, and [`ProcessList::apply`] evaluates them.
+//! Integer bit depths are taken into account when parsing, so that all nodes
+//! operate on normalized floats, where 1.0 corresponds to the largest integer
+//! code value.
+use super::{
+    color::TransformFn,
+    conversion::ColorConversion,
+    lut::{Interpolation, Lut1d, Lut3d, Shaper},
+    transform::pq,
+};
+use crate::{Float, Mat3, Vec3};
+use std::fmt::Write;
+
+/// The number of entries in the `LUT1D` nodes used for the PQ transform
+/// function.
+const PQ_LUT_SIZE: usize = 4096;
+
+/// Returned when a process list can't be created or parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClfError {
+    /// The transform function can't be expressed with the supported process
+    /// nodes.
+    UnsupportedTransform(TransformFn),
+    /// The document isn't well-formed XML, or its root isn't a `ProcessList`.
+    InvalidXml,
+    /// The named process node isn't supported.
+    UnsupportedNode(String),
+    /// The named process node has missing or malformed parameters.
+    InvalidNode(String),
+}
+
+impl core::fmt::Display for ClfError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ClfError::UnsupportedTransform(transform) => {
+                write!(f, "{:?} can't be expressed as CLF process nodes", transform)
+            }
+            ClfError::InvalidXml => write!(f, "invalid CLF document"),
+            ClfError::UnsupportedNode(name) => write!(f, "unsupported CLF node {}", name),
+            ClfError::InvalidNode(name) => write!(f, "invalid CLF node {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ClfError {}
+
+/// The style of a CLF `Log` node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LogStyle {
+    Log10,
+    Log2,
+    AntiLog10,
+    AntiLog2,
+    /// `logSideSlope · log(linSideSlope · x + linSideOffset) + logSideOffset`
+    LinToLog,
+    /// The inverse of [`LinToLog`](Self::LinToLog).
+    LogToLin,
+}
+
+impl LogStyle {
+    const NAMES: [(LogStyle, &'static str); 6] = [
+        (LogStyle::Log10, "log10"),
+        (LogStyle::Log2, "log2"),
+        (LogStyle::AntiLog10, "antiLog10"),
+        (LogStyle::AntiLog2, "antiLog2"),
+        (LogStyle::LinToLog, "linToLog"),
+        (LogStyle::LogToLin, "logToLin"),
+    ];
+}
+
+/// The parameters of the [`LinToLog`](LogStyle::LinToLog) and
+/// [`LogToLin`](LogStyle::LogToLin) styles of a CLF `Log` node.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LogParams {
+    pub base: Float,
+    pub log_side_slope: Float,
+    pub log_side_offset: Float,
+    pub lin_side_slope: Float,
+    pub lin_side_offset: Float,
+}
+
+impl Default for LogParams {
+    fn default() -> Self {
+        Self {
+            base: 2.0,
+            log_side_slope: 1.0,
+            log_side_offset: 0.0,
+            lin_side_slope: 1.0,
+            lin_side_offset: 0.0,
+        }
+    }
+}
+
+/// The style of a CLF `Exponent` node.
+///
+/// The basic styles raise to the power of the exponent, clamping negative
+/// values to 0, mirroring them around 0, or passing them through unchanged.
+/// The `MonCurve` styles add a linear segment near 0, like the sRGB transfer
+/// functions. The `Fwd` styles go from encoded to linear values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExponentStyle {
+    BasicFwd,
+    BasicRev,
+    BasicMirrorFwd,
+    BasicMirrorRev,
+    BasicPassThruFwd,
+    BasicPassThruRev,
+    MonCurveFwd,
+    MonCurveRev,
+    MonCurveMirrorFwd,
+    MonCurveMirrorRev,
+}
+
+impl ExponentStyle {
+    const NAMES: [(ExponentStyle, &'static str); 10] = [
+        (ExponentStyle::BasicFwd, "basicFwd"),
+        (ExponentStyle::BasicRev, "basicRev"),
+        (ExponentStyle::BasicMirrorFwd, "basicMirrorFwd"),
+        (ExponentStyle::BasicMirrorRev, "basicMirrorRev"),
+        (ExponentStyle::BasicPassThruFwd, "basicPassThruFwd"),
+        (ExponentStyle::BasicPassThruRev, "basicPassThruRev"),
+        (ExponentStyle::MonCurveFwd, "monCurveFwd"),
+        (ExponentStyle::MonCurveRev, "monCurveRev"),
+        (ExponentStyle::MonCurveMirrorFwd, "monCurveMirrorFwd"),
+        (ExponentStyle::MonCurveMirrorRev, "monCurveMirrorRev"),
+    ];
+
+    fn apply(self, value: Float, exponent: Float, offset: Float) -> Float {
+        use ExponentStyle::*;
+        let mirror = |f: &dyn Fn(Float) -> Float| f(value.abs()).copysign(value);
+        match self {
+            BasicFwd => value.max(0.0).powf(exponent),
+            BasicRev => value.max(0.0).powf(1.0 / exponent),
+            BasicMirrorFwd => mirror(&|value| value.powf(exponent)),
+            BasicMirrorRev => mirror(&|value| value.powf(1.0 / exponent)),
+            BasicPassThruFwd if value < 0.0 => value,
+            BasicPassThruFwd => value.powf(exponent),
+            BasicPassThruRev if value < 0.0 => value,
+            BasicPassThruRev => value.powf(1.0 / exponent),
+            MonCurveFwd => mon_curve_fwd(value, exponent, offset),
+            MonCurveRev => mon_curve_rev(value, exponent, offset),
+            MonCurveMirrorFwd => mirror(&|value| mon_curve_fwd(value, exponent, offset)),
+            MonCurveMirrorRev => mirror(&|value| mon_curve_rev(value, exponent, offset)),
+        }
+    }
+}
+
+/// Returns the break point and the slope of the linear segment of a
+/// `MonCurve` exponent.
+fn mon_curve_linear_segment(exponent: Float, offset: Float) -> (Float, Float) {
+    let break_point = offset / (exponent - 1.0);
+    let slope = ((break_point + offset) / (1.0 + offset)).powf(exponent) / break_point;
+    (break_point, slope)
+}
+
+fn mon_curve_fwd(value: Float, exponent: Float, offset: Float) -> Float {
+    if offset <= 0.0 {
+        return value.max(0.0).powf(exponent);
+    }
+    let (break_point, slope) = mon_curve_linear_segment(exponent, offset);
+    if value >= break_point {
+        ((value + offset) / (1.0 + offset)).powf(exponent)
+    } else {
+        value * slope
+    }
+}
+
+fn mon_curve_rev(value: Float, exponent: Float, offset: Float) -> Float {
+    if offset <= 0.0 {
+        return value.max(0.0).powf(1.0 / exponent);
+    }
+    let (break_point, slope) = mon_curve_linear_segment(exponent, offset);
+    if value >= break_point * slope {
+        (1.0 + offset) * value.powf(1.0 / exponent) - offset
+    } else {
+        value / slope
+    }
+}
+
+/// A process node of a CLF [`ProcessList`], with normalized input and output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessNode {
+    /// Multiplies by `matrix` and adds `offset`.
+    Matrix {
+        matrix: Mat3,
+        offset: Vec3,
+    },
+    /// Maps `min.0` to `min.1` and `max.0` to `max.1` linearly. If only one
+    /// bound is given, values are offset to match it instead. Unless `clamp`
+    /// is false, the output is clamped to the given bounds.
+    Range {
+        min: Option<(Float, Float)>,
+        max: Option<(Float, Float)>,
+        clamp: bool,
+    },
+    Log {
+        style: LogStyle,
+        params: LogParams,
+    },
+    /// Applies an exponent to each component, with per-component parameters.
+    Exponent {
+        style: ExponentStyle,
+        exponent: Vec3,
+        offset: Vec3,
+    },
+    Lut1d(Lut1d),
+    Lut3d {
+        lut: Lut3d,
+        interpolation: Interpolation,
+    },
+}
+
+impl ProcessNode {
+    /// Applies the node to a color.
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        match self {
+            ProcessNode::Matrix { matrix, offset } => *matrix * color + *offset,
+            ProcessNode::Range { min, max, clamp } => {
+                let range = |value: Float| {
+                    let mapped = match (min, max) {
+                        (Some((min_in, min_out)), Some((max_in, max_out))) => {
+                            let scale = (max_out - min_out) / (max_in - min_in);
+                            min_out + (value - min_in) * scale
+                        }
+                        (Some((min_in, min_out)), None) => value + min_out - min_in,
+                        (None, Some((max_in, max_out))) => value + max_out - max_in,
+                        (None, None) => value,
+                    };
+                    if !clamp {
+                        return mapped;
+                    }
+                    let mapped = min.map_or(mapped, |(_, min_out)| mapped.max(min_out));
+                    max.map_or(mapped, |(_, max_out)| mapped.min(max_out))
+                };
+                Vec3::new(range(color.x), range(color.y), range(color.z))
+            }
+            ProcessNode::Log { style, params } => {
+                let log = |value: Float| match style {
+                    LogStyle::Log10 => value.max(Float::MIN_POSITIVE).log10(),
+                    LogStyle::Log2 => value.max(Float::MIN_POSITIVE).log2(),
+                    LogStyle::AntiLog10 => (10.0 as Float).powf(value),
+                    LogStyle::AntiLog2 => value.exp2(),
+                    LogStyle::LinToLog => {
+                        let linear = params.lin_side_slope * value + params.lin_side_offset;
+                        params.log_side_slope * linear.max(Float::MIN_POSITIVE).log(params.base)
+                            + params.log_side_offset
+                    }
+                    LogStyle::LogToLin => {
+                        let exponent = (value - params.log_side_offset) / params.log_side_slope;
+                        (params.base.powf(exponent) - params.lin_side_offset)
+                            / params.lin_side_slope
+                    }
+                };
+                Vec3::new(log(color.x), log(color.y), log(color.z))
+            }
+            ProcessNode::Exponent {
+                style,
+                exponent,
+                offset,
+            } => Vec3::new(
+                style.apply(color.x, exponent.x, offset.x),
+                style.apply(color.y, exponent.y, offset.y),
+                style.apply(color.z, exponent.z, offset.z),
+            ),
+            ProcessNode::Lut1d(lut) => lut.apply(color),
+            ProcessNode::Lut3d { lut, interpolation } => lut.apply_with(color, *interpolation),
+        }
+    }
+}
+
+/// A CLF process list.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProcessList {
+    pub id: String,
+    pub name: Option<String>,
+    pub descriptions: Vec<String>,
+    pub nodes: Vec<ProcessNode>,
+}
+
+impl ProcessList {
+    /// Expresses `conversion` as a process list, or returns
+    /// [`ClfError::UnsupportedTransform`] if either of its transform functions
+    /// can't be expressed with CLF process nodes.
+    pub fn from_conversion(conversion: &ColorConversion, id: &str) -> Result<Self, ClfError> {
+        let mut nodes = decode_nodes(conversion.src_transform_fn())?;
+        let matrix = conversion.linear_part().matrix();
+        if matrix != Mat3::IDENTITY {
+            nodes.push(ProcessNode::Matrix {
+                matrix,
+                offset: Vec3::splat(0.0),
+            });
+        }
+        nodes.extend(encode_nodes(conversion.dst_transform_fn())?);
+        Ok(Self {
+            id: id.to_string(),
+            nodes,
+            ..Default::default()
+        })
+    }
+
+    /// Applies every node of the process list to a color in order.
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        self.nodes
+            .iter()
+            .fold(color, |color, node| node.apply(color))
+    }
+
+    /// Writes the process list as a CLF v3 document, with 32-bit float bit
+    /// depths.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        write!(
+            xml,
+            "<ProcessList compCLFversion=\"3.0\" id=\"{}\"",
+            escape(&self.id)
+        )
+        .unwrap();
+        if let Some(name) = &self.name {
+            write!(xml, " name=\"{}\"", escape(name)).unwrap();
+        }
+        xml.push_str(">\n");
+        for description in &self.descriptions {
+            writeln!(
+                xml,
+                "    <Description>{}</Description>",
+                escape(description)
+            )
+            .unwrap();
+        }
+        for node in &self.nodes {
+            write_node(&mut xml, node);
+        }
+        xml.push_str("</ProcessList>\n");
+        xml
+    }
+
+    /// Parses a CLF document. Only the `Matrix`, `Range`, `Log`, `Exponent`,
+    /// `LUT1D` and `LUT3D` nodes are supported, and other nodes return
+    /// [`ClfError::UnsupportedNode`].
+    pub fn parse(xml: &str) -> Result<Self, ClfError> {
+        let root = XmlParser::parse(xml).ok_or(ClfError::InvalidXml)?;
+        if root.name != "ProcessList" {
+            return Err(ClfError::InvalidXml);
+        }
+        let mut list = ProcessList {
+            id: root.attribute("id").unwrap_or_default().to_string(),
+            name: root.attribute("name").map(str::to_string),
+            ..Default::default()
+        };
+        for element in &root.children {
+            match element.name.as_str() {
+                "Description" => list.descriptions.push(element.text.clone()),
+                "InputDescriptor" | "OutputDescriptor" | "Info" => {}
+                _ => list.nodes.push(parse_node(element)?),
+            }
+        }
+        Ok(list)
+    }
+}
+
+/// Returns the nodes converting from `transform` to its linear reference.
+fn decode_nodes(transform: TransformFn) -> Result<Vec<ProcessNode>, ClfError> {
+    Ok(match transform {
+        TransformFn::None => vec![],
+        TransformFn::Srgb => vec![mon_curve(ExponentStyle::MonCurveFwd, 2.4, 0.055)],
+        TransformFn::Bt601 => vec![mon_curve(ExponentStyle::MonCurveFwd, 1.0 / 0.45, 0.0993)],
+        TransformFn::Pq => {
+            // The rational part of the PQ EOTF, followed by its exponent and
+            // scale, so that the LUT only needs to cover a smooth curve.
+            let table = sample_lut1d(|value| {
+                let v = value.max(0.0).powf(pq::M_2_d);
+                ((v - pq::C_1).max(0.0) / (pq::C_2 - pq::C_3 * v)).max(0.0)
+            });
+            vec![
+                ProcessNode::Lut1d(table),
+                ProcessNode::Exponent {
+                    style: ExponentStyle::BasicFwd,
+                    exponent: Vec3::splat(pq::M_1_d),
+                    offset: Vec3::splat(0.0),
+                },
+                ProcessNode::Range {
+                    min: Some((0.0, 0.0)),
+                    max: Some((1.0, pq::L_p)),
+                    clamp: false,
+                },
+            ]
+        }
+        transform => return Err(ClfError::UnsupportedTransform(transform)),
+    })
+}
+
+/// Returns the nodes converting from the linear reference of `transform` to
+/// the transform.
+fn encode_nodes(transform: TransformFn) -> Result<Vec<ProcessNode>, ClfError> {
+    Ok(match transform {
+        TransformFn::None => vec![],
+        TransformFn::Srgb => vec![mon_curve(ExponentStyle::MonCurveRev, 2.4, 0.055)],
+        TransformFn::Bt601 => vec![mon_curve(ExponentStyle::MonCurveRev, 1.0 / 0.45, 0.0993)],
+        TransformFn::Pq => {
+            let table = sample_lut1d(|value| {
+                ((pq::C_1 + pq::C_2 * value) / (1.0 + pq::C_3 * value)).powf(pq::M_2)
+            });
+            vec![
+                ProcessNode::Range {
+                    min: Some((0.0, 0.0)),
+                    max: Some((pq::L_p, 1.0)),
+                    clamp: false,
+                },
+                ProcessNode::Exponent {
+                    style: ExponentStyle::BasicFwd,
+                    exponent: Vec3::splat(pq::M_1),
+                    offset: Vec3::splat(0.0),
+                },
+                ProcessNode::Lut1d(table),
+            ]
+        }
+        transform => return Err(ClfError::UnsupportedTransform(transform)),
+    })
+}
+
+fn mon_curve(style: ExponentStyle, exponent: Float, offset: Float) -> ProcessNode {
+    ProcessNode::Exponent {
+        style,
+        exponent: Vec3::splat(exponent),
+        offset: Vec3::splat(offset),
+    }
+}
+
+fn sample_lut1d(f: impl Fn(Float) -> Float) -> Lut1d {
+    let last = (PQ_LUT_SIZE - 1) as Float;
+    let table = (0..PQ_LUT_SIZE)
+        .map(|i| Vec3::splat(f(i as Float / last)))
+        .collect();
+    Lut1d::new(Vec3::splat(0.0), Vec3::splat(1.0), table)
+}
+
+fn write_node(xml: &mut String, node: &ProcessNode) {
+    const DEPTHS: &str = "inBitDepth=\"32f\" outBitDepth=\"32f\"";
+    match node {
+        ProcessNode::Matrix { matrix, offset } => {
+            let has_offset = *offset != Vec3::splat(0.0);
+            writeln!(xml, "    <Matrix {}>", DEPTHS).unwrap();
+            let dim = if has_offset { "3 4" } else { "3 3" };
+            writeln!(xml, "        <Array dim=\"{}\">", dim).unwrap();
+            let rows = [
+                (matrix.x_axis.x, matrix.y_axis.x, matrix.z_axis.x, offset.x),
+                (matrix.x_axis.y, matrix.y_axis.y, matrix.z_axis.y, offset.y),
+                (matrix.x_axis.z, matrix.y_axis.z, matrix.z_axis.z, offset.z),
+            ];
+            for (a, b, c, offset) in rows {
+                write!(xml, "{} {} {}", a, b, c).unwrap();
+                if has_offset {
+                    write!(xml, " {}", offset).unwrap();
+                }
+                xml.push('\n');
+            }
+            xml.push_str("        </Array>\n    </Matrix>\n");
+        }
+        ProcessNode::Range { min, max, clamp } => {
+            let style = if *clamp { "" } else { " style=\"noClamp\"" };
+            writeln!(xml, "    <Range {}{}>", DEPTHS, style).unwrap();
+            if let Some((input, _)) = min {
+                writeln!(xml, "        <minInValue>{}</minInValue>", input).unwrap();
+            }
+            if let Some((input, _)) = max {
+                writeln!(xml, "        <maxInValue>{}</maxInValue>", input).unwrap();
+            }
+            if let Some((_, output)) = min {
+                writeln!(xml, "        <minOutValue>{}</minOutValue>", output).unwrap();
+            }
+            if let Some((_, output)) = max {
+                writeln!(xml, "        <maxOutValue>{}</maxOutValue>", output).unwrap();
+            }
+            xml.push_str("    </Range>\n");
+        }
+        ProcessNode::Log { style, params } => {
+            let name = LogStyle::NAMES.iter().find(|(s, _)| s == style).unwrap().1;
+            writeln!(xml, "    <Log {} style=\"{}\">", DEPTHS, name).unwrap();
+            if matches!(style, LogStyle::LinToLog | LogStyle::LogToLin) {
+                writeln!(
+                    xml,
+                    "        <LogParams base=\"{}\" logSideSlope=\"{}\" logSideOffset=\"{}\" linSideSlope=\"{}\" linSideOffset=\"{}\"/>",
+                    params.base,
+                    params.log_side_slope,
+                    params.log_side_offset,
+                    params.lin_side_slope,
+                    params.lin_side_offset
+                )
+                .unwrap();
+            }
+            xml.push_str("    </Log>\n");
+        }
+        ProcessNode::Exponent {
+            style,
+            exponent,
+            offset,
+        } => {
+            let name = ExponentStyle::NAMES
+                .iter()
+                .find(|(s, _)| s == style)
+                .unwrap()
+                .1;
+            writeln!(xml, "    <Exponent {} style=\"{}\">", DEPTHS, name).unwrap();
+            let mon_curve = matches!(
+                style,
+                ExponentStyle::MonCurveFwd
+                    | ExponentStyle::MonCurveRev
+                    | ExponentStyle::MonCurveMirrorFwd
+                    | ExponentStyle::MonCurveMirrorRev
+            );
+            let channels = [
+                ("R", exponent.x, offset.x),
+                ("G", exponent.y, offset.y),
+                ("B", exponent.z, offset.z),
+            ];
+            let uniform = exponent.x == exponent.y
+                && exponent.y == exponent.z
+                && offset.x == offset.y
+                && offset.y == offset.z;
+            let channels = if uniform {
+                &channels[..1]
+            } else {
+                &channels[..]
+            };
+            for &(channel, exponent, offset) in channels {
+                xml.push_str("        <ExponentParams");
+                if !uniform {
+                    write!(xml, " channel=\"{}\"", channel).unwrap();
+                }
+                write!(xml, " exponent=\"{}\"", exponent).unwrap();
+                if mon_curve {
+                    write!(xml, " offset=\"{}\"", offset).unwrap();
+                }
+                xml.push_str("/>\n");
+            }
+            xml.push_str("    </Exponent>\n");
+        }
+        ProcessNode::Lut1d(lut) => {
+            write_domain(xml, lut.domain_min(), lut.domain_max());
+            writeln!(xml, "    <LUT1D {}>", DEPTHS).unwrap();
+            writeln!(xml, "        <Array dim=\"{} 3\">", lut.table().len()).unwrap();
+            for entry in lut.table() {
+                writeln!(xml, "{} {} {}", entry.x, entry.y, entry.z).unwrap();
+            }
+            xml.push_str("        </Array>\n    </LUT1D>\n");
+        }
+        ProcessNode::Lut3d { lut, interpolation } => {
+            match lut.shaper() {
+                Shaper::Linear { min, max } => write_domain(xml, *min, *max),
+                Shaper::Log2 { min, max } => {
+                    let (log_min, log_max) = (min.log2(), max.log2());
+                    write_node(
+                        xml,
+                        &ProcessNode::Log {
+                            style: LogStyle::LinToLog,
+                            params: LogParams {
+                                log_side_slope: 1.0 / (log_max - log_min),
+                                log_side_offset: -log_min / (log_max - log_min),
+                                ..Default::default()
+                            },
+                        },
+                    );
+                }
+                Shaper::Lut1d(shaper) => write_node(xml, &ProcessNode::Lut1d(shaper.clone())),
+            }
+            let interpolation = match interpolation {
+                Interpolation::Trilinear => "trilinear",
+                Interpolation::Tetrahedral => "tetrahedral",
+            };
+            writeln!(
+                xml,
+                "    <LUT3D {} interpolation=\"{}\">",
+                DEPTHS, interpolation
+            )
+            .unwrap();
+            let size = lut.size();
+            writeln!(xml, "        <Array dim=\"{0} {0} {0} 3\">", size).unwrap();
+            // CLF orders 3D LUTs with blue changing fastest.
+            for r in 0..size {
+                for g in 0..size {
+                    for b in 0..size {
+                        let entry = lut.get(r, g, b);
+                        writeln!(xml, "{} {} {}", entry.x, entry.y, entry.z).unwrap();
+                    }
+                }
+            }
+            xml.push_str("        </Array>\n    </LUT3D>\n");
+        }
+    }
+}
+
+/// Writes a `Matrix` node mapping `min..=max` to `0.0..=1.0`, the domain of
+/// CLF LUTs, unless it's already the domain.
+fn write_domain(xml: &mut String, min: Vec3, max: Vec3) {
+    if min == Vec3::splat(0.0) && max == Vec3::splat(1.0) {
+        return;
+    }
+    let scale = Vec3::new(
+        1.0 / (max.x - min.x),
+        1.0 / (max.y - min.y),
+        1.0 / (max.z - min.z),
+    );
+    let matrix = Mat3::from_cols_array(&[scale.x, 0.0, 0.0, 0.0, scale.y, 0.0, 0.0, 0.0, scale.z]);
+    let offset = Vec3::splat(0.0) - min * scale;
+    write_node(xml, &ProcessNode::Matrix { matrix, offset });
+}
+
+/// Returns the largest code value of a CLF bit depth.
+fn bit_depth_scale(depth: &str) -> Option<Float> {
+    Some(match depth {
+        "8i" => 255.0,
+        "10i" => 1023.0,
+        "12i" => 4095.0,
+        "16i" => 65535.0,
+        "16f" | "32f" => 1.0,
+        _ => return None,
+    })
+}
+
+fn parse_node(element: &Element) -> Result<ProcessNode, ClfError> {
+    let name = element.name.as_str();
+    let invalid = || ClfError::InvalidNode(name.to_string());
+    let depth = |attribute: &str| {
+        element
+            .attribute(attribute)
+            .map_or(Some(1.0), bit_depth_scale)
+            .ok_or_else(invalid)
+    };
+    let (in_scale, out_scale) = (depth("inBitDepth")?, depth("outBitDepth")?);
+    let array = || -> Result<(Vec<usize>, Vec<Float>), ClfError> {
+        let array = element.child("Array").ok_or_else(invalid)?;
+        let dim = array
+            .attribute("dim")
+            .ok_or_else(invalid)?
+            .split_whitespace()
+            .map(|value| value.parse::<usize>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = parse_floats(&array.text).ok_or_else(invalid)?;
+        let len = dim
+            .iter()
+            .try_fold(1usize, |len, &size| len.checked_mul(size))
+            .ok_or_else(invalid)?;
+        if values.len() != len {
+            return Err(invalid());
+        }
+        Ok((dim, values))
+    };
+    let float_child = |child: &str| -> Result<Option<Float>, ClfError> {
+        element
+            .child(child)
+            .map(|child| child.text.parse::<Float>().map_err(|_| invalid()))
+            .transpose()
+    };
+    let float_attribute = |element: &Element, attribute: &str| -> Result<Option<Float>, ClfError> {
+        element
+            .attribute(attribute)
+            .map(|value| value.parse::<Float>().map_err(|_| invalid()))
+            .transpose()
+    };
+    let style = element.attribute("style");
+
+    Ok(match name {
+        "Matrix" => {
+            let (dim, values) = array()?;
+            let columns = match dim.as_slice() {
+                [3, 3] | [3, 3, 3] => 3,
+                [3, 4] | [3, 4, 3] => 4,
+                _ => return Err(invalid()),
+            };
+            let row = |r: usize| Vec3::from_slice(&values[r * columns..]) * (in_scale / out_scale);
+            let (r0, r1, r2) = (row(0), row(1), row(2));
+            let matrix =
+                Mat3::from_cols_array(&[r0.x, r1.x, r2.x, r0.y, r1.y, r2.y, r0.z, r1.z, r2.z]);
+            let offset = if columns == 4 {
+                Vec3::new(values[3], values[7], values[11]) / out_scale
+            } else {
+                Vec3::splat(0.0)
+            };
+            ProcessNode::Matrix { matrix, offset }
+        }
+        "Range" => {
+            let bound = |input: &str, output: &str| -> Result<_, ClfError> {
+                match (float_child(input)?, float_child(output)?) {
+                    (Some(input), Some(output)) => Ok(Some((input / in_scale, output / out_scale))),
+                    (None, None) => Ok(None),
+                    _ => Err(invalid()),
+                }
+            };
+            ProcessNode::Range {
+                min: bound("minInValue", "minOutValue")?,
+                max: bound("maxInValue", "maxOutValue")?,
+                clamp: style != Some("noClamp"),
+            }
+        }
+        "Log" => {
+            let style = LogStyle::NAMES
+                .iter()
+                .find(|(_, name)| Some(*name) == style)
+                .ok_or_else(invalid)?
+                .0;
+            let mut params = LogParams::default();
+            if let Some(element) = element.child("LogParams") {
+                if element.attribute("channel").is_some() {
+                    return Err(ClfError::UnsupportedNode(
+                        "Log with per-channel parameters".into(),
+                    ));
+                }
+                let fields = [
+                    ("base", &mut params.base),
+                    ("logSideSlope", &mut params.log_side_slope),
+                    ("logSideOffset", &mut params.log_side_offset),
+                    ("linSideSlope", &mut params.lin_side_slope),
+                    ("linSideOffset", &mut params.lin_side_offset),
+                ];
+                for (attribute, field) in fields {
+                    if let Some(value) = float_attribute(element, attribute)? {
+                        *field = value;
+                    }
+                }
+            }
+            ProcessNode::Log { style, params }
+        }
+        "Exponent" => {
+            let style = ExponentStyle::NAMES
+                .iter()
+                .find(|(_, name)| Some(*name) == style)
+                .ok_or_else(invalid)?
+                .0;
+            let mut exponent = Vec3::splat(1.0);
+            let mut offset = Vec3::splat(0.0);
+            let mut found = false;
+            for params in element
+                .children
+                .iter()
+                .filter(|e| e.name == "ExponentParams")
+            {
+                let value = float_attribute(params, "exponent")?.ok_or_else(invalid)?;
+                let value_offset = float_attribute(params, "offset")?.unwrap_or(0.0);
+                match params.attribute("channel") {
+                    None => {
+                        exponent = Vec3::splat(value);
+                        offset = Vec3::splat(value_offset);
+                    }
+                    Some("R") => (exponent.x, offset.x) = (value, value_offset),
+                    Some("G") => (exponent.y, offset.y) = (value, value_offset),
+                    Some("B") => (exponent.z, offset.z) = (value, value_offset),
+                    Some(_) => return Err(invalid()),
+                }
+                found = true;
+            }
+            if !found {
+                return Err(invalid());
+            }
+            ProcessNode::Exponent {
+                style,
+                exponent,
+                offset,
+            }
+        }
+        "LUT1D" => {
+            if element.attribute("halfDomain").is_some() || element.attribute("rawHalfs").is_some()
+            {
+                return Err(ClfError::UnsupportedNode("LUT1D with half domain".into()));
+            }
+            let (dim, values) = array()?;
+            let table = match dim.as_slice() {
+                [size, 1] if *size >= 2 => values.iter().map(|&v| Vec3::splat(v)).collect(),
+                [size, 3] if *size >= 2 => values.chunks_exact(3).map(Vec3::from_slice).collect(),
+                _ => return Err(invalid()),
+            };
+            let table = scale_table(table, out_scale);
+            ProcessNode::Lut1d(Lut1d::new(Vec3::splat(0.0), Vec3::splat(1.0), table))
+        }
+        "LUT3D" => {
+            let interpolation = match element.attribute("interpolation") {
+                None | Some("trilinear") => Interpolation::Trilinear,
+                Some("tetrahedral") => Interpolation::Tetrahedral,
+                Some(_) => return Err(invalid()),
+            };
+            let (dim, values) = array()?;
+            let size = match dim.as_slice() {
+                [r, g, b, 3] if r == g && g == b && *r >= 2 => *r,
+                _ => return Err(invalid()),
+            };
+            let entries = values
+                .chunks_exact(3)
+                .map(Vec3::from_slice)
+                .collect::<Vec<_>>();
+            let mut table = Vec::with_capacity(entries.len());
+            for b in 0..size {
+                for g in 0..size {
+                    for r in 0..size {
+                        table.push(entries[(r * size + g) * size + b]);
+                    }
+                }
+            }
+            let lut = Lut3d::new(size, Shaper::default(), scale_table(table, out_scale));
+            ProcessNode::Lut3d { lut, interpolation }
+        }
+        _ => return Err(ClfError::UnsupportedNode(name.to_string())),
+    })
+}
+
+fn scale_table(table: Vec<Vec3>, scale: Float) -> Vec<Vec3> {
+    table.into_iter().map(|entry| entry / scale).collect()
+}
+
+fn parse_floats(text: &str) -> Option<Vec<Float>> {
+    text.split_whitespace()
+        .map(|value| value.parse().ok())
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// An XML element, with the text of all of its direct text content.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+/// The deepest nesting of elements accepted by [`XmlParser`]. CLF documents
+/// only nest a few levels, and the limit keeps hostile documents from
+/// overflowing the stack.
+const MAX_XML_DEPTH: usize = 32;
+
+/// A minimal XML parser for CLF documents, which ignores processing
+/// instructions, comments and doctypes.
+struct XmlParser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> XmlParser<'a> {
+    fn parse(xml: &'a str) -> Option<Element> {
+        let mut parser = XmlParser { rest: xml };
+        parser.skip_misc()?;
+        let root = parser.element(0)?;
+        parser.skip_misc()?;
+        parser.rest.is_empty().then_some(root)
+    }
+
+    fn skip_past(&mut self, end: &str) -> Option<()> {
+        let index = self.rest.find(end)?;
+        self.rest = &self.rest[index + end.len()..];
+        Some(())
+    }
+
+    /// Skips whitespace, processing instructions, comments and doctypes.
+    fn skip_misc(&mut self) -> Option<()> {
+        loop {
+            self.rest = self.rest.trim_start();
+            if self.rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Some(());
+            }
+        }
+    }
+
+    fn element(&mut self, depth: usize) -> Option<Element> {
+        if depth >= MAX_XML_DEPTH {
+            return None;
+        }
+        self.rest = self.rest.strip_prefix('<')?;
+        let name_end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+        let mut element = Element {
+            name: self.rest[..name_end].to_string(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new(),
+        };
+        self.rest = &self.rest[name_end..];
+        loop {
+            self.rest = self.rest.trim_start();
+            if let Some(rest) = self.rest.strip_prefix("/>") {
+                self.rest = rest;
+                return Some(element);
+            }
+            if let Some(rest) = self.rest.strip_prefix('>') {
+                self.rest = rest;
+                break;
+            }
+            let equals = self.rest.find('=')?;
+            let key = self.rest[..equals].trim().to_string();
+            self.rest = self.rest[equals + 1..].trim_start();
+            let quote = self
+                .rest
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')?;
+            let end = self.rest[1..].find(quote)? + 1;
+            element.attributes.push((key, unescape(&self.rest[1..end])));
+            self.rest = &self.rest[end + 1..];
+        }
+        loop {
+            let next = self.rest.find('<')?;
+            element.text.push_str(&unescape(&self.rest[..next]));
+            self.rest = &self.rest[next..];
+            if let Some(rest) = self.rest.strip_prefix("</") {
+                let end = rest.find('>')?;
+                if rest[..end].trim() != element.name {
+                    return None;
+                }
+                self.rest = &rest[end + 1..];
+                element.text = element.text.trim().to_string();
+                return Some(element);
+            } else if let Some(rest) = self.rest.strip_prefix("<![CDATA[") {
+                let end = rest.find("]]>")?;
+                element.text.push_str(&rest[..end]);
+                self.rest = &rest[end + 3..];
+            } else if self.rest.starts_with("<!--") || self.rest.starts_with("<?") {
+                self.skip_misc()?;
+            } else {
+                element.children.push(self.element(depth + 1)?);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    #[test]
+    fn conversion_round_trip() {
+        let colors = [
+            Vec3::new(0.0, 0.5, 1.0),
+            Vec3::new(0.02, 0.18, 0.9),
+            Vec3::new(0.003, 0.7, 0.3),
+        ];
+        let pairs = [
+            (spaces::ENCODED_SRGB, spaces::ACES_CG),
+            (spaces::ACES_2065_1, spaces::ENCODED_SRGB),
+            (spaces::ENCODED_BT_709, spaces::LINEAR_SRGB),
+        ];
+        for (src, dst) in pairs {
+            let conversion = ColorConversion::new(src, dst);
+            let list = ProcessList::from_conversion(&conversion, "test").unwrap();
+            let parsed = ProcessList::parse(&list.to_xml()).unwrap();
+            assert_eq!(parsed.nodes.len(), list.nodes.len());
+            for color in colors {
+                let expected = conversion.convert(color);
+                // MonCurve approximates the linear segments of the transfer
+                // functions, which matters for out of gamut colors.
+                let magnitude = expected.x.abs().max(expected.y.abs()).max(expected.z.abs());
+                assert!(list
+                    .apply(color)
+                    .abs_diff_eq(expected, 2e-3 * magnitude.max(1.0)));
+                assert!(parsed.apply(color).abs_diff_eq(list.apply(color), 1e-5));
+            }
+        }
+
+        let conversion = ColorConversion::new(spaces::BT_2020, spaces::ENCODED_BT_2100_PQ);
+        let list = ProcessList::from_conversion(&conversion, "pq").unwrap();
+        let inverse = ProcessList::from_conversion(&conversion.invert(), "pq").unwrap();
+        for nits in [0.1, 1.0, 100.0, 1000.0, 10000.0] {
+            let color = Vec3::new(nits, nits * 0.5, nits * 0.1);
+            let expected = conversion.convert(color);
+            assert!(list.apply(color).abs_diff_eq(expected, 1e-4));
+            let round_trip = inverse.apply(list.apply(color));
+            assert!((round_trip.x - color.x).abs() <= color.x * 2e-3);
+        }
+
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::OK_LAB);
+        assert_eq!(
+            ProcessList::from_conversion(&conversion, "oklab"),
+            Err(ClfError::UnsupportedTransform(TransformFn::OkLab))
+        );
+    }
+
+    #[test]
+    fn lut_round_trip() {
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::OK_LAB);
+        let shapers = [
+            Shaper::Linear {
+                min: Vec3::splat(-0.5),
+                max: Vec3::new(1.0, 1.5, 2.0),
+            },
+            Shaper::Log2 {
+                min: 1.0 / 64.0,
+                max: 8.0,
+            },
+        ];
+        for shaper in shapers {
+            let lut = Lut3d::bake(&conversion, 5, shaper);
+            let list = ProcessList {
+                id: "lut".into(),
+                nodes: vec![ProcessNode::Lut3d {
+                    lut,
+                    interpolation: Interpolation::Tetrahedral,
+                }],
+                ..Default::default()
+            };
+            let parsed = ProcessList::parse(&list.to_xml()).unwrap();
+            for color in [Vec3::new(0.1, 0.5, 0.9), Vec3::new(1.0, 0.03, 0.2)] {
+                assert!(parsed.apply(color).abs_diff_eq(list.apply(color), 1e-4));
+            }
+        }
+    }
+
+    #[test]
+    fn parse_process_list() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- A hand written test -->
+<ProcessList compCLFversion="3.0" id="1" name="Test &amp; check">
+    <Description>Scales, logs and looks up</Description>
+    <Range inBitDepth="10i" outBitDepth="32f">
+        <minInValue>0</minInValue>
+        <maxInValue>1023</maxInValue>
+        <minOutValue>0</minOutValue>
+        <maxOutValue>2</maxOutValue>
+    </Range>
+    <Matrix inBitDepth="32f" outBitDepth="32f">
+        <Array dim="3 4">
+            0.5 0 0 0.5
+            0 1 0 0
+            0 0 1 0
+        </Array>
+    </Matrix>
+    <Log inBitDepth="32f" outBitDepth="32f" style="linToLog">
+        <LogParams base="10" logSideSlope="0.5" logSideOffset="1" linSideSlope="1" linSideOffset="0"/>
+    </Log>
+    <Exponent inBitDepth="32f" outBitDepth="32f" style="basicFwd">
+        <ExponentParams exponent="2"/>
+        <ExponentParams channel="B" exponent="1"/>
+    </Exponent>
+    <LUT1D inBitDepth="32f" outBitDepth="12i">
+        <Array dim="2 1">
+            0
+            4095
+        </Array>
+    </LUT1D>
+    <LUT3D inBitDepth="32f" outBitDepth="32f" interpolation="tetrahedral">
+        <Array dim="2 2 2 3">
+            0 0 0
+            0 0 1
+            0 1 0
+            0 1 1
+            1 0 0
+            1 0 1
+            1 1 0
+            1 1 1
+        </Array>
+    </LUT3D>
+</ProcessList>
+"#;
+        let list = ProcessList::parse(xml).unwrap();
+        assert_eq!(list.name.as_deref(), Some("Test & check"));
+        assert_eq!(list.descriptions, ["Scales, logs and looks up"]);
+        assert_eq!(list.nodes.len(), 6);
+
+        // The Range maps 0.05 to 0.1 and the Matrix maps red to 0.55, and the
+        // LUTs are identities.
+        let log = |value: Float| 0.5 * value.log10() + 1.0;
+        let expected = Vec3::new(log(0.55).powi(2), log(0.1).powi(2), log(0.1));
+        let result = list.apply(Vec3::splat(0.05));
+        assert!(result.abs_diff_eq(expected, 1e-5), "{:?}", result);
+        assert!(list
+            .apply(Vec3::splat(0.5))
+            .abs_diff_eq(Vec3::splat(1.0), 1e-5));
+
+        assert_eq!(
+            ProcessList::parse(&xml.replace("Range", "ASC_CDL")),
+            Err(ClfError::UnsupportedNode("ASC_CDL".into()))
+        );
+        assert_eq!(
+            ProcessList::parse(&xml.replace("dim=\"2 1\"", "dim=\"3 1\"")),
+            Err(ClfError::InvalidNode("LUT1D".into()))
+        );
+        assert_eq!(
+            ProcessList::parse(&xml.replace("dim=\"2 1\"", "dim=\"4294967296 4294967296 3\"")),
+            Err(ClfError::InvalidNode("LUT1D".into()))
+        );
+        assert_eq!(
+            ProcessList::parse("<ProcessList>"),
+            Err(ClfError::InvalidXml)
+        );
+        let nested = "<a>".repeat(100_000) + &"</a>".repeat(100_000);
+        assert_eq!(ProcessList::parse(&nested), Err(ClfError::InvalidXml));
+    }
+
+    #[test]
+    fn exponent_styles() {
+        // MonCurve with the sRGB parameters closely matches the sRGB EOTF.
+        for value in [0.0, 0.01, 0.04, 0.2, 0.5, 1.0] {
+            let linear = mon_curve_fwd(value, 2.4, 0.055);
+            let expected = crate::details::transform::srgb_eotf(
+                Vec3::splat(value),
+                crate::details::color::WhitePoint::D65,
+            )
+            .x;
+            assert!((linear - expected).abs() < 1e-4);
+            assert!((mon_curve_rev(linear, 2.4, 0.055) - value).abs() < 1e-5);
+        }
+        let mirror = ExponentStyle::BasicMirrorFwd.apply(-0.5, 2.0, 0.0);
+        assert_eq!(mirror, -0.25);
+        assert_eq!(ExponentStyle::BasicFwd.apply(-0.5, 2.0, 0.0), 0.0);
+        assert_eq!(ExponentStyle::BasicPassThruRev.apply(-0.5, 2.0, 0.0), -0.5);
+    }
+}
@@ -1,4 +1,7 @@
-use super::{conversion::ColorConversion, transform::ColorTransform};
+use super::{
+    conversion::{ColorConversion, ConversionError},
+    transform::ColorTransform,
+};
 use crate::{Float, Vec3};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -55,6 +58,12 @@ pub enum TransformFn {
 
 impl TransformFn {
     pub const ENUM_COUNT: TransformFn = TransformFn::Pq;
+
+    /// Whether the transform function and its inverse are implemented.
+    /// Converting to or from a space with an unimplemented transform panics.
+    pub fn is_implemented(&self) -> bool {
+        !matches!(self, TransformFn::Cie1964Uvw)
+    }
 }
 
 /// A set of primary colors picked to define an RGB color space.
@@ -232,8 +241,8 @@ impl ColorSpace {
     }
 
     /// Whether the color space has a non-linear transform applied
-    pub fn is_linear(&self) -> bool {
-        self.transform_fn == TransformFn::None
+    pub const fn is_linear(&self) -> bool {
+        matches!(self.transform_fn, TransformFn::None)
     }
 
     pub fn as_linear(&self) -> Self {
@@ -244,15 +253,15 @@ impl ColorSpace {
         }
     }
 
-    pub fn primaries(&self) -> RgbPrimaries {
+    pub const fn primaries(&self) -> RgbPrimaries {
         self.primaries
     }
 
-    pub fn white_point(&self) -> WhitePoint {
+    pub const fn white_point(&self) -> WhitePoint {
         self.white_point
     }
 
-    pub fn transform_function(&self) -> TransformFn {
+    pub const fn transform_function(&self) -> TransformFn {
         self.transform_fn
     }
 
@@ -519,6 +528,16 @@ impl Color {
         }
     }
 
+    /// Returns a `Color` converted into the provided [`ColorSpace`], or a
+    /// [`ConversionError`] if either space is invalid.
+    pub fn try_to(&self, space: ColorSpace) -> Result<Color, ConversionError> {
+        let conversion = ColorConversion::try_new(self.space, space)?;
+        Ok(Color {
+            space,
+            value: conversion.convert(self.value),
+        })
+    }
+
     pub fn to_linear(&self) -> Color {
         if self.space.is_linear() {
             *self
@@ -537,12 +556,138 @@ impl Color {
             }
         }
     }
+
+    /// Returns the color in the linear version of its color space, or a
+    /// [`ConversionError`] if the transform function isn't implemented.
+    pub fn try_to_linear(&self) -> Result<Color, ConversionError> {
+        let transform_fn = self.space.transform_function();
+        if !transform_fn.is_implemented() {
+            return Err(ConversionError::UnimplementedTransform(transform_fn));
+        }
+        Ok(self.to_linear())
+    }
+}
+
+/// Whether the components of an [`AlphaColor`] have been multiplied by its
+/// alpha.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AlphaMode {
+    /// The color is independent of alpha, also known as unassociated alpha.
+    #[default]
+    Straight,
+    /// The color has been multiplied by alpha, also known as associated
+    /// alpha.
+    Premultiplied,
+}
+
+/// A [`Color`] with an alpha component, which is either straight or
+/// premultiplied.
+///
+/// Conversions keep the [`AlphaMode`] of the color. Premultiplied colors are
+/// un-premultiplied before non-linear transform functions are applied and
+/// premultiplied again afterwards, see
+/// [`ColorConversion::convert_premultiplied`].
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AlphaColor {
+    pub color: Color,
+    pub alpha: Float,
+    pub mode: AlphaMode,
+}
+impl AlphaColor {
+    pub const fn new(color: Color, alpha: Float, mode: AlphaMode) -> Self {
+        Self { color, alpha, mode }
+    }
+
+    /// Creates a color with straight alpha.
+    pub const fn straight(color: Color, alpha: Float) -> Self {
+        Self::new(color, alpha, AlphaMode::Straight)
+    }
+
+    /// Creates a color whose value has already been multiplied by `alpha`.
+    pub const fn premultiplied(color: Color, alpha: Float) -> Self {
+        Self::new(color, alpha, AlphaMode::Premultiplied)
+    }
+
+    pub const fn space(&self) -> ColorSpace {
+        self.color.space
+    }
+
+    /// Returns the color with straight alpha. A fully transparent
+    /// premultiplied color is returned unchanged, as its straight color is
+    /// undefined.
+    pub fn to_straight(&self) -> Self {
+        match self.mode {
+            AlphaMode::Premultiplied if self.alpha != 0.0 => Self::straight(
+                Color {
+                    value: self.color.value / self.alpha,
+                    space: self.color.space,
+                },
+                self.alpha,
+            ),
+            _ => Self::straight(self.color, self.alpha),
+        }
+    }
+
+    /// Returns the color with premultiplied alpha.
+    pub fn to_premultiplied(&self) -> Self {
+        match self.mode {
+            AlphaMode::Straight => Self::premultiplied(
+                Color {
+                    value: self.color.value * self.alpha,
+                    space: self.color.space,
+                },
+                self.alpha,
+            ),
+            AlphaMode::Premultiplied => *self,
+        }
+    }
+
+    /// Returns an `AlphaColor` converted into the provided [`ColorSpace`],
+    /// keeping its alpha and [`AlphaMode`].
+    pub fn to(&self, space: ColorSpace) -> Self {
+        self.convert_with(&ColorConversion::new(self.color.space, space))
+    }
+
+    /// Returns an `AlphaColor` converted into the provided [`ColorSpace`], or
+    /// a [`ConversionError`] if either space is invalid.
+    pub fn try_to(&self, space: ColorSpace) -> Result<Self, ConversionError> {
+        Ok(self.convert_with(&ColorConversion::try_new(self.color.space, space)?))
+    }
+
+    /// Returns the color in the linear version of its color space.
+    pub fn to_linear(&self) -> Self {
+        self.to(self.color.space.as_linear())
+    }
+
+    fn convert_with(&self, conversion: &ColorConversion) -> Self {
+        let value = match self.mode {
+            AlphaMode::Straight => conversion.convert(self.color.value),
+            AlphaMode::Premultiplied => {
+                conversion.convert_premultiplied(self.color.value, self.alpha)
+            }
+        };
+        Self::new(
+            Color {
+                value,
+                space: conversion.dst_space(),
+            },
+            self.alpha,
+            self.mode,
+        )
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod test {
     use super::*;
-    use crate::details::conversion::LinearColorConversion;
+    use crate::details::{
+        cat::LmsConeSpace,
+        conversion::{ConversionOptions, LinearColorConversion},
+    };
+    use crate::Mat3;
     use color_spaces as spaces;
     #[test]
     fn linear_srgb_to_aces_cg() {
@@ -566,7 +711,7 @@ mod test {
         let expected = Vec3::new(0.6262097, 0.34919018, 0.9063317);
         assert!(
             result.abs_diff_eq(expected, 0.001),
-            "{} != {}",
+            "{:?} != {:?}",
             result,
             expected
         );
@@ -584,6 +729,73 @@ mod test {
     //     }
     // }
 
+    #[test]
+    fn bradford_adaptation() {
+        // sRGB to XYZ D50 with Bradford adaptation, from Bruce Lindbloom.
+        let options = ConversionOptions::default().with_adaptation_method(LmsConeSpace::Bradford);
+        let xyz_d50 = spaces::CIE_XYZ.with_whitepoint(WhitePoint::D50);
+        let conversion =
+            LinearColorConversion::new_with_options(spaces::LINEAR_SRGB, xyz_d50, options);
+        let expected = Mat3::from_cols_array(&[
+            0.4360747, 0.2225045, 0.0139322, 0.3850649, 0.7168786, 0.0971045, 0.1430804, 0.0606169,
+            0.7141733,
+        ]);
+        assert!(
+            conversion.matrix().abs_diff_eq(expected, 0.0005),
+            "{:?} != {:?}",
+            conversion.matrix(),
+            expected
+        );
+
+        let sharp = LinearColorConversion::new(spaces::LINEAR_SRGB, xyz_d50);
+        assert!(!sharp.matrix().abs_diff_eq(expected, 0.0005));
+
+        let conversion = ColorConversion::new_with_options(spaces::ENCODED_SRGB, xyz_d50, options);
+        let inverse = conversion.invert();
+        assert_eq!(inverse.options(), options);
+        let color = Vec3::new(0.2, 0.5, 0.9);
+        assert!(inverse
+            .convert(conversion.convert(color))
+            .abs_diff_eq(color, 0.0001));
+    }
+
+    #[test]
+    fn conversion_errors() {
+        let error = LinearColorConversion::try_new(spaces::ENCODED_SRGB, spaces::ACES_CG);
+        assert_eq!(
+            error.unwrap_err(),
+            ConversionError::NonLinearSpace(spaces::ENCODED_SRGB)
+        );
+
+        let degenerate = ColorSpace::linear(RgbPrimaries::None, WhitePoint::D65);
+        let error = ColorConversion::try_new(degenerate, spaces::LINEAR_SRGB);
+        assert_eq!(
+            error.unwrap_err(),
+            ConversionError::DegenerateSpace(degenerate)
+        );
+        let no_white = ColorSpace::linear(RgbPrimaries::Bt709, WhitePoint::None);
+        let error = Color::new(0.5, 0.5, 0.5, spaces::LINEAR_SRGB).try_to(no_white);
+        assert_eq!(
+            error.unwrap_err(),
+            ConversionError::DegenerateSpace(no_white)
+        );
+
+        let uvw = spaces::CIE_XYZ.with_transform(TransformFn::Cie1964Uvw);
+        let color = Color::new(0.5, 0.5, 0.5, uvw);
+        assert_eq!(
+            color.try_to_linear().unwrap_err(),
+            ConversionError::UnimplementedTransform(TransformFn::Cie1964Uvw)
+        );
+        assert!(Color::srgb(0.5, 0.5, 0.5).try_to(uvw).is_err());
+
+        let color = Color::srgb(0.2, 0.4, 0.6);
+        let result = color.try_to(spaces::OK_LAB).unwrap();
+        assert!(result
+            .value
+            .abs_diff_eq(color.to(spaces::OK_LAB).value, 0.0));
+        assert!(color.try_to_linear().is_ok());
+    }
+
     #[test]
     fn aces_cg_to_srgb() {
         let conversion = ColorConversion::new(spaces::ACES_CG, spaces::ENCODED_SRGB);
@@ -591,9 +803,51 @@ mod test {
         let expected = Vec3::new(0.713855624199, 0.271821975708, 0.955197274685);
         assert!(
             result.abs_diff_eq(expected, 0.01),
-            "{} != {}",
+            "{:?} != {:?}",
             result,
             expected
         );
     }
+
+    #[test]
+    fn alpha_colors() {
+        let straight = AlphaColor::straight(Color::srgb(0.5, 0.2, 1.0), 0.5);
+        let premultiplied = straight.to_premultiplied();
+        assert_eq!(premultiplied.mode, AlphaMode::Premultiplied);
+        assert_eq!(premultiplied.color.value, Vec3::new(0.25, 0.1, 0.5));
+
+        // Premultiplied colors are un-premultiplied around the sRGB transform.
+        let linear = premultiplied.to(spaces::LINEAR_SRGB);
+        assert_eq!(linear.mode, AlphaMode::Premultiplied);
+        assert_eq!(linear.alpha, 0.5);
+        let expected = straight.color.to(spaces::LINEAR_SRGB).value * 0.5;
+        assert!(linear.color.value.abs_diff_eq(expected, 1e-6));
+        let straight_linear = straight.to_linear();
+        assert!(straight_linear
+            .to_premultiplied()
+            .color
+            .value
+            .abs_diff_eq(expected, 1e-6));
+
+        // Linear conversions apply to premultiplied colors directly.
+        let aces = linear.to(spaces::ACES_CG);
+        let expected =
+            ColorConversion::new(spaces::LINEAR_SRGB, spaces::ACES_CG).convert(linear.color.value);
+        assert!(aces.color.value.abs_diff_eq(expected, 1e-6));
+
+        let round_trip = aces.to(spaces::ENCODED_SRGB).to_straight();
+        assert_eq!(round_trip.mode, AlphaMode::Straight);
+        assert!(round_trip
+            .color
+            .value
+            .abs_diff_eq(straight.color.value, 1e-5));
+
+        let transparent = AlphaColor::premultiplied(Color::srgb(0.0, 0.0, 0.0), 0.0);
+        let converted = transparent.to(spaces::LINEAR_SRGB);
+        assert_eq!(converted.color.value, Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            transparent.to_straight().color.value,
+            Vec3::new(0.0, 0.0, 0.0)
+        );
+    }
 }
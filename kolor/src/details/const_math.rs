@@ -0,0 +1,185 @@
+//! Conversion matrix math usable in `const` context.
+//!
+//! These mirror the runtime functions in [`xyz`](super::xyz) and
+//! [`cat`](super::cat), written without operator traits so that they can be
+//! evaluated at compile time. They back
+//! [`LinearColorConversion::new_const`](super::conversion::LinearColorConversion::new_const)
+//! and the [`conversion_matrix!`](crate::conversion_matrix) macro, which turn
+//! any conversion matrix into a constant, for `no_std` targets and shader
+//! constant tables.
+use super::{
+    cat::LmsConeSpace,
+    color::{ColorSpace, RgbPrimaries},
+};
+use crate::{Float, Mat3, Vec3};
+
+const fn dot(a: Vec3, b: Vec3) -> Float {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+const fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(
+        a.y * b.z - b.y * a.z,
+        a.z * b.x - b.z * a.x,
+        a.x * b.y - b.x * a.y,
+    )
+}
+
+/// Multiplies a matrix with a vector.
+pub const fn mul_vec3(m: Mat3, v: Vec3) -> Vec3 {
+    Vec3::new(
+        m.x_axis.x * v.x + m.y_axis.x * v.y + m.z_axis.x * v.z,
+        m.x_axis.y * v.x + m.y_axis.y * v.y + m.z_axis.y * v.z,
+        m.x_axis.z * v.x + m.y_axis.z * v.y + m.z_axis.z * v.z,
+    )
+}
+
+/// Multiplies two matrices.
+pub const fn mul(a: Mat3, b: Mat3) -> Mat3 {
+    let x = mul_vec3(a, b.x_axis);
+    let y = mul_vec3(a, b.y_axis);
+    let z = mul_vec3(a, b.z_axis);
+    Mat3::from_cols_array(&[x.x, x.y, x.z, y.x, y.y, y.z, z.x, z.y, z.z])
+}
+
+/// Inverts a matrix. A singular matrix produces infinities or NaNs.
+pub const fn inverse(m: Mat3) -> Mat3 {
+    let row_x = cross(m.y_axis, m.z_axis);
+    let row_y = cross(m.z_axis, m.x_axis);
+    let row_z = cross(m.x_axis, m.y_axis);
+    let inv_det = 1.0 / dot(m.z_axis, row_z);
+    Mat3::from_cols_array(&[
+        row_x.x * inv_det,
+        row_y.x * inv_det,
+        row_z.x * inv_det,
+        row_x.y * inv_det,
+        row_y.y * inv_det,
+        row_z.y * inv_det,
+        row_x.z * inv_det,
+        row_y.z * inv_det,
+        row_z.z * inv_det,
+    ])
+}
+
+const fn diagonal(v: Vec3) -> Mat3 {
+    Mat3::from_cols_array(&[v.x, 0.0, 0.0, 0.0, v.y, 0.0, 0.0, 0.0, v.z])
+}
+
+/// Like [`xyz::rgb_to_xyz`](super::xyz::rgb_to_xyz).
+#[allow(non_snake_case)]
+pub const fn rgb_to_xyz(primaries: &[[Float; 2]; 3], white_point: &[Float; 3]) -> Mat3 {
+    let [[xr, yr], [xg, yg], [xb, yb]] = *primaries;
+    let [Wx, Wy, Wz] = *white_point;
+    let base = Mat3::from_cols_array(&[
+        xr / yr,
+        1.0,
+        (1.0 - xr - yr) / yr,
+        xg / yg,
+        1.0,
+        (1.0 - xg - yg) / yg,
+        xb / yb,
+        1.0,
+        (1.0 - xb - yb) / yb,
+    ]);
+    let scale = mul_vec3(inverse(base), Vec3::new(Wx, Wy, Wz));
+    mul(base, diagonal(scale))
+}
+
+/// Like [`xyz::xyz_to_rgb`](super::xyz::xyz_to_rgb).
+pub const fn xyz_to_rgb(primaries: &[[Float; 2]; 3], white_point: &[Float; 3]) -> Mat3 {
+    inverse(rgb_to_xyz(primaries, white_point))
+}
+
+/// Like [`LmsConeSpace::chromatic_adaptation_transform`], with complete
+/// adaptation.
+pub const fn chromatic_adaptation_transform(
+    method: LmsConeSpace,
+    src_illuminant: Vec3,
+    dst_illuminant: Vec3,
+) -> Mat3 {
+    let cone_space_transform = method.matrix();
+    let src = mul_vec3(cone_space_transform, src_illuminant);
+    let dst = mul_vec3(cone_space_transform, dst_illuminant);
+    let gains = diagonal(Vec3::new(dst.x / src.x, dst.y / src.y, dst.z / src.z));
+    mul(
+        inverse(cone_space_transform),
+        mul(gains, cone_space_transform),
+    )
+}
+
+const fn white_point_vec3(space: ColorSpace) -> Vec3 {
+    let [x, y, z] = *space.white_point().values();
+    Vec3::new(x, y, z)
+}
+
+/// The matrix converting from linear `src` to linear `dst`, adapting white
+/// points completely with `method`.
+pub const fn conversion_matrix(src: ColorSpace, dst: ColorSpace, method: LmsConeSpace) -> Mat3 {
+    let src_to_xyz = if matches!(src.primaries(), RgbPrimaries::CieXyz) {
+        Mat3::IDENTITY
+    } else {
+        rgb_to_xyz(src.primaries().values(), src.white_point().values())
+    };
+    let xyz_to_dst = if matches!(dst.primaries(), RgbPrimaries::CieXyz) {
+        Mat3::IDENTITY
+    } else {
+        xyz_to_rgb(dst.primaries().values(), dst.white_point().values())
+    };
+    if src.white_point() as u8 == dst.white_point() as u8 {
+        mul(xyz_to_dst, src_to_xyz)
+    } else {
+        let white_point_transform =
+            chromatic_adaptation_transform(method, white_point_vec3(src), white_point_vec3(dst));
+        mul(xyz_to_dst, mul(white_point_transform, src_to_xyz))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        details::{
+            color::color_spaces::ALL_COLOR_SPACES,
+            conversion::{ConversionOptions, LinearColorConversion},
+        },
+        spaces,
+    };
+
+    const SRGB_TO_ACES_CG: Mat3 = crate::conversion_matrix!(spaces::LINEAR_SRGB => spaces::ACES_CG);
+
+    #[test]
+    fn matches_runtime_matrices() {
+        let runtime = LinearColorConversion::new(spaces::LINEAR_SRGB, spaces::ACES_CG).matrix();
+        assert!(SRGB_TO_ACES_CG.abs_diff_eq(runtime, 1e-5));
+
+        let linear_spaces = ALL_COLOR_SPACES.iter().filter(|space| space.is_linear());
+        for src in linear_spaces.clone() {
+            for dst in linear_spaces.clone() {
+                for method in [
+                    LmsConeSpace::Sharp,
+                    LmsConeSpace::Bradford,
+                    LmsConeSpace::Cat16,
+                ] {
+                    let options = ConversionOptions::default().with_adaptation_method(method);
+                    let runtime = LinearColorConversion::new_with_options(*src, *dst, options);
+                    let constant =
+                        LinearColorConversion::new_const_with_adaptation_method(*src, *dst, method);
+                    assert!(
+                        constant.matrix().abs_diff_eq(runtime.matrix(), 1e-4),
+                        "{:?} -> {:?}",
+                        src,
+                        dst
+                    );
+                    assert_eq!(constant.input_space(), *src);
+                    assert_eq!(constant.output_space(), *dst);
+                }
+            }
+        }
+
+        let matrix = rgb_to_xyz(
+            spaces::DISPLAY_P3.primaries().values(),
+            spaces::DISPLAY_P3.white_point().values(),
+        );
+        assert!(mul(matrix, inverse(matrix)).abs_diff_eq(Mat3::IDENTITY, 1e-6));
+    }
+}
@@ -1,5 +1,8 @@
 use super::{
+    cat::{DegreeOfAdaptation, LmsConeSpace},
     color::{RgbPrimaries, TransformFn},
+    const_math,
+    pixel_format::PixelFormat,
     transform::ColorTransform,
     xyz::{rgb_to_xyz, xyz_to_rgb},
 };
@@ -7,6 +10,87 @@ use crate::{ColorSpace, Float, Mat3, Vec3};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// Returned when a conversion can't be created between two color spaces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// A linear color space was required.
+    NonLinearSpace(ColorSpace),
+    /// The primaries or white point of the color space don't span a valid
+    /// coordinate system, so its conversion matrix can't be inverted.
+    DegenerateSpace(ColorSpace),
+    /// The transform function of a color space isn't implemented yet.
+    UnimplementedTransform(TransformFn),
+}
+
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConversionError::NonLinearSpace(space) => {
+                write!(f, "{:?} is not a linear color space", space)
+            }
+            ConversionError::DegenerateSpace(space) => {
+                write!(f, "{:?} has degenerate primaries or white point", space)
+            }
+            ConversionError::UnimplementedTransform(transform_fn) => {
+                write!(f, "{:?} is not implemented", transform_fn)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {}
+
+/// Checks that `space` has a white point and, for RGB spaces, primaries that
+/// form an invertible matrix.
+fn validate_linear_space(space: ColorSpace) -> Result<(), ConversionError> {
+    if !space.is_linear() {
+        return Err(ConversionError::NonLinearSpace(space));
+    }
+    if space.white_point().values()[1] <= 0.0 {
+        return Err(ConversionError::DegenerateSpace(space));
+    }
+    if space.primaries() != RgbPrimaries::CieXyz {
+        let determinant =
+            rgb_to_xyz(space.primaries().values(), space.white_point().values()).determinant();
+        if !determinant.is_finite() || determinant.abs() <= Float::EPSILON {
+            return Err(ConversionError::DegenerateSpace(space));
+        }
+    }
+    Ok(())
+}
+
+/// Options controlling how a [`ColorConversion`] or [`LinearColorConversion`]
+/// is calculated.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConversionOptions {
+    /// The cone space used for chromatic adaptation when the source and
+    /// destination white points differ. Defaults to
+    /// [`Sharp`](LmsConeSpace::Sharp).
+    pub adaptation_method: LmsConeSpace,
+    /// How completely colors are adapted to the destination white point.
+    /// Defaults to [`Full`](DegreeOfAdaptation::Full).
+    ///
+    /// This is applied as a single partial von Kries step. The two-step
+    /// adaptation of
+    /// [`two_step_chromatic_adaptation_transform`](LmsConeSpace::two_step_chromatic_adaptation_transform)
+    /// isn't available through `ConversionOptions`; use its matrix directly.
+    pub degree_of_adaptation: DegreeOfAdaptation,
+}
+
+impl ConversionOptions {
+    pub fn with_adaptation_method(mut self, adaptation_method: LmsConeSpace) -> Self {
+        self.adaptation_method = adaptation_method;
+        self
+    }
+
+    pub fn with_degree_of_adaptation(mut self, degree_of_adaptation: DegreeOfAdaptation) -> Self {
+        self.degree_of_adaptation = degree_of_adaptation;
+        self
+    }
+}
+
 /// A transformation from one linear color space to another.
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -17,11 +101,11 @@ pub struct LinearColorConversion {
 }
 
 impl LinearColorConversion {
-    pub fn input_space(&self) -> ColorSpace {
+    pub const fn input_space(&self) -> ColorSpace {
         self.input_space
     }
 
-    pub fn output_space(&self) -> ColorSpace {
+    pub const fn output_space(&self) -> ColorSpace {
         self.output_space
     }
 
@@ -29,11 +113,44 @@ impl LinearColorConversion {
         self.mat * color
     }
 
-    pub fn matrix(&self) -> Mat3 {
+    pub const fn matrix(&self) -> Mat3 {
         self.mat
     }
 
     pub fn new(src: ColorSpace, dst: ColorSpace) -> Self {
+        Self::new_with_options(src, dst, ConversionOptions::default())
+    }
+
+    /// Like [`new`](Self::new), but returns a [`ConversionError`] instead of
+    /// panicking if either space isn't linear, or producing a matrix of NaNs
+    /// if either space is degenerate.
+    pub fn try_new(src: ColorSpace, dst: ColorSpace) -> Result<Self, ConversionError> {
+        Self::try_new_with_options(src, dst, ConversionOptions::default())
+    }
+
+    /// Like [`new_with_options`](Self::new_with_options), but returns a
+    /// [`ConversionError`] if either space is invalid.
+    pub fn try_new_with_options(
+        src: ColorSpace,
+        dst: ColorSpace,
+        options: ConversionOptions,
+    ) -> Result<Self, ConversionError> {
+        validate_linear_space(src)?;
+        validate_linear_space(dst)?;
+        let conversion = Self::new_with_options(src, dst, options);
+        if !conversion.mat.is_finite() {
+            return Err(ConversionError::DegenerateSpace(src));
+        }
+        Ok(conversion)
+    }
+
+    /// Creates a conversion using the chromatic adaptation method and degree
+    /// of adaptation from `options`.
+    ///
+    /// The bundled matrices of the `color-matrices` feature are calculated
+    /// with the default options, so they are only used when the white points
+    /// match or the default options are requested.
+    pub fn new_with_options(src: ColorSpace, dst: ColorSpace, options: ConversionOptions) -> Self {
         if !src.is_linear() {
             panic!("{:?} is not a linear color space", src);
         }
@@ -41,12 +158,17 @@ impl LinearColorConversion {
             panic!("{:?} is not a linear color space", dst);
         }
         #[cfg(feature = "color-matrices")]
-        let const_conversion = super::generated_matrices::const_conversion_matrix(
-            src.primaries(),
-            src.white_point(),
-            dst.primaries(),
-            dst.white_point(),
-        );
+        let const_conversion =
+            if src.white_point() == dst.white_point() || options == ConversionOptions::default() {
+                super::generated_matrices::const_conversion_matrix(
+                    src.primaries(),
+                    src.white_point(),
+                    dst.primaries(),
+                    dst.white_point(),
+                )
+            } else {
+                None
+            };
         #[cfg(not(feature = "color-matrices"))]
         let const_conversion: Option<Mat3> = None;
 
@@ -64,10 +186,12 @@ impl LinearColorConversion {
                 xyz_to_rgb(dst.primaries().values(), dst.white_point().values())
             };
             if src.white_point() != dst.white_point() {
-                let white_point_transform = super::cat::LmsConeSpace::Sharp
-                    .chromatic_adaptation_transform(
+                let white_point_transform = options
+                    .adaptation_method
+                    .partial_chromatic_adaptation_transform(
                         Vec3::from_slice(src.white_point().values()),
                         Vec3::from_slice(dst.white_point().values()),
+                        options.degree_of_adaptation,
                     );
                 xyz_to_dst * white_point_transform * src_to_xyz
             } else {
@@ -80,6 +204,37 @@ impl LinearColorConversion {
             output_space: dst,
         }
     }
+
+    /// Like [`new`](Self::new), but usable in `const` context, e.g. to embed
+    /// a conversion matrix in a `static` or a shader constant table. See also
+    /// [`conversion_matrix!`](crate::conversion_matrix).
+    ///
+    /// The matrix is always calculated rather than taken from the bundled
+    /// matrices of the `color-matrices` feature, so it may differ from the
+    /// one returned by [`new`](Self::new) by a rounding error.
+    pub const fn new_const(src: ColorSpace, dst: ColorSpace) -> Self {
+        Self::new_const_with_adaptation_method(src, dst, LmsConeSpace::Sharp)
+    }
+
+    /// Like [`new_const`](Self::new_const), adapting white points completely
+    /// with `adaptation_method`.
+    pub const fn new_const_with_adaptation_method(
+        src: ColorSpace,
+        dst: ColorSpace,
+        adaptation_method: LmsConeSpace,
+    ) -> Self {
+        if !src.is_linear() {
+            panic!("source is not a linear color space");
+        }
+        if !dst.is_linear() {
+            panic!("destination is not a linear color space");
+        }
+        Self {
+            mat: const_math::conversion_matrix(src, dst, adaptation_method),
+            input_space: src,
+            output_space: dst,
+        }
+    }
 }
 
 /// Defines an operation that maps a 3-component vector from a source
@@ -91,10 +246,13 @@ pub struct ColorConversion {
     src_transform: Option<ColorTransform>,
     linear_transform: Option<LinearColorConversion>,
     dst_transform: Option<ColorTransform>,
+    options: ConversionOptions,
 }
 impl PartialEq for ColorConversion {
     fn eq(&self, other: &Self) -> bool {
-        self.src_space == other.src_space && self.dst_space == other.dst_space
+        self.src_space == other.src_space
+            && self.dst_space == other.dst_space
+            && self.options == other.options
     }
 }
 impl core::fmt::Debug for ColorConversion {
@@ -115,20 +273,65 @@ impl core::fmt::Debug for ColorConversion {
             .field("src_transform", &src_transform)
             .field("linear_transform", &self.linear_transform)
             .field("dst_transform", &dst_transform)
+            .field("options", &self.options)
             .finish()
     }
 }
 
 impl ColorConversion {
     pub fn new(src: ColorSpace, dst: ColorSpace) -> Self {
+        Self::new_with_options(src, dst, ConversionOptions::default())
+    }
+
+    /// Like [`new`](Self::new), but returns a [`ConversionError`] if either
+    /// space is degenerate or uses an unimplemented transform function.
+    pub fn try_new(src: ColorSpace, dst: ColorSpace) -> Result<Self, ConversionError> {
+        Self::try_new_with_options(src, dst, ConversionOptions::default())
+    }
+
+    /// Like [`new_with_options`](Self::new_with_options), but returns a
+    /// [`ConversionError`] if either space is invalid.
+    pub fn try_new_with_options(
+        src: ColorSpace,
+        dst: ColorSpace,
+        options: ConversionOptions,
+    ) -> Result<Self, ConversionError> {
+        for transform_fn in [src.transform_function(), dst.transform_function()] {
+            if !transform_fn.is_implemented() {
+                return Err(ConversionError::UnimplementedTransform(transform_fn));
+            }
+        }
+        let linear_transform =
+            LinearColorConversion::try_new_with_options(src.as_linear(), dst.as_linear(), options)?;
+        Ok(Self::from_linear_transform(
+            src,
+            dst,
+            linear_transform,
+            options,
+        ))
+    }
+
+    /// Creates a conversion using the chromatic adaptation options from
+    /// `options`. See [`LinearColorConversion::new_with_options`].
+    pub fn new_with_options(src: ColorSpace, dst: ColorSpace, options: ConversionOptions) -> Self {
+        let src_linear = ColorSpace::linear(src.primaries(), src.white_point());
+        let dst_linear = ColorSpace::linear(dst.primaries(), dst.white_point());
+        let linear_transform =
+            LinearColorConversion::new_with_options(src_linear, dst_linear, options);
+        Self::from_linear_transform(src, dst, linear_transform, options)
+    }
+
+    fn from_linear_transform(
+        src: ColorSpace,
+        dst: ColorSpace,
+        linear_transform: LinearColorConversion,
+        options: ConversionOptions,
+    ) -> Self {
         let src_transform = if !src.is_linear() {
             ColorTransform::new(src.transform_function(), TransformFn::None)
         } else {
             None
         };
-        let src_linear = ColorSpace::linear(src.primaries(), src.white_point());
-        let dst_linear = ColorSpace::linear(dst.primaries(), dst.white_point());
-        let linear_transform = LinearColorConversion::new(src_linear, dst_linear);
         let linear_transform = if linear_transform.mat == Mat3::IDENTITY {
             None
         } else {
@@ -145,11 +348,38 @@ impl ColorConversion {
             src_transform,
             dst_transform,
             linear_transform,
+            options,
         }
     }
 
+    /// Returns the conversion from the destination back to the source space.
+    ///
+    /// Partial von Kries gains aren't reciprocal when the white points are
+    /// swapped, so unless the degree of adaptation is
+    /// [`Full`](DegreeOfAdaptation::Full) the linear matrix is inverted
+    /// instead of recalculated.
     pub fn invert(&self) -> Self {
-        ColorConversion::new(self.dst_space, self.src_space)
+        if self.options.degree_of_adaptation == DegreeOfAdaptation::Full {
+            return ColorConversion::new_with_options(self.dst_space, self.src_space, self.options);
+        }
+        let mat = self
+            .linear_transform
+            .map_or(Mat3::IDENTITY, |transform| transform.mat.inverse());
+        let linear_transform = LinearColorConversion {
+            mat,
+            input_space: self.dst_space.as_linear(),
+            output_space: self.src_space.as_linear(),
+        };
+        Self::from_linear_transform(
+            self.dst_space,
+            self.src_space,
+            linear_transform,
+            self.options,
+        )
+    }
+
+    pub fn options(&self) -> ConversionOptions {
+        self.options
     }
 
     pub fn is_linear(&self) -> bool {
@@ -231,4 +461,389 @@ impl ColorConversion {
         color = self.apply_dst_transform(color);
         color
     }
+
+    /// Converts a color with premultiplied alpha. Non-linear transform
+    /// functions are applied to the straight color, so the color is
+    /// un-premultiplied before the conversion and premultiplied again
+    /// afterwards. Linear conversions commute with premultiplication and are
+    /// applied directly.
+    ///
+    /// A fully transparent premultiplied color can't be un-premultiplied, so
+    /// only the linear part of the conversion is applied to it.
+    pub fn convert_premultiplied(&self, color: Vec3, alpha: Float) -> Vec3 {
+        if (self.src_transform.is_none() && self.dst_transform.is_none()) || alpha == 0.0 {
+            return self.apply_linear_part(color);
+        }
+        self.convert(color / alpha) * alpha
+    }
+
+    /// Converts every color in `colors` in place.
+    ///
+    /// This is equivalent to calling [`convert`](Self::convert) on each
+    /// color, but only checks which stages of the conversion are needed once
+    /// for the whole slice.
+    pub fn convert_slice(&self, colors: &mut [Vec3]) {
+        self.convert_each(
+            colors.iter_mut(),
+            |color| (**color, ()),
+            |color, value, ()| *color = value,
+        );
+    }
+
+    /// Converts every color in `colors` in place, like
+    /// [`convert_slice`](Self::convert_slice).
+    pub fn convert_slice_f32x3(&self, colors: &mut [[Float; 3]]) {
+        self.convert_each(
+            colors.iter_mut(),
+            |color| (Vec3::from_slice(*color), ()),
+            |color, value, ()| *color = value.into(),
+        );
+    }
+
+    /// Converts interleaved colors in place, where each color starts every
+    /// `stride` components of `data`. Only the first 3 components of each
+    /// color are converted, so with a `stride` of 4 an RGBA buffer can be
+    /// converted while leaving alpha untouched. A trailing partial color is
+    /// ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is less than 3.
+    pub fn convert_strided(&self, data: &mut [Float], stride: usize) {
+        assert!(stride >= 3, "stride must be at least 3, got {}", stride);
+        self.convert_each(
+            data.chunks_exact_mut(stride),
+            |color| (Vec3::from_slice(color), ()),
+            |color, value, ()| color[..3].copy_from_slice(&<[Float; 3]>::from(value)),
+        );
+    }
+
+    /// Converts colors stored in separate planes for each component in place,
+    /// using SIMD with the `simd` feature. See the
+    /// [`simd`](super::simd) module for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the planes have different lengths.
+    pub fn convert_planar(&self, x: &mut [Float], y: &mut [Float], z: &mut [Float]) {
+        super::simd::convert_planar(self, x, y, z);
+    }
+
+    /// Decodes, converts and re-encodes every pixel in `pixels` in place.
+    /// Alpha is passed through unchanged. See the
+    /// [`pixel_format`](super::pixel_format) module for the available formats.
+    pub fn convert_pixels<P: PixelFormat>(&self, pixels: &mut [P]) {
+        self.convert_each(
+            pixels.iter_mut(),
+            |pixel| pixel.decode(),
+            |pixel, value, alpha| *pixel = P::encode(value, alpha),
+        );
+    }
+
+    /// Decodes and converts every pixel in `src`, encoding the results into
+    /// `dst`, which may use a different format. Alpha is passed through
+    /// unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` and `dst` have different lengths.
+    pub fn convert_pixels_into<P: PixelFormat, Q: PixelFormat>(&self, src: &[P], dst: &mut [Q]) {
+        assert_eq!(
+            src.len(),
+            dst.len(),
+            "pixel buffers must have the same length"
+        );
+        self.convert_each(
+            src.iter().zip(dst.iter_mut()),
+            |(pixel, _)| pixel.decode(),
+            |(_, out), value, alpha| *out = Q::encode(value, alpha),
+        );
+    }
+
+    /// Applies the conversion to every item, choosing a loop specialized for
+    /// the stages of the conversion up front. `load` returns the color of an
+    /// item and any data to pass through to `store` unchanged, such as alpha.
+    #[inline(always)]
+    fn convert_each<I, E>(
+        &self,
+        items: impl Iterator<Item = I>,
+        load: impl Fn(&I) -> (Vec3, E),
+        store: impl Fn(I, Vec3, E),
+    ) {
+        let src_white_point = self.src_space.white_point();
+        let dst_white_point = self.dst_space.white_point();
+        let mat = self.linear_transform.map(|transform| transform.mat);
+        match (self.src_transform, mat, self.dst_transform) {
+            (None, None, None) => map_items(items, load, store, |color| color),
+            (Some(src), None, None) => map_items(items, load, store, |color| {
+                src.apply(color, src_white_point)
+            }),
+            (None, Some(mat), None) => map_items(items, load, store, |color| mat * color),
+            (None, None, Some(dst)) => map_items(items, load, store, |color| {
+                dst.apply(color, dst_white_point)
+            }),
+            (Some(src), Some(mat), None) => map_items(items, load, store, |color| {
+                mat * src.apply(color, src_white_point)
+            }),
+            (Some(src), None, Some(dst)) => map_items(items, load, store, |color| {
+                dst.apply(src.apply(color, src_white_point), dst_white_point)
+            }),
+            (None, Some(mat), Some(dst)) => map_items(items, load, store, |color| {
+                dst.apply(mat * color, dst_white_point)
+            }),
+            (Some(src), Some(mat), Some(dst)) => map_items(items, load, store, |color| {
+                dst.apply(mat * src.apply(color, src_white_point), dst_white_point)
+            }),
+        }
+    }
+}
+
+/// The number of colors converted by each task of the parallel batch
+/// conversions.
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_LEN: usize = 16 * 1024;
+
+/// Parallel versions of the batch conversions, which split the buffers into
+/// chunks converted on the `rayon` thread pool. The results are identical to
+/// the serial versions.
+#[cfg(feature = "rayon")]
+impl ColorConversion {
+    /// Parallel version of [`convert_slice`](Self::convert_slice).
+    pub fn par_convert_slice(&self, colors: &mut [Vec3]) {
+        use rayon::prelude::*;
+        colors
+            .par_chunks_mut(PARALLEL_CHUNK_LEN)
+            .for_each(|chunk| self.convert_slice(chunk));
+    }
+
+    /// Parallel version of [`convert_slice_f32x3`](Self::convert_slice_f32x3).
+    pub fn par_convert_slice_f32x3(&self, colors: &mut [[Float; 3]]) {
+        use rayon::prelude::*;
+        colors
+            .par_chunks_mut(PARALLEL_CHUNK_LEN)
+            .for_each(|chunk| self.convert_slice_f32x3(chunk));
+    }
+
+    /// Parallel version of [`convert_strided`](Self::convert_strided).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is less than 3.
+    pub fn par_convert_strided(&self, data: &mut [Float], stride: usize) {
+        use rayon::prelude::*;
+        assert!(stride >= 3, "stride must be at least 3, got {}", stride);
+        data.par_chunks_mut(PARALLEL_CHUNK_LEN * stride)
+            .for_each(|chunk| self.convert_strided(chunk, stride));
+    }
+
+    /// Parallel version of [`convert_pixels`](Self::convert_pixels).
+    pub fn par_convert_pixels<P: PixelFormat + Send>(&self, pixels: &mut [P]) {
+        use rayon::prelude::*;
+        pixels
+            .par_chunks_mut(PARALLEL_CHUNK_LEN)
+            .for_each(|chunk| self.convert_pixels(chunk));
+    }
+
+    /// Parallel version of [`convert_pixels_into`](Self::convert_pixels_into).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` and `dst` have different lengths.
+    pub fn par_convert_pixels_into<P: PixelFormat + Sync, Q: PixelFormat + Send>(
+        &self,
+        src: &[P],
+        dst: &mut [Q],
+    ) {
+        use rayon::prelude::*;
+        assert_eq!(
+            src.len(),
+            dst.len(),
+            "pixel buffers must have the same length"
+        );
+        src.par_chunks(PARALLEL_CHUNK_LEN)
+            .zip(dst.par_chunks_mut(PARALLEL_CHUNK_LEN))
+            .for_each(|(src, dst)| self.convert_pixels_into(src, dst));
+    }
+
+    /// Parallel version of [`convert_planar`](Self::convert_planar).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the planes have different lengths.
+    pub fn par_convert_planar(&self, x: &mut [Float], y: &mut [Float], z: &mut [Float]) {
+        use rayon::prelude::*;
+        assert!(
+            x.len() == y.len() && y.len() == z.len(),
+            "planes must have the same length"
+        );
+        x.par_chunks_mut(PARALLEL_CHUNK_LEN)
+            .zip(y.par_chunks_mut(PARALLEL_CHUNK_LEN))
+            .zip(z.par_chunks_mut(PARALLEL_CHUNK_LEN))
+            .for_each(|((x, y), z)| self.convert_planar(x, y, z));
+    }
+}
+
+#[inline(always)]
+fn map_items<I, E>(
+    items: impl Iterator<Item = I>,
+    load: impl Fn(&I) -> (Vec3, E),
+    store: impl Fn(I, Vec3, E),
+    convert: impl Fn(Vec3) -> Vec3,
+) {
+    for item in items {
+        let (value, extra) = load(&item);
+        store(item, convert(value), extra);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn batch_conversions_match_convert() {
+        let colors = [
+            Vec3::new(0.1, 0.2, 0.3),
+            Vec3::new(0.9, 0.5, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ];
+        let pairs = [
+            (spaces::LINEAR_SRGB, spaces::LINEAR_SRGB),
+            (spaces::ENCODED_SRGB, spaces::LINEAR_SRGB),
+            (spaces::LINEAR_SRGB, spaces::ACES_CG),
+            (spaces::LINEAR_SRGB, spaces::ENCODED_SRGB),
+            (spaces::ENCODED_SRGB, spaces::ACES_CG),
+            (spaces::ENCODED_SRGB, spaces::OK_LAB),
+            (spaces::ACES_CG, spaces::OK_LAB),
+            (spaces::ENCODED_SRGB, spaces::ICT_CP_PQ),
+        ];
+        for (src, dst) in pairs {
+            let conversion = ColorConversion::new(src, dst);
+            let expected = colors.map(|color| conversion.convert(color));
+
+            let mut vectors = colors;
+            conversion.convert_slice(&mut vectors);
+            assert_eq!(vectors, expected);
+
+            let mut arrays = colors.map(<[Float; 3]>::from);
+            conversion.convert_slice_f32x3(&mut arrays);
+            assert_eq!(arrays, expected.map(<[Float; 3]>::from));
+
+            let mut rgba = colors
+                .iter()
+                .flat_map(|color| [color.x, color.y, color.z, 0.5])
+                .collect::<Vec<_>>();
+            rgba.push(0.25);
+            conversion.convert_strided(&mut rgba, 4);
+            for (pixel, expected) in rgba.chunks_exact(4).zip(expected) {
+                assert_eq!(Vec3::from_slice(pixel), expected);
+                assert_eq!(pixel[3], 0.5);
+            }
+            assert_eq!(rgba[16], 0.25);
+        }
+    }
+
+    #[test]
+    fn pixel_conversions() {
+        use crate::details::pixel_format::{Rg11B10Float, Rgba8Unorm};
+
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::LINEAR_SRGB);
+        let mut pixels = [Rgba8Unorm([255, 188, 0, 64]), Rgba8Unorm([0, 0, 0, 255])];
+        conversion.convert_pixels(&mut pixels);
+        assert_eq!(
+            pixels,
+            [Rgba8Unorm([255, 128, 0, 64]), Rgba8Unorm([0, 0, 0, 255])]
+        );
+
+        let src = [Rgba8Unorm([255, 255, 255, 0])];
+        let mut dst = [Rg11B10Float::default()];
+        conversion.convert_pixels_into(&src, &mut dst);
+        assert_eq!(dst[0].0, 0x3c0 | 0x3c0 << 11 | 0x1e0 << 22);
+    }
+
+    #[test]
+    fn invert_partial_adaptation() {
+        let options = ConversionOptions::default()
+            .with_degree_of_adaptation(DegreeOfAdaptation::Explicit(0.5));
+        let conversion =
+            ColorConversion::new_with_options(spaces::ENCODED_SRGB, spaces::ACES_CG, options);
+        let inverse = conversion.invert();
+        assert_eq!(inverse.src_space(), spaces::ACES_CG);
+        assert_eq!(inverse.dst_space(), spaces::ENCODED_SRGB);
+        let color = Vec3::new(0.2, 0.5, 0.9);
+        let round_trip = inverse.convert(conversion.convert(color));
+        assert!(round_trip.abs_diff_eq(color, 1e-5), "{:?}", round_trip);
+    }
+
+    #[test]
+    fn identity_pixel_conversion_changes_format() {
+        use crate::details::pixel_format::{Rgba16Unorm, Rgba8Unorm};
+
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::LINEAR_SRGB);
+        let src = [Rgba8Unorm([255, 128, 0, 255]), Rgba8Unorm([1, 2, 3, 4])];
+        let mut dst = [Rgba16Unorm::default(); 2];
+        conversion.convert_pixels_into(&src, &mut dst);
+        assert_eq!(
+            dst,
+            [
+                Rgba16Unorm([65535, 32896, 0, 65535]),
+                Rgba16Unorm([257, 514, 771, 1028])
+            ]
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_conversions_match_serial() {
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::OK_LAB);
+        let len = PARALLEL_CHUNK_LEN * 3 + 17;
+        let colors = (0..len)
+            .map(|i| {
+                let t = i as Float / len as Float;
+                Vec3::new(t, 1.0 - t, (t * 13.0) % 1.0)
+            })
+            .collect::<Vec<_>>();
+
+        let mut serial = colors.clone();
+        conversion.convert_slice(&mut serial);
+        let mut parallel = colors.clone();
+        conversion.par_convert_slice(&mut parallel);
+        assert_eq!(parallel, serial);
+
+        let mut arrays = colors
+            .iter()
+            .map(|&c| c.into())
+            .collect::<Vec<[Float; 3]>>();
+        conversion.par_convert_slice_f32x3(&mut arrays);
+        assert!(arrays
+            .iter()
+            .zip(&serial)
+            .all(|(a, b)| Vec3::from(*a) == *b));
+
+        let mut rgba = colors
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.z, 1.0])
+            .collect::<Vec<_>>();
+        conversion.par_convert_strided(&mut rgba, 4);
+        assert!(rgba
+            .chunks_exact(4)
+            .zip(&serial)
+            .all(|(a, b)| Vec3::from_slice(a) == *b && a[3] == 1.0));
+
+        let mut planes = [0, 1, 2].map(|i| {
+            colors
+                .iter()
+                .map(|c| <[Float; 3]>::from(*c)[i])
+                .collect::<Vec<_>>()
+        });
+        let mut expected = planes.clone();
+        let [x, y, z] = &mut expected;
+        conversion.convert_planar(x, y, z);
+        let [x, y, z] = &mut planes;
+        conversion.par_convert_planar(x, y, z);
+        assert_eq!(planes, expected);
+    }
 }
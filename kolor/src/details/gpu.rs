@@ -0,0 +1,372 @@
+//! A fixed-layout description of a [`ColorConversion`] for shaders.
+//!
+//! [`GpuConversion`] flattens a conversion into plain `f32` and `u32` fields,
+//! laid out so that it can be uploaded as is to a uniform or storage buffer.
+//! Every array element is a 16-byte vector, so the layout is the same in
+//! `std140`, `std430` and WGSL uniform buffers when declared as:
+//!
+//! ```text
+//! struct GpuConversion {
+//!     matrix: mat3x3<f32>,                // mat3 in GLSL
+//!     src_white_point: vec4<f32>,
+//!     dst_white_point: vec4<f32>,
+//!     src_params: array<vec4<f32>, 2>,    // vec4 src_params[2] in GLSL
+//!     dst_params: array<vec4<f32>, 2>,
+//!     src_transform: u32,
+//!     dst_transform: u32,
+//! }
+//! ```
+//!
+//! A single "uber-shader" can then perform any supported conversion chosen at
+//! runtime, by switching on the transform ids, which are the `TRANSFORM_*`
+//! constants of this module.
+//!
+//! [`GpuConversion::eval`] is a CPU reference implementation of such a
+//! shader. It only uses the data in the struct and the constants documented
+//! on [`GpuConversion`], so shader output can be checked against it.
+//!
+//! With the `bytemuck` feature, `GpuConversion` implements `bytemuck::Pod`.
+use super::{
+    color::TransformFn,
+    conversion::ColorConversion,
+    transform::{piecewise_power_params, pq, OKLAB_M_1, OKLAB_M_2},
+};
+use crate::{Float, Mat3, Vec3};
+#[cfg(all(not(feature = "std"), not(test), feature = "libm"))]
+use num_traits::Float as _;
+
+/// The id of [`TransformFn::None`].
+pub const TRANSFORM_NONE: u32 = 0;
+/// The id of [`TransformFn::Srgb`].
+pub const TRANSFORM_SRGB: u32 = 1;
+/// The id of [`TransformFn::Bt601`].
+pub const TRANSFORM_BT601: u32 = 2;
+/// The id of [`TransformFn::Pq`].
+pub const TRANSFORM_PQ: u32 = 3;
+/// The id of [`TransformFn::OkLab`].
+pub const TRANSFORM_OKLAB: u32 = 4;
+/// The id of [`TransformFn::OkLch`].
+pub const TRANSFORM_OKLCH: u32 = 5;
+/// The id of [`TransformFn::CieXyY`].
+pub const TRANSFORM_CIE_XYY: u32 = 6;
+/// The id of [`TransformFn::CieLab`].
+pub const TRANSFORM_CIE_LAB: u32 = 7;
+
+/// The transform functions which [`GpuConversion`] supports, with their ids.
+const GPU_TRANSFORMS: [(TransformFn, u32); 8] = [
+    (TransformFn::None, TRANSFORM_NONE),
+    (TransformFn::Srgb, TRANSFORM_SRGB),
+    (TransformFn::Bt601, TRANSFORM_BT601),
+    (TransformFn::Pq, TRANSFORM_PQ),
+    (TransformFn::OkLab, TRANSFORM_OKLAB),
+    (TransformFn::OkLch, TRANSFORM_OKLCH),
+    (TransformFn::CieXyY, TRANSFORM_CIE_XYY),
+    (TransformFn::CieLab, TRANSFORM_CIE_LAB),
+];
+
+/// Returned when a conversion can't be described by a [`GpuConversion`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GpuConversionError {
+    /// The transform function isn't supported.
+    UnsupportedTransform(TransformFn),
+}
+
+impl core::fmt::Display for GpuConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GpuConversionError::UnsupportedTransform(transform) => {
+                write!(f, "{:?} is not supported on the GPU", transform)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GpuConversionError {}
+
+/// A [`ColorConversion`] as plain data for shaders.
+///
+/// The conversion decodes the color with the source transform function,
+/// multiplies it with `matrix`, and encodes it with the destination transform
+/// function. Transform functions are identified by the `TRANSFORM_*`
+/// constants of the [module](self), and only the sRGB, BT.601, PQ, Oklab,
+/// Oklch, CIE xyY and CIELAB transform functions are supported.
+///
+/// The `params` of a transform function, in the order of the components of
+/// the two vectors, are:
+///
+/// * for sRGB and BT.601, a piecewise curve `[cutoff, linear_scale,
+///   linear_offset, a, b, exponent, scale, offset]`, which maps `x` to `x *
+///   linear_scale + linear_offset` for `x < cutoff`, and `scale * max(a * x +
+///   b, 0)^exponent + offset` otherwise.
+/// * for CIELAB, the same curve for the non-linearity `f` (or its inverse)
+///   applied to XYZ divided by the white point.
+/// * for PQ, `[m1, m2, c1, c2, c3, peak]`, where `peak` is the luminance in
+///   nits of the encoded value 1.
+/// * zero for the others, which only use the constants of the Oklab
+///   matrices.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+pub struct GpuConversion {
+    /// The columns of the matrix of the linear part, each padded to four
+    /// components.
+    pub matrix: [[f32; 4]; 3],
+    /// The XYZ white point of the source space, padded to four components.
+    pub src_white_point: [f32; 4],
+    /// The XYZ white point of the destination space, padded to four
+    /// components.
+    pub dst_white_point: [f32; 4],
+    /// The parameters of the inverse of the source transform function.
+    pub src_params: [[f32; 4]; 2],
+    /// The parameters of the destination transform function.
+    pub dst_params: [[f32; 4]; 2],
+    /// The id of the source transform function.
+    pub src_transform: u32,
+    /// The id of the destination transform function.
+    pub dst_transform: u32,
+    pub padding: [u32; 2],
+}
+
+/// The parameters of `transform`, or of its inverse when `decode` is set.
+fn transform_params(transform: TransformFn, decode: bool) -> [Float; 8] {
+    if transform == TransformFn::Pq {
+        return [
+            pq::M_1,
+            pq::M_2,
+            pq::C_1,
+            pq::C_2,
+            pq::C_3,
+            pq::L_p,
+            0.0,
+            0.0,
+        ];
+    }
+    piecewise_power_params(transform, decode).unwrap_or([0.0; 8])
+}
+
+/// The parameters in the order of their components.
+fn params(params: &[[f32; 4]; 2]) -> [Float; 8] {
+    let [[p0, p1, p2, p3], [p4, p5, p6, p7]] = params.map(|v| v.map(|p| p as Float));
+    [p0, p1, p2, p3, p4, p5, p6, p7]
+}
+
+fn piecewise(params: &[Float; 8], x: Float) -> Float {
+    let [cutoff, linear_scale, linear_offset, a, b, exponent, scale, offset] = *params;
+    if x < cutoff {
+        x * linear_scale + linear_offset
+    } else {
+        scale * (x * a + b).max(0.0).powf(exponent) + offset
+    }
+}
+
+fn pq_eotf(params: &[Float; 8], x: Float) -> Float {
+    let [m_1, m_2, c_1, c_2, c_3, peak, _, _] = *params;
+    let p = x.max(0.0).powf(1.0 / m_2);
+    peak * ((p - c_1).max(0.0) / (c_2 - c_3 * p)).powf(1.0 / m_1)
+}
+
+fn pq_eotf_inverse(params: &[Float; 8], x: Float) -> Float {
+    let [m_1, m_2, c_1, c_2, c_3, peak, _, _] = *params;
+    let y = (x / peak).max(0.0).powf(m_1);
+    ((c_1 + c_2 * y) / (c_3 * y + 1.0)).powf(m_2)
+}
+
+fn channels(color: Vec3, f: impl Fn(Float) -> Float) -> Vec3 {
+    Vec3::new(f(color.x), f(color.y), f(color.z))
+}
+
+fn white_point(values: &[f32; 4]) -> Vec3 {
+    Vec3::new(values[0] as Float, values[1] as Float, values[2] as Float)
+}
+
+/// Applies the inverse of transform function `id`, producing a linear color.
+fn decode(id: u32, params: &[Float; 8], white: &[f32; 4], color: Vec3) -> Vec3 {
+    match id {
+        TRANSFORM_SRGB | TRANSFORM_BT601 => channels(color, |x| piecewise(params, x)),
+        TRANSFORM_PQ => channels(color, |x| pq_eotf(params, x)),
+        TRANSFORM_OKLAB => {
+            let lms = channels(OKLAB_M_2.inverse() * color, |x| x * x * x);
+            OKLAB_M_1.inverse() * lms
+        }
+        TRANSFORM_OKLCH => {
+            let lab = Vec3::new(color.x, color.y * color.z.cos(), color.y * color.z.sin());
+            decode(TRANSFORM_OKLAB, params, white, lab)
+        }
+        TRANSFORM_CIE_XYY => Vec3::new(
+            color.z / color.y * color.x,
+            color.z,
+            color.z / color.y * (1.0 - color.x - color.y),
+        ),
+        TRANSFORM_CIE_LAB => {
+            let l = (color.x + 16.0) / 116.0;
+            let f = Vec3::new(l + color.y / 500.0, l, l - color.z / 200.0);
+            channels(f, |x| piecewise(params, x)) * white_point(white)
+        }
+        _ => color,
+    }
+}
+
+/// Applies transform function `id` to a linear color.
+fn encode(id: u32, params: &[Float; 8], white: &[f32; 4], color: Vec3) -> Vec3 {
+    match id {
+        TRANSFORM_SRGB | TRANSFORM_BT601 => channels(color, |x| piecewise(params, x)),
+        TRANSFORM_PQ => channels(color, |x| pq_eotf_inverse(params, x)),
+        TRANSFORM_OKLAB => {
+            let lms = channels(OKLAB_M_1 * color, |x| x.cbrt());
+            OKLAB_M_2 * lms
+        }
+        TRANSFORM_OKLCH => {
+            let lab = encode(TRANSFORM_OKLAB, params, white, color);
+            Vec3::new(
+                lab.x,
+                (lab.y * lab.y + lab.z * lab.z).sqrt(),
+                lab.z.atan2(lab.y),
+            )
+        }
+        TRANSFORM_CIE_XYY => {
+            let sum = color.x + color.y + color.z;
+            Vec3::new(color.x / sum, color.y / sum, color.y)
+        }
+        TRANSFORM_CIE_LAB => {
+            let white = white_point(white);
+            let scaled = Vec3::new(color.x / white.x, color.y / white.y, color.z / white.z);
+            let f = channels(scaled, |x| piecewise(params, x));
+            Vec3::new(116.0 * f.y - 16.0, 500.0 * (f.x - f.y), 200.0 * (f.y - f.z))
+        }
+        _ => color,
+    }
+}
+
+impl GpuConversion {
+    /// Flattens `conversion`, failing if it uses a transform function which
+    /// isn't supported.
+    pub fn new(conversion: &ColorConversion) -> Result<Self, GpuConversionError> {
+        let src_transform = conversion.src_transform_fn();
+        let dst_transform = conversion.dst_transform_fn();
+        let id = |transform| {
+            GPU_TRANSFORMS
+                .iter()
+                .find(|(supported, _)| *supported == transform)
+                .map(|(_, id)| *id)
+                .ok_or(GpuConversionError::UnsupportedTransform(transform))
+        };
+
+        let matrix = conversion.linear_part().matrix();
+        let column = |axis: Vec3| [axis.x as f32, axis.y as f32, axis.z as f32, 0.0];
+        let white_point =
+            |values: &[Float; 3]| [values[0] as f32, values[1] as f32, values[2] as f32, 0.0];
+        let params = |transform, decode| {
+            let [p0, p1, p2, p3, p4, p5, p6, p7] =
+                transform_params(transform, decode).map(|p| p as f32);
+            [[p0, p1, p2, p3], [p4, p5, p6, p7]]
+        };
+        Ok(Self {
+            matrix: [
+                column(matrix.x_axis),
+                column(matrix.y_axis),
+                column(matrix.z_axis),
+            ],
+            src_white_point: white_point(conversion.src_space().white_point().values()),
+            dst_white_point: white_point(conversion.dst_space().white_point().values()),
+            src_params: params(src_transform, true),
+            dst_params: params(dst_transform, false),
+            src_transform: id(src_transform)?,
+            dst_transform: id(dst_transform)?,
+            padding: [0; 2],
+        })
+    }
+
+    /// The matrix of the linear part.
+    pub fn matrix(&self) -> Mat3 {
+        let [x, y, z] = self.matrix.map(|column| column.map(|value| value as Float));
+        Mat3::from_cols_array(&[x[0], x[1], x[2], y[0], y[1], y[2], z[0], z[1], z[2]])
+    }
+
+    /// Converts a color using only the data in the struct, as a reference for
+    /// shader implementations.
+    pub fn eval(&self, color: [f32; 3]) -> [f32; 3] {
+        let color = Vec3::new(color[0] as Float, color[1] as Float, color[2] as Float);
+        let linear = decode(
+            self.src_transform,
+            &params(&self.src_params),
+            &self.src_white_point,
+            color,
+        );
+        let color = encode(
+            self.dst_transform,
+            &params(&self.dst_params),
+            &self.dst_white_point,
+            self.matrix() * linear,
+        );
+        [color.x as f32, color.y as f32, color.z as f32]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    #[test]
+    fn layout() {
+        assert_eq!(core::mem::size_of::<GpuConversion>(), 160);
+        assert_eq!(core::mem::align_of::<GpuConversion>(), 4);
+        assert_eq!(core::mem::offset_of!(GpuConversion, src_white_point), 48);
+        assert_eq!(core::mem::offset_of!(GpuConversion, src_params), 80);
+        assert_eq!(core::mem::offset_of!(GpuConversion, dst_params), 112);
+        assert_eq!(core::mem::offset_of!(GpuConversion, src_transform), 144);
+
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::ACES_CG);
+        let gpu = GpuConversion::new(&conversion).unwrap();
+        assert_eq!(gpu.src_transform, TRANSFORM_SRGB);
+        assert_eq!(gpu.dst_transform, TRANSFORM_NONE);
+        assert_eq!(gpu.src_params[0][0], 0.04045);
+        assert_eq!(gpu.src_params[1][1], 2.4);
+        assert_eq!(gpu.matrix[1][3], 0.0);
+        #[cfg(feature = "bytemuck")]
+        assert_eq!(bytemuck::bytes_of(&gpu).len(), 160);
+
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::ICT_CP_HLG);
+        assert_eq!(
+            GpuConversion::new(&conversion),
+            Err(GpuConversionError::UnsupportedTransform(
+                TransformFn::IctCpHlg
+            ))
+        );
+    }
+
+    /// `eval` only reads the descriptor, so check each transform id once as
+    /// the source and once as the destination.
+    #[test]
+    fn eval_matches_conversions() {
+        let chain = [
+            spaces::ENCODED_SRGB,
+            spaces::OK_LCH,
+            spaces::ENCODED_BT_2100_PQ,
+            spaces::LINEAR_SRGB.to_cie_xyy(),
+            spaces::PRO_PHOTO.to_cie_lab(),
+            spaces::OK_LAB,
+            spaces::ENCODED_BT_709,
+            spaces::ACES_2065_1,
+            spaces::ENCODED_SRGB,
+        ];
+        for pair in chain.windows(2) {
+            let conversion = ColorConversion::new(pair[0], pair[1]);
+            let gpu = GpuConversion::new(&conversion).unwrap();
+            let color = ColorConversion::new(spaces::LINEAR_SRGB, pair[0])
+                .convert(Vec3::new(0.2, 0.5, 0.8));
+            let expected = conversion.convert(color);
+            let actual = gpu.eval([color.x as f32, color.y as f32, color.z as f32]);
+            let actual = Vec3::new(actual[0] as Float, actual[1] as Float, actual[2] as Float);
+            let scale = expected.x.abs().max(expected.y.abs()).max(expected.z.abs());
+            assert!(
+                actual.abs_diff_eq(expected, 1e-3 * scale.max(1.0)),
+                "{:?}: {:?} != {:?}",
+                pair,
+                actual,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,1161 @@
+//! Reading and writing ICC profiles of RGB matrix/TRC displays and color
+//! spaces.
+//!
+//! [`IccProfile::parse`] reads the colorant (`rXYZ`, `gXYZ`, `bXYZ`), white
+//! point (`wtpt`), chromatic adaptation (`chad`) and tone curve (`rTRC`,
+//! `gTRC`, `bTRC`) tags of ICC v2 and v4 profiles. Profiles built on lookup
+//! tables (`A2B0` and friends) are not supported.
+//!
+//! ICC colorants are adapted to the D50 profile connection space, so the
+//! profile's own chromatic adaptation is undone to find the primaries and
+//! white point of the RGB space. When these and the tone curves match a
+//! built-in combination, [`IccProfile::color_space`] returns the equivalent
+//! [`ColorSpace`], such as
+//! [`ENCODED_SRGB`](super::color::color_spaces::ENCODED_SRGB). A `ColorSpace`
+//! can only hold the built-in primaries, white points and [`TransformFn`]s,
+//! so most profiles of calibrated displays and cameras have no equivalent.
+//! These are converted with [`IccProfile::to_xyz`] and
+//! [`IccProfile::from_xyz`] to and from the profile connection space,
+//! [`PCS_COLOR_SPACE`], from which [`ColorConversion`] reaches any other
+//! space.
+//!
+//! [`ColorConversion`]: super::conversion::ColorConversion
+//!
+//! [`IccProfile::from_color_space`] creates a profile for an RGB `ColorSpace`
+//! to embed in exported images, and [`IccProfile::to_bytes`] writes it as a
+//! compact ICC v4 profile. The sRGB, BT.601 and linear transform functions
+//! are written as `para` curves, and PQ as a sampled `curv` relative to its
+//! 10000 nits peak.
+use super::{
+    cat::LmsConeSpace,
+    color::{ColorSpace, RgbPrimaries, TransformFn, WhitePoint},
+    transform::{bt601_oetf_inverse, pq, srgb_eotf},
+    xyz::rgb_to_xyz,
+};
+use crate::{Float, Mat3, Vec3};
+
+/// The D50 illuminant of the ICC profile connection space, as written in
+/// profile headers.
+pub const PCS_ILLUMINANT: [Float; 3] = [0.9642, 1.0, 0.8249];
+
+/// The color space of [`IccProfile::to_xyz`], CIE XYZ adapted to D50.
+pub const PCS_COLOR_SPACE: ColorSpace =
+    ColorSpace::new(RgbPrimaries::CieXyz, WhitePoint::D50, TransformFn::None);
+
+/// The largest difference in xy chromaticity for primaries and white points
+/// to be considered equal to a built-in one.
+const CHROMATICITY_TOLERANCE: Float = 0.002;
+
+/// The largest difference between a tone curve and a [`TransformFn`] for them
+/// to be considered equal.
+const CURVE_TOLERANCE: Float = 0.001;
+
+/// The primaries which profiles are matched against. CIE XYZ is left out, as
+/// its blue primary has no chromaticity.
+const PRIMARIES: [RgbPrimaries; 10] = [
+    RgbPrimaries::Bt709,
+    RgbPrimaries::Bt2020,
+    RgbPrimaries::Ap0,
+    RgbPrimaries::Ap1,
+    RgbPrimaries::P3,
+    RgbPrimaries::Adobe1998,
+    RgbPrimaries::AdobeWide,
+    RgbPrimaries::Apple,
+    RgbPrimaries::ProPhoto,
+    RgbPrimaries::CieRgb,
+];
+
+const WHITE_POINTS: [WhitePoint; 13] = [
+    WhitePoint::A,
+    WhitePoint::B,
+    WhitePoint::C,
+    WhitePoint::E,
+    WhitePoint::D50,
+    WhitePoint::D55,
+    WhitePoint::D60,
+    WhitePoint::D65,
+    WhitePoint::D75,
+    WhitePoint::P3Dci,
+    WhitePoint::F2,
+    WhitePoint::F7,
+    WhitePoint::F11,
+];
+
+/// The number of entries of sampled curves written for transform functions
+/// which aren't parametric.
+const SAMPLED_CURVE_SIZE: usize = 4096;
+
+/// The transform functions which can be expressed as ICC tone curves.
+const TRANSFORM_FNS: [TransformFn; 4] = [
+    TransformFn::None,
+    TransformFn::Srgb,
+    TransformFn::Bt601,
+    TransformFn::Pq,
+];
+
+/// Decodes `x` with one of [`TRANSFORM_FNS`], with PQ relative to its peak.
+fn decode_transform(transform: TransformFn, x: Float) -> Float {
+    match transform {
+        TransformFn::Srgb => srgb_eotf(Vec3::splat(x), WhitePoint::D65).x,
+        TransformFn::Bt601 => bt601_oetf_inverse(Vec3::splat(x), WhitePoint::D65).x,
+        TransformFn::Pq => pq::st_2084_pq_eotf_float(x) / pq::L_p,
+        _ => x,
+    }
+}
+
+/// Returned when an ICC profile can't be read or created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IccError {
+    /// The data ends before the header, the tag table or a tag.
+    Truncated,
+    /// The profile doesn't have the `acsp` file signature.
+    InvalidSignature,
+    /// Only major versions 2 and 4 are supported.
+    UnsupportedVersion(u8),
+    /// The data color space isn't RGB.
+    UnsupportedColorSpace([u8; 4]),
+    /// The profile connection space isn't XYZ.
+    UnsupportedPcs([u8; 4]),
+    /// A required tag is missing.
+    MissingTag([u8; 4]),
+    /// A tag has an unsupported type or invalid contents.
+    InvalidTag([u8; 4]),
+    /// Only RGB color spaces can be written as matrix/TRC profiles.
+    NotRgb(ColorSpace),
+    /// The transform function isn't a curve applied to each channel.
+    UnsupportedTransform(TransformFn),
+}
+
+impl core::fmt::Display for IccError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = |signature: &[u8; 4]| String::from_utf8_lossy(signature).into_owned();
+        match self {
+            IccError::Truncated => write!(f, "the ICC profile is truncated"),
+            IccError::InvalidSignature => write!(f, "the data is not an ICC profile"),
+            IccError::UnsupportedVersion(version) => {
+                write!(f, "unsupported ICC profile version {}", version)
+            }
+            IccError::UnsupportedColorSpace(space) => {
+                write!(f, "unsupported data color space '{}'", name(space))
+            }
+            IccError::UnsupportedPcs(space) => {
+                write!(f, "unsupported profile connection space '{}'", name(space))
+            }
+            IccError::MissingTag(tag) => write!(f, "missing tag '{}'", name(tag)),
+            IccError::InvalidTag(tag) => write!(f, "invalid tag '{}'", name(tag)),
+            IccError::NotRgb(space) => write!(f, "{:?} is not an RGB color space", space),
+            IccError::UnsupportedTransform(transform) => {
+                write!(f, "{:?} can't be written as an ICC tone curve", transform)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IccError {}
+
+/// An ICC parametric curve in its most general form (function type 4):
+///
+/// `Y = (aX + b)^g + e` for `X >= d`, and `Y = cX + f` otherwise.
+///
+/// The other function types are special cases of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParametricCurve {
+    pub g: Float,
+    pub a: Float,
+    pub b: Float,
+    pub c: Float,
+    pub d: Float,
+    pub e: Float,
+    pub f: Float,
+}
+
+impl ParametricCurve {
+    /// A pure power curve, `Y = X^g`.
+    pub const fn gamma(g: Float) -> Self {
+        Self {
+            g,
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 0.0,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    /// Creates a curve from the function type and parameters of a `para` tag.
+    /// Returns `None` for unknown function types, if there are too few
+    /// parameters, or if `a` is zero where it is needed for the breakpoint.
+    pub fn from_icc_params(function_type: u16, params: &[Float]) -> Option<Self> {
+        let count = [1, 3, 4, 5, 7].get(function_type as usize)?;
+        if params.len() < *count {
+            return None;
+        }
+        let mut curve = Self::gamma(params[0]);
+        if function_type == 0 {
+            return Some(curve);
+        }
+        curve.a = params[1];
+        curve.b = params[2];
+        match function_type {
+            1 | 2 => {
+                if curve.a == 0.0 {
+                    return None;
+                }
+                curve.d = -curve.b / curve.a;
+                if function_type == 2 {
+                    curve.e = params[3];
+                    curve.f = params[3];
+                }
+            }
+            _ => {
+                curve.c = params[3];
+                curve.d = params[4];
+                if function_type == 4 {
+                    curve.e = params[5];
+                    curve.f = params[6];
+                }
+            }
+        }
+        Some(curve)
+    }
+
+    /// The curve decoding `transform`, for the transform functions which have
+    /// one.
+    pub fn from_transform_fn(transform: TransformFn) -> Option<Self> {
+        match transform {
+            TransformFn::None => Some(Self::gamma(1.0)),
+            TransformFn::Srgb => Some(Self {
+                g: 2.4,
+                a: 1.0 / 1.055,
+                b: 0.055 / 1.055,
+                c: 1.0 / 12.92,
+                d: 0.04045,
+                e: 0.0,
+                f: 0.0,
+            }),
+            TransformFn::Bt601 => Some(Self {
+                g: 1.0 / 0.45,
+                a: 1.0 / 1.0993,
+                b: 0.0993 / 1.0993,
+                c: 1.0 / 4.5,
+                d: 0.08145,
+                e: 0.0,
+                f: 0.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The function type and parameters of the simplest `para` tag for the
+    /// curve.
+    pub fn icc_params(&self) -> (u16, Vec<Float>) {
+        let Self {
+            g,
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+        } = *self;
+        if a == 1.0 && b == 0.0 && d == 0.0 && e == 0.0 && f == 0.0 {
+            (0, vec![g])
+        } else if e == 0.0 && f == 0.0 {
+            (3, vec![g, a, b, c, d])
+        } else {
+            (4, vec![g, a, b, c, d, e, f])
+        }
+    }
+
+    /// Evaluates the curve.
+    pub fn eval(&self, x: Float) -> Float {
+        if x >= self.d {
+            (self.a * x + self.b).max(0.0).powf(self.g) + self.e
+        } else {
+            self.c * x + self.f
+        }
+    }
+
+    /// Evaluates the inverse of the curve, assuming it is increasing.
+    pub fn eval_inverse(&self, y: Float) -> Float {
+        let breakpoint = (self.a * self.d + self.b).max(0.0).powf(self.g) + self.e;
+        if y >= breakpoint && self.a != 0.0 {
+            ((y - self.e).max(0.0).powf(1.0 / self.g) - self.b) / self.a
+        } else if self.c != 0.0 {
+            (y - self.f) / self.c
+        } else {
+            self.d
+        }
+    }
+}
+
+/// The tone reproduction curve of one channel, which maps encoded values to
+/// linear values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToneCurve {
+    /// A `para` curve, or a `curv` with zero (identity) or one (gamma)
+    /// entries.
+    Parametric(ParametricCurve),
+    /// A `curv` sampled at evenly spaced inputs from `0.0..=1.0`, which is
+    /// interpolated linearly.
+    Sampled(Vec<Float>),
+}
+
+impl ToneCurve {
+    /// Decodes an encoded value. Sampled curves clamp the input to
+    /// `0.0..=1.0`.
+    pub fn eval(&self, x: Float) -> Float {
+        match self {
+            ToneCurve::Parametric(curve) => curve.eval(x),
+            ToneCurve::Sampled(table) => {
+                let last = table.len() - 1;
+                let position = x.clamp(0.0, 1.0) * last as Float;
+                let index = (position as usize).min(last - 1);
+                let t = position - index as Float;
+                table[index] + (table[index + 1] - table[index]) * t
+            }
+        }
+    }
+
+    /// Encodes a linear value, assuming the curve is increasing.
+    pub fn eval_inverse(&self, y: Float) -> Float {
+        match self {
+            ToneCurve::Parametric(curve) => curve.eval_inverse(y),
+            ToneCurve::Sampled(table) => {
+                let last = table.len() - 1;
+                if y <= table[0] {
+                    return 0.0;
+                }
+                if y >= table[last] {
+                    return 1.0;
+                }
+                let upper = table.partition_point(|&value| value <= y);
+                let lower = upper - 1;
+                let span = table[upper] - table[lower];
+                let t = if span > 0.0 {
+                    (y - table[lower]) / span
+                } else {
+                    0.0
+                };
+                (lower as Float + t) / last as Float
+            }
+        }
+    }
+
+    /// Whether the curve decodes like `transform` within
+    /// [`CURVE_TOLERANCE`].
+    fn matches(&self, transform: TransformFn) -> bool {
+        let samples = 64;
+        (0..=samples).all(|i| {
+            let x = i as Float / samples as Float;
+            (self.eval(x) - decode_transform(transform, x)).abs() <= CURVE_TOLERANCE
+        })
+    }
+}
+
+/// An RGB matrix/TRC ICC profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccProfile {
+    version: (u8, u8),
+    description: Option<String>,
+    pcs_illuminant: Vec3,
+    media_white_point: Option<Vec3>,
+    colorants: Mat3,
+    chromatic_adaptation: Mat3,
+    curves: [ToneCurve; 3],
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_signature(data: &[u8], offset: usize) -> Option<[u8; 4]> {
+    Some(read_u32(data, offset)?.to_be_bytes())
+}
+
+fn read_s15_fixed16(data: &[u8], offset: usize) -> Option<Float> {
+    Some(read_u32(data, offset)? as i32 as Float / 65536.0)
+}
+
+fn read_xyz_number(data: &[u8], offset: usize) -> Option<Vec3> {
+    Some(Vec3::new(
+        read_s15_fixed16(data, offset)?,
+        read_s15_fixed16(data, offset + 4)?,
+        read_s15_fixed16(data, offset + 8)?,
+    ))
+}
+
+/// Reads an `XYZ ` tag.
+fn parse_xyz(data: &[u8]) -> Option<Vec3> {
+    if &data[..4] != b"XYZ " {
+        return None;
+    }
+    read_xyz_number(data, 8)
+}
+
+/// Reads an `sf32` tag holding a row-major 3x3 matrix.
+fn parse_matrix(data: &[u8]) -> Option<Mat3> {
+    if &data[..4] != b"sf32" {
+        return None;
+    }
+    let mut values = [0.0; 9];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = read_s15_fixed16(data, 8 + i * 4)?;
+    }
+    Some(Mat3::from_cols_array(&values).transpose())
+}
+
+/// Reads a `curv` or `para` tag.
+fn parse_curve(data: &[u8]) -> Option<ToneCurve> {
+    match &data[..4] {
+        b"curv" => {
+            let count = read_u32(data, 8)? as usize;
+            match count {
+                0 => Some(ToneCurve::Parametric(ParametricCurve::gamma(1.0))),
+                1 => {
+                    let gamma = read_u16(data, 12)? as Float / 256.0;
+                    Some(ToneCurve::Parametric(ParametricCurve::gamma(gamma)))
+                }
+                _ => {
+                    let table = (0..count)
+                        .map(|i| Some(read_u16(data, 12 + i * 2)? as Float / 65535.0))
+                        .collect::<Option<Vec<_>>>()?;
+                    Some(ToneCurve::Sampled(table))
+                }
+            }
+        }
+        b"para" => {
+            let function_type = read_u16(data, 8)?;
+            let mut params = [0.0; 7];
+            let mut count = 0;
+            while count < params.len() {
+                match read_s15_fixed16(data, 12 + count * 4) {
+                    Some(param) => params[count] = param,
+                    None => break,
+                }
+                count += 1;
+            }
+            ParametricCurve::from_icc_params(function_type, &params[..count])
+                .map(ToneCurve::Parametric)
+        }
+        _ => None,
+    }
+}
+
+/// Reads a v2 `desc` or v4 `mluc` text tag, using the first record of the
+/// latter.
+fn parse_text(data: &[u8]) -> Option<String> {
+    match &data[..4] {
+        b"desc" => {
+            let length = read_u32(data, 8)? as usize;
+            let text = data.get(12..12 + length)?;
+            let end = text.iter().position(|&c| c == 0).unwrap_or(text.len());
+            Some(String::from_utf8_lossy(&text[..end]).into_owned())
+        }
+        b"mluc" => {
+            if read_u32(data, 8)? == 0 {
+                return None;
+            }
+            let length = read_u32(data, 20)? as usize;
+            let offset = read_u32(data, 24)? as usize;
+            let units = (0..length / 2)
+                .map(|i| read_u16(data, offset + i * 2))
+                .collect::<Option<Vec<_>>>()?;
+            Some(String::from_utf16_lossy(&units))
+        }
+        b"text" => {
+            let text = data.get(8..)?;
+            let end = text.iter().position(|&c| c == 0).unwrap_or(text.len());
+            Some(String::from_utf8_lossy(&text[..end]).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// The chromaticity of an XYZ color.
+fn xy(xyz: Vec3) -> [Float; 2] {
+    let sum = xyz.x + xyz.y + xyz.z;
+    [xyz.x / sum, xyz.y / sum]
+}
+
+/// The largest difference between the components of two chromaticities.
+fn xy_distance(a: [Float; 2], b: [Float; 2]) -> Float {
+    (a[0] - b[0]).abs().max((a[1] - b[1]).abs())
+}
+
+fn write_s15_fixed16(data: &mut Vec<u8>, value: Float) {
+    data.extend_from_slice(&((value * 65536.0).round() as i32).to_be_bytes());
+}
+
+fn xyz_tag_data(value: Vec3) -> Vec<u8> {
+    let mut data = b"XYZ \0\0\0\0".to_vec();
+    for component in [value.x, value.y, value.z] {
+        write_s15_fixed16(&mut data, component);
+    }
+    data
+}
+
+/// Writes an `sf32` tag holding a matrix in row-major order.
+fn matrix_tag_data(matrix: Mat3) -> Vec<u8> {
+    let mut data = b"sf32\0\0\0\0".to_vec();
+    let rows = matrix.transpose();
+    for row in [rows.x_axis, rows.y_axis, rows.z_axis] {
+        for value in [row.x, row.y, row.z] {
+            write_s15_fixed16(&mut data, value);
+        }
+    }
+    data
+}
+
+fn curve_tag_data(curve: &ToneCurve) -> Vec<u8> {
+    match curve {
+        ToneCurve::Parametric(curve) => {
+            let (function_type, params) = curve.icc_params();
+            let mut data = b"para\0\0\0\0".to_vec();
+            data.extend_from_slice(&function_type.to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+            for param in params {
+                write_s15_fixed16(&mut data, param);
+            }
+            data
+        }
+        ToneCurve::Sampled(table) => {
+            let mut data = b"curv\0\0\0\0".to_vec();
+            data.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            for value in table {
+                let value = (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+            data
+        }
+    }
+}
+
+/// Writes an `mluc` tag with a single en-US record.
+fn text_tag_data(text: &str) -> Vec<u8> {
+    let units = text.encode_utf16().collect::<Vec<_>>();
+    let mut data = b"mluc\0\0\0\0".to_vec();
+    data.extend_from_slice(&1u32.to_be_bytes());
+    data.extend_from_slice(&12u32.to_be_bytes());
+    data.extend_from_slice(b"enUS");
+    data.extend_from_slice(&(units.len() as u32 * 2).to_be_bytes());
+    data.extend_from_slice(&28u32.to_be_bytes());
+    for unit in units {
+        data.extend_from_slice(&unit.to_be_bytes());
+    }
+    data
+}
+
+impl IccProfile {
+    /// Creates a v4 profile for an RGB color space. The colorants are adapted
+    /// to the PCS illuminant with the Bradford transform, as recommended by
+    /// the ICC, which is recorded in the `chad` tag.
+    pub fn from_color_space(space: ColorSpace, description: &str) -> Result<Self, IccError> {
+        if matches!(space.primaries(), RgbPrimaries::None | RgbPrimaries::CieXyz)
+            || space.white_point() == WhitePoint::None
+        {
+            return Err(IccError::NotRgb(space));
+        }
+        let transform = space.transform_function();
+        let curve = match ParametricCurve::from_transform_fn(transform) {
+            Some(curve) => ToneCurve::Parametric(curve),
+            None if TRANSFORM_FNS.contains(&transform) => ToneCurve::Sampled(
+                (0..SAMPLED_CURVE_SIZE)
+                    .map(|i| {
+                        let x = i as Float / (SAMPLED_CURVE_SIZE - 1) as Float;
+                        decode_transform(transform, x)
+                    })
+                    .collect(),
+            ),
+            None => return Err(IccError::UnsupportedTransform(transform)),
+        };
+
+        let [x, y, z] = *space.white_point().values();
+        let white = Vec3::new(x, y, z);
+        let pcs_illuminant = Vec3::new(PCS_ILLUMINANT[0], PCS_ILLUMINANT[1], PCS_ILLUMINANT[2]);
+        let chromatic_adaptation =
+            LmsConeSpace::Bradford.chromatic_adaptation_transform(white, pcs_illuminant);
+        let colorants = chromatic_adaptation
+            * rgb_to_xyz(space.primaries().values(), space.white_point().values());
+
+        Ok(Self {
+            version: (4, 4),
+            description: Some(description.to_owned()),
+            pcs_illuminant,
+            media_white_point: Some(pcs_illuminant),
+            colorants,
+            chromatic_adaptation,
+            curves: [curve.clone(), curve.clone(), curve],
+        })
+    }
+
+    /// Writes the profile as an ICC v4 display profile. Identical tags, such
+    /// as the tone curves of most profiles, share their data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tags = [
+            (
+                *b"desc",
+                text_tag_data(self.description.as_deref().unwrap_or("")),
+            ),
+            (*b"cprt", text_tag_data("No copyright, use freely")),
+            (*b"wtpt", xyz_tag_data(self.pcs_illuminant)),
+            (*b"chad", matrix_tag_data(self.chromatic_adaptation)),
+            (*b"rXYZ", xyz_tag_data(self.colorants.x_axis)),
+            (*b"gXYZ", xyz_tag_data(self.colorants.y_axis)),
+            (*b"bXYZ", xyz_tag_data(self.colorants.z_axis)),
+            (*b"rTRC", curve_tag_data(&self.curves[0])),
+            (*b"gTRC", curve_tag_data(&self.curves[1])),
+            (*b"bTRC", curve_tag_data(&self.curves[2])),
+        ];
+
+        let mut data = vec![0; 128];
+        data[8] = 4;
+        data[9] = 0x40;
+        data[12..16].copy_from_slice(b"mntr");
+        data[16..20].copy_from_slice(b"RGB ");
+        data[20..24].copy_from_slice(b"XYZ ");
+        data[36..40].copy_from_slice(b"acsp");
+        data[68..80].copy_from_slice(&xyz_tag_data(self.pcs_illuminant)[8..]);
+
+        data.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+        let table_end = 132 + tags.len() * 12;
+        let mut contents = Vec::new();
+        let mut offsets = Vec::with_capacity(tags.len());
+        for (i, (signature, tag)) in tags.iter().enumerate() {
+            let offset = match tags[..i].iter().position(|(_, other)| other == tag) {
+                Some(shared) => offsets[shared],
+                None => {
+                    let offset = table_end + contents.len();
+                    contents.extend_from_slice(tag);
+                    contents.resize(contents.len().next_multiple_of(4), 0);
+                    offset
+                }
+            };
+            offsets.push(offset);
+            data.extend_from_slice(signature);
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        }
+        data.extend_from_slice(&contents);
+        let size = data.len() as u32;
+        data[..4].copy_from_slice(&size.to_be_bytes());
+        data
+    }
+
+    /// Parses an ICC v2 or v4 RGB matrix/TRC profile.
+    pub fn parse(data: &[u8]) -> Result<Self, IccError> {
+        let size = read_u32(data, 0).ok_or(IccError::Truncated)? as usize;
+        if data.len() < 132 || size < 132 || data.len() < size {
+            return Err(IccError::Truncated);
+        }
+        let data = &data[..size];
+        if &data[36..40] != b"acsp" {
+            return Err(IccError::InvalidSignature);
+        }
+        let version = (data[8], data[9] >> 4);
+        if version.0 != 2 && version.0 != 4 {
+            return Err(IccError::UnsupportedVersion(version.0));
+        }
+        let color_space = read_signature(data, 16).ok_or(IccError::Truncated)?;
+        if &color_space != b"RGB " {
+            return Err(IccError::UnsupportedColorSpace(color_space));
+        }
+        let pcs = read_signature(data, 20).ok_or(IccError::Truncated)?;
+        if &pcs != b"XYZ " {
+            return Err(IccError::UnsupportedPcs(pcs));
+        }
+        let pcs_illuminant = match read_xyz_number(data, 68) {
+            Some(illuminant) if illuminant.y > 0.0 => illuminant,
+            _ => Vec3::new(PCS_ILLUMINANT[0], PCS_ILLUMINANT[1], PCS_ILLUMINANT[2]),
+        };
+
+        let tag_count = read_u32(data, 128).ok_or(IccError::Truncated)? as usize;
+        let mut tags = Vec::with_capacity(tag_count.min(64));
+        for i in 0..tag_count {
+            let entry = 132 + i * 12;
+            let signature = read_signature(data, entry).ok_or(IccError::Truncated)?;
+            let offset = read_u32(data, entry + 4).ok_or(IccError::Truncated)? as usize;
+            let length = read_u32(data, entry + 8).ok_or(IccError::Truncated)? as usize;
+            let tag = offset
+                .checked_add(length)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(IccError::Truncated)?;
+            tags.push((signature, tag));
+        }
+        let find = |signature: &[u8; 4]| {
+            tags.iter()
+                .find(|(tag, _)| tag == signature)
+                .map(|(_, data)| *data)
+        };
+        let read = |signature: &[u8; 4]| {
+            let data = find(signature).ok_or(IccError::MissingTag(*signature))?;
+            if data.len() < 8 {
+                return Err(IccError::InvalidTag(*signature));
+            }
+            Ok(data)
+        };
+        let xyz_tag = |signature: &[u8; 4]| {
+            parse_xyz(read(signature)?).ok_or(IccError::InvalidTag(*signature))
+        };
+        let curve_tag = |signature: &[u8; 4]| {
+            parse_curve(read(signature)?).ok_or(IccError::InvalidTag(*signature))
+        };
+
+        let red = xyz_tag(b"rXYZ")?;
+        let green = xyz_tag(b"gXYZ")?;
+        let blue = xyz_tag(b"bXYZ")?;
+        let colorants = Mat3::from_cols_array(&[
+            red.x, red.y, red.z, green.x, green.y, green.z, blue.x, blue.y, blue.z,
+        ]);
+        let curves = [
+            curve_tag(b"rTRC")?,
+            curve_tag(b"gTRC")?,
+            curve_tag(b"bTRC")?,
+        ];
+        let media_white_point = match find(b"wtpt") {
+            Some(_) => Some(xyz_tag(b"wtpt")?),
+            None => None,
+        };
+        let chromatic_adaptation = match find(b"chad") {
+            Some(_) => parse_matrix(read(b"chad")?).ok_or(IccError::InvalidTag(*b"chad"))?,
+            // Profiles without a chad tag, such as v2 profiles, were adapted
+            // from the media white point with Bradford.
+            None => match media_white_point {
+                Some(white) if xy_distance(xy(white), xy(pcs_illuminant)) > 1e-4 => {
+                    LmsConeSpace::Bradford.chromatic_adaptation_transform(white, pcs_illuminant)
+                }
+                _ => Mat3::IDENTITY,
+            },
+        };
+        if !chromatic_adaptation.is_finite() || chromatic_adaptation.determinant() == 0.0 {
+            return Err(IccError::InvalidTag(*b"chad"));
+        }
+        let description = find(b"desc").and_then(|data| {
+            if data.len() < 12 {
+                None
+            } else {
+                parse_text(data)
+            }
+        });
+
+        Ok(Self {
+            version,
+            description,
+            pcs_illuminant,
+            media_white_point,
+            colorants,
+            chromatic_adaptation,
+            curves,
+        })
+    }
+
+    /// The major and minor version of the profile.
+    pub fn version(&self) -> (u8, u8) {
+        self.version
+    }
+
+    /// The profile description, if it has one.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The illuminant of the profile connection space from the header, which
+    /// is D50.
+    pub fn pcs_illuminant(&self) -> Vec3 {
+        self.pcs_illuminant
+    }
+
+    /// The `wtpt` tag. In v4 profiles this is always the PCS illuminant.
+    pub fn media_white_point(&self) -> Option<Vec3> {
+        self.media_white_point
+    }
+
+    /// The matrix from linear RGB to the D50 profile connection space, made
+    /// of the colorant tags.
+    pub fn colorants(&self) -> Mat3 {
+        self.colorants
+    }
+
+    /// The matrix adapting XYZ colors from the white point of the RGB space
+    /// to the PCS illuminant. This is the `chad` tag, or derived from the
+    /// media white point for profiles without one.
+    pub fn chromatic_adaptation(&self) -> Mat3 {
+        self.chromatic_adaptation
+    }
+
+    /// The tone curves of the red, green and blue channels.
+    pub fn curves(&self) -> &[ToneCurve; 3] {
+        &self.curves
+    }
+
+    /// The matrix from linear RGB to XYZ, without the adaptation to D50.
+    pub fn rgb_to_xyz(&self) -> Mat3 {
+        self.chromatic_adaptation.inverse() * self.colorants
+    }
+
+    /// The white point of the RGB space, normalized to `Y = 1`.
+    pub fn white_point(&self) -> Vec3 {
+        let white = self.rgb_to_xyz() * Vec3::new(1.0, 1.0, 1.0);
+        white * (1.0 / white.y)
+    }
+
+    /// The xy chromaticities of the red, green and blue primaries.
+    pub fn primaries(&self) -> [[Float; 2]; 3] {
+        let matrix = self.rgb_to_xyz();
+        [xy(matrix.x_axis), xy(matrix.y_axis), xy(matrix.z_axis)]
+    }
+
+    /// Decodes an encoded color with the tone curves, to linear RGB with the
+    /// profile's primaries and white point. This is
+    /// [`linear_color_space`](Self::linear_color_space) when that exists.
+    pub fn decode(&self, color: Vec3) -> Vec3 {
+        Vec3::new(
+            self.curves[0].eval(color.x),
+            self.curves[1].eval(color.y),
+            self.curves[2].eval(color.z),
+        )
+    }
+
+    /// Encodes a linear color with the inverse of the tone curves.
+    pub fn encode(&self, color: Vec3) -> Vec3 {
+        Vec3::new(
+            self.curves[0].eval_inverse(color.x),
+            self.curves[1].eval_inverse(color.y),
+            self.curves[2].eval_inverse(color.z),
+        )
+    }
+
+    /// Decodes an encoded color and converts it to XYZ in the profile
+    /// connection space, [`PCS_COLOR_SPACE`]. Unlike
+    /// [`color_space`](Self::color_space), this works for any profile.
+    pub fn to_xyz(&self, color: Vec3) -> Vec3 {
+        self.colorants * self.decode(color)
+    }
+
+    /// Converts an XYZ color in the profile connection space to the profile's
+    /// encoded RGB. The inverse of [`to_xyz`](Self::to_xyz).
+    pub fn from_xyz(&self, xyz: Vec3) -> Vec3 {
+        self.encode(self.colorants.inverse() * xyz)
+    }
+
+    /// The built-in [`TransformFn`] equivalent to the tone curves, if all
+    /// three channels match one.
+    pub fn transform_fn(&self) -> Option<TransformFn> {
+        TRANSFORM_FNS
+            .iter()
+            .copied()
+            .find(|transform| self.curves.iter().all(|curve| curve.matches(*transform)))
+    }
+
+    /// The linear [`ColorSpace`] with the closest built-in primaries and white
+    /// point, if they match within a tolerance.
+    pub fn linear_color_space(&self) -> Option<ColorSpace> {
+        let primaries = self.primaries();
+        let primaries = PRIMARIES
+            .iter()
+            .map(|candidate| {
+                let distance = candidate
+                    .values()
+                    .iter()
+                    .zip(primaries.iter())
+                    .map(|(a, b)| xy_distance(*a, *b))
+                    .fold(0.0, Float::max);
+                (*candidate, distance)
+            })
+            .filter(|(_, distance)| *distance <= CHROMATICITY_TOLERANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))?
+            .0;
+
+        let white = xy(self.white_point());
+        let white_point = WHITE_POINTS
+            .iter()
+            .map(|candidate| {
+                let [x, y, z] = *candidate.values();
+                let distance = xy_distance(xy(Vec3::new(x, y, z)), white);
+                (*candidate, distance)
+            })
+            .filter(|(_, distance)| *distance <= CHROMATICITY_TOLERANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))?
+            .0;
+
+        Some(ColorSpace::new(primaries, white_point, TransformFn::None))
+    }
+
+    /// The [`ColorSpace`] equivalent to the profile, if its primaries, white
+    /// point and tone curves all match built-in ones.
+    pub fn color_space(&self) -> Option<ColorSpace> {
+        let transform = self.transform_fn()?;
+        Some(self.linear_color_space()?.with_transform(transform))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        details::{
+            color::color_spaces,
+            conversion::{ColorConversion, ConversionOptions},
+        },
+        spaces,
+    };
+
+    /// Assembles a profile from tags, adapting the colorants to D50 the way
+    /// display profiles do.
+    fn build_profile(
+        major_version: u8,
+        space: ColorSpace,
+        curve: Vec<u8>,
+        write_chad: bool,
+    ) -> Vec<u8> {
+        let white = space.white_point().values();
+        let white = Vec3::new(white[0], white[1], white[2]);
+        let pcs = Vec3::new(PCS_ILLUMINANT[0], PCS_ILLUMINANT[1], PCS_ILLUMINANT[2]);
+        let chad = LmsConeSpace::Bradford.chromatic_adaptation_transform(white, pcs);
+        let colorants = chad * rgb_to_xyz(space.primaries().values(), space.white_point().values());
+
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"rXYZ", xyz_tag_data(colorants.x_axis)),
+            (b"gXYZ", xyz_tag_data(colorants.y_axis)),
+            (b"bXYZ", xyz_tag_data(colorants.z_axis)),
+            (b"rTRC", curve.clone()),
+            (b"gTRC", curve.clone()),
+            (b"bTRC", curve),
+        ];
+        if write_chad {
+            tags.push((b"chad", matrix_tag_data(chad)));
+            tags.push((b"wtpt", xyz_tag_data(pcs)));
+        } else {
+            tags.push((b"wtpt", xyz_tag_data(white)));
+        }
+        let mut description = b"desc\0\0\0\0".to_vec();
+        description.extend_from_slice(&5u32.to_be_bytes());
+        description.extend_from_slice(b"Test\0");
+        tags.push((b"desc", description));
+
+        let mut data = vec![0; 128];
+        data[8] = major_version;
+        data[16..20].copy_from_slice(b"RGB ");
+        data[20..24].copy_from_slice(b"XYZ ");
+        data[36..40].copy_from_slice(b"acsp");
+        data[68..80].copy_from_slice(&xyz_tag_data(pcs)[8..]);
+        data.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+        let mut offset = 132 + tags.len() * 12;
+        let mut contents = Vec::new();
+        for (signature, tag) in &tags {
+            data.extend_from_slice(*signature);
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+            contents.extend_from_slice(tag);
+            while contents.len() % 4 != 0 {
+                contents.push(0);
+            }
+            offset = 132 + tags.len() * 12 + contents.len();
+        }
+        data.extend_from_slice(&contents);
+        let size = data.len() as u32;
+        data[..4].copy_from_slice(&size.to_be_bytes());
+        data
+    }
+
+    fn sampled_curve(count: usize, decode: impl Fn(Float) -> Float) -> Vec<u8> {
+        let mut tag = b"curv\0\0\0\0".to_vec();
+        tag.extend_from_slice(&(count as u32).to_be_bytes());
+        for i in 0..count {
+            let value = decode(i as Float / (count - 1) as Float);
+            tag.extend_from_slice(&((value * 65535.0).round() as u16).to_be_bytes());
+        }
+        tag
+    }
+
+    fn parametric_curve(function_type: u16, params: &[Float]) -> Vec<u8> {
+        let mut tag = b"para\0\0\0\0".to_vec();
+        tag.extend_from_slice(&function_type.to_be_bytes());
+        tag.extend_from_slice(&[0, 0]);
+        for param in params {
+            write_s15_fixed16(&mut tag, *param);
+        }
+        tag
+    }
+
+    #[test]
+    fn recognizes_built_in_spaces() {
+        let srgb_curve = sampled_curve(1024, |x| srgb_eotf(Vec3::splat(x), WhitePoint::D65).x);
+        let profile =
+            IccProfile::parse(&build_profile(2, spaces::ENCODED_SRGB, srgb_curve, false)).unwrap();
+        assert_eq!(profile.version(), (2, 0));
+        assert_eq!(profile.description(), Some("Test"));
+        assert_eq!(profile.color_space(), Some(spaces::ENCODED_SRGB));
+
+        let srgb_para =
+            parametric_curve(3, &[2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045]);
+        let profile = IccProfile::parse(&build_profile(
+            4,
+            spaces::ENCODED_DISPLAY_P3,
+            srgb_para,
+            true,
+        ))
+        .unwrap();
+        assert_eq!(profile.color_space(), Some(spaces::ENCODED_DISPLAY_P3));
+        let white = profile.white_point();
+        assert!((white.x - 0.95047).abs() < 1e-3 && (white.z - 1.08883).abs() < 1e-3);
+
+        let bt601_para = parametric_curve(
+            3,
+            &[
+                1.0 / 0.45,
+                1.0 / 1.0993,
+                0.0993 / 1.0993,
+                1.0 / 4.5,
+                0.08145,
+            ],
+        );
+        let profile =
+            IccProfile::parse(&build_profile(4, spaces::ENCODED_BT_2020, bt601_para, true))
+                .unwrap();
+        assert_eq!(profile.color_space(), Some(spaces::ENCODED_BT_2020));
+
+        let linear = parametric_curve(0, &[1.0]);
+        let profile = IccProfile::parse(&build_profile(4, spaces::ACES_CG, linear, true)).unwrap();
+        assert_eq!(profile.color_space(), Some(spaces::ACES_CG));
+    }
+
+    #[test]
+    fn custom_curves() {
+        let mut gamma = b"curv\0\0\0\0".to_vec();
+        gamma.extend_from_slice(&1u32.to_be_bytes());
+        gamma.extend_from_slice(&((2.2 * 256.0) as u16).to_be_bytes());
+        gamma.extend_from_slice(&[0, 0]);
+        let profile =
+            IccProfile::parse(&build_profile(2, spaces::ADOBE_1998, gamma, false)).unwrap();
+        assert_eq!(profile.transform_fn(), None);
+        assert_eq!(profile.color_space(), None);
+        assert_eq!(profile.linear_color_space(), Some(spaces::ADOBE_1998));
+
+        let decoded = profile.decode(Vec3::new(0.0, 0.5, 1.0));
+        assert!((decoded.y - (0.5 as Float).powf(563.0 / 256.0)).abs() < 1e-6);
+        let encoded = profile.encode(decoded);
+        assert!(encoded.abs_diff_eq(Vec3::new(0.0, 0.5, 1.0), 1e-5));
+
+        // The PCS path agrees with converting the matching built-in space.
+        let options = ConversionOptions::default().with_adaptation_method(LmsConeSpace::Bradford);
+        let conversion =
+            ColorConversion::new_with_options(spaces::ADOBE_1998, PCS_COLOR_SPACE, options);
+        let color = Vec3::new(0.2, 0.5, 0.9);
+        let xyz = profile.to_xyz(color);
+        assert!(xyz.abs_diff_eq(conversion.convert(profile.decode(color)), 1e-3));
+
+        // A calibrated display matches no built-in space, but still converts.
+        let mut calibrated = profile.clone();
+        calibrated.colorants.x_axis.x += 0.02;
+        assert_eq!(calibrated.linear_color_space(), None);
+        let xyz = calibrated.to_xyz(color);
+        assert!(calibrated.from_xyz(xyz).abs_diff_eq(color, 1e-5));
+        assert!(calibrated
+            .to_xyz(Vec3::new(0.0, 0.0, 0.0))
+            .abs_diff_eq(Vec3::new(0.0, 0.0, 0.0), 1e-9));
+
+        let table = ToneCurve::Sampled(vec![0.0, 0.25, 1.0]);
+        assert_eq!(table.eval(0.75), 0.625);
+        assert_eq!(table.eval_inverse(0.625), 0.75);
+        assert_eq!(table.eval(2.0), 1.0);
+
+        let curve = ParametricCurve::from_icc_params(2, &[2.0, 1.0, -0.5, 0.1]).unwrap();
+        assert_eq!(curve.eval(0.25), 0.1);
+        assert!((curve.eval(1.0) - 0.35).abs() < 1e-6);
+        assert!((curve.eval_inverse(0.35) - 1.0).abs() < 1e-6);
+        assert_eq!(ParametricCurve::from_icc_params(5, &[1.0; 7]), None);
+        assert_eq!(ParametricCurve::from_icc_params(3, &[1.0; 4]), None);
+    }
+
+    #[test]
+    fn write_round_trip() {
+        for space in color_spaces::ALL_COLOR_SPACES {
+            let profile = match IccProfile::from_color_space(space, "kolor") {
+                Ok(profile) => profile,
+                Err(IccError::NotRgb(_)) => {
+                    assert_eq!(space.primaries(), RgbPrimaries::CieXyz);
+                    continue;
+                }
+                Err(error) => {
+                    assert!(matches!(
+                        space.transform_function(),
+                        TransformFn::IctCpPq | TransformFn::IctCpHlg
+                    ));
+                    assert_eq!(
+                        error,
+                        IccError::UnsupportedTransform(space.transform_function())
+                    );
+                    continue;
+                }
+            };
+            let data = profile.to_bytes();
+            let parsed = IccProfile::parse(&data).unwrap();
+            assert_eq!(parsed.version(), (4, 4));
+            assert_eq!(parsed.description(), Some("kolor"));
+            assert_eq!(parsed.color_space(), Some(space), "{:?}", space);
+            assert!(parsed.rgb_to_xyz().abs_diff_eq(profile.rgb_to_xyz(), 1e-4));
+
+            // The tone curves share one tag.
+            let trc_offset = |i: usize| read_u32(&data, 132 + (7 + i) * 12 + 4).unwrap();
+            assert_eq!(trc_offset(0), trc_offset(2));
+            if space.transform_function() != TransformFn::Pq {
+                assert!(data.len() < 600, "{}", data.len());
+            }
+        }
+
+        let custom = IccProfile::parse(&build_profile(
+            2,
+            spaces::ADOBE_1998,
+            parametric_curve(0, &[2.2]),
+            false,
+        ))
+        .unwrap();
+        let parsed = IccProfile::parse(&custom.to_bytes()).unwrap();
+        assert_eq!(parsed.curves(), custom.curves());
+        assert_eq!(parsed.linear_color_space(), Some(spaces::ADOBE_1998));
+        assert!(parsed
+            .chromatic_adaptation()
+            .abs_diff_eq(custom.chromatic_adaptation(), 1e-4));
+    }
+
+    #[test]
+    fn errors() {
+        let linear = parametric_curve(0, &[1.0]);
+        let profile = build_profile(4, spaces::BT_2020, linear, true);
+        assert_eq!(IccProfile::parse(&profile[..100]), Err(IccError::Truncated));
+
+        let mut invalid = profile.clone();
+        invalid[36] = b'x';
+        assert_eq!(IccProfile::parse(&invalid), Err(IccError::InvalidSignature));
+
+        let mut gray = profile.clone();
+        gray[16..20].copy_from_slice(b"GRAY");
+        assert_eq!(
+            IccProfile::parse(&gray),
+            Err(IccError::UnsupportedColorSpace(*b"GRAY"))
+        );
+
+        let mut missing = profile.clone();
+        missing[132..136].copy_from_slice(b"xXYZ");
+        assert_eq!(
+            IccProfile::parse(&missing),
+            Err(IccError::MissingTag(*b"rXYZ"))
+        );
+
+        let mut lut_curve = profile;
+        let offset = read_u32(&lut_curve, 132 + 3 * 12 + 4).unwrap() as usize;
+        lut_curve[offset..offset + 4].copy_from_slice(b"mft2");
+        assert_eq!(
+            IccProfile::parse(&lut_curve),
+            Err(IccError::InvalidTag(*b"rTRC"))
+        );
+    }
+}
@@ -0,0 +1,1012 @@
+//! 1D and 3D lookup tables, baked from [`ColorConversion`]s or loaded from
+//! files.
+//!
+//! [`Lut3d::bake`] samples a conversion on a regular grid, after mapping the
+//! input through a [`Shaper`] to spend the grid points where they are needed,
+//! for example logarithmically for scene-linear HDR input. The result can be
+//! written as a Resolve/Adobe `.cube` file with [`Lut3d::to_cube`], or as raw
+//! floats for uploading to a 3D texture with [`Lut3d::to_rgba_f32`].
+//!
+//! Sampling a LUT interpolates between grid points, which is only an
+//! approximation of the conversion. [`Lut3d::max_error`] measures how far off
+//! it is.
+//!
+//! LUTs from other applications can be loaded from `.cube` files with
+//! [`Lut::parse_cube`] and from `.3dl` files with [`Lut::parse_3dl`]. A
+//! [`LutConversion`] declares which [`ColorSpace`]s a LUT converts between,
+//! so that it can be chained with kolor's own conversions.
+use super::{
+    color::{Color, ColorSpace},
+    conversion::ColorConversion,
+};
+use crate::{Float, Vec3};
+use std::fmt::Write;
+
+/// The number of entries in the 1D shaper written before the 3D table by
+/// [`Lut3d::to_cube`] for non-linear shapers.
+const CUBE_SHAPER_SIZE: usize = 4096;
+
+/// Maps input colors to coordinates in the grid of a [`Lut3d`], where
+/// `0.0..=1.0` covers the grid. Input outside of the range of the shaper is
+/// clamped to the edges of the grid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shaper {
+    /// Maps each component linearly from `min..=max`.
+    Linear { min: Vec3, max: Vec3 },
+    /// Maps each component logarithmically from `min..=max`, which must be
+    /// positive. Suitable for scene-linear input spanning many stops.
+    Log2 { min: Float, max: Float },
+    /// Maps each component through a 1D LUT whose output is the grid
+    /// coordinate, as used by Resolve `.cube` files with a shaper. The LUT
+    /// must be increasing.
+    Lut1d(Lut1d),
+}
+
+impl Default for Shaper {
+    /// A linear mapping from `0.0..=1.0`.
+    fn default() -> Self {
+        Shaper::Linear {
+            min: Vec3::splat(0.0),
+            max: Vec3::splat(1.0),
+        }
+    }
+}
+
+impl Shaper {
+    /// Returns the grid coordinates of an input color.
+    pub fn to_grid(&self, color: Vec3) -> Vec3 {
+        match *self {
+            Shaper::Linear { min, max } => Vec3::new(
+                (color.x - min.x) / (max.x - min.x),
+                (color.y - min.y) / (max.y - min.y),
+                (color.z - min.z) / (max.z - min.z),
+            ),
+            Shaper::Log2 { min, max } => {
+                let (log_min, log_max) = (min.log2(), max.log2());
+                let shape = |value: Float| (value.max(min).log2() - log_min) / (log_max - log_min);
+                Vec3::new(shape(color.x), shape(color.y), shape(color.z))
+            }
+            Shaper::Lut1d(ref lut) => lut.apply(color),
+        }
+    }
+
+    /// Returns the input color at grid coordinates, the inverse of
+    /// [`to_grid`](Self::to_grid).
+    pub fn from_grid(&self, coords: Vec3) -> Vec3 {
+        match *self {
+            Shaper::Linear { min, max } => min + (max - min) * coords,
+            Shaper::Log2 { min, max } => {
+                let (log_min, log_max) = (min.log2(), max.log2());
+                let unshape = |coord: Float| (log_min + (log_max - log_min) * coord).exp2();
+                Vec3::new(unshape(coords.x), unshape(coords.y), unshape(coords.z))
+            }
+            Shaper::Lut1d(ref lut) => lut.invert(coords),
+        }
+    }
+
+    /// Returns the range of input values covered by the shaper.
+    fn input_range(&self) -> (Vec3, Vec3) {
+        match *self {
+            Shaper::Linear { min, max } => (min, max),
+            Shaper::Log2 { min, max } => (Vec3::splat(min), Vec3::splat(max)),
+            Shaper::Lut1d(ref lut) => (lut.domain_min, lut.domain_max),
+        }
+    }
+}
+
+/// How a [`Lut3d`] interpolates between its grid points.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Interpolation {
+    /// Interpolates between the 8 corners of the grid cell.
+    #[default]
+    Trilinear,
+    /// Interpolates between the 4 corners of the tetrahedron within the grid
+    /// cell containing the input, which preserves the neutral axis and is the
+    /// default in most grading applications.
+    Tetrahedral,
+}
+
+/// A 1D lookup table which maps each component through its own curve, with
+/// the table entries spread evenly over `domain_min..=domain_max`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut1d {
+    domain_min: Vec3,
+    domain_max: Vec3,
+    table: Vec<Vec3>,
+}
+
+impl Lut1d {
+    /// Creates a LUT from its table entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` has fewer than 2 entries.
+    pub fn new(domain_min: Vec3, domain_max: Vec3, table: Vec<Vec3>) -> Self {
+        assert!(
+            table.len() >= 2,
+            "LUT size must be at least 2, got {}",
+            table.len()
+        );
+        Self {
+            domain_min,
+            domain_max,
+            table,
+        }
+    }
+
+    pub fn domain_min(&self) -> Vec3 {
+        self.domain_min
+    }
+
+    pub fn domain_max(&self) -> Vec3 {
+        self.domain_max
+    }
+
+    pub fn table(&self) -> &[Vec3] {
+        &self.table
+    }
+
+    /// Applies the LUT to each component of a color with linear
+    /// interpolation.
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        let channel = |value: Float, min: Float, max: Float, get: fn(Vec3) -> Float| {
+            let (i, t) = grid_cell((value - min) / (max - min), self.table.len());
+            let (a, b) = (get(self.table[i]), get(self.table[i + 1]));
+            a + (b - a) * t
+        };
+        Vec3::new(
+            channel(color.x, self.domain_min.x, self.domain_max.x, |v| v.x),
+            channel(color.y, self.domain_min.y, self.domain_max.y, |v| v.y),
+            channel(color.z, self.domain_min.z, self.domain_max.z, |v| v.z),
+        )
+    }
+
+    /// Returns the input which [`apply`](Self::apply) maps to `color`,
+    /// assuming the table is increasing.
+    fn invert(&self, color: Vec3) -> Vec3 {
+        let last = (self.table.len() - 1) as Float;
+        let channel = |value: Float, min: Float, max: Float, get: fn(Vec3) -> Float| {
+            let upper = self
+                .table
+                .partition_point(|&entry| get(entry) < value)
+                .clamp(1, self.table.len() - 1);
+            let (a, b) = (get(self.table[upper - 1]), get(self.table[upper]));
+            let t = if b > a { (value - a) / (b - a) } else { 0.0 };
+            min + (max - min) * ((upper - 1) as Float + t) / last
+        };
+        Vec3::new(
+            channel(color.x, self.domain_min.x, self.domain_max.x, |v| v.x),
+            channel(color.y, self.domain_min.y, self.domain_max.y, |v| v.y),
+            channel(color.z, self.domain_min.z, self.domain_max.z, |v| v.z),
+        )
+    }
+}
+
+/// The largest difference between a [`Lut3d`] and the conversion it was baked
+/// from, as returned by [`Lut3d::max_error`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InterpolationError {
+    /// The largest absolute difference of any component.
+    pub max_error: Float,
+    /// The input color with the largest error.
+    pub input: Vec3,
+}
+
+/// A 3D lookup table with `size` points along each axis.
+///
+/// The table is stored with the red coordinate changing fastest, then green,
+/// then blue, matching the order of `.cube` files and of the texels of a 3D
+/// texture with red along its width.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    size: usize,
+    shaper: Shaper,
+    table: Vec<Vec3>,
+}
+
+impl Lut3d {
+    /// Creates a LUT from a table of `size³` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is less than 2 or `table` has the wrong length.
+    pub fn new(size: usize, shaper: Shaper, table: Vec<Vec3>) -> Self {
+        assert!(size >= 2, "LUT size must be at least 2, got {}", size);
+        assert_eq!(
+            table.len(),
+            size * size * size,
+            "LUT table must have size³ entries"
+        );
+        Self {
+            size,
+            shaper,
+            table,
+        }
+    }
+
+    /// Samples `conversion` on a grid with `size` points along each axis,
+    /// spaced in the input according to `shaper`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is less than 2.
+    pub fn bake(conversion: &ColorConversion, size: usize, shaper: Shaper) -> Self {
+        assert!(size >= 2, "LUT size must be at least 2, got {}", size);
+        let last = (size - 1) as Float;
+        let mut table = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let coords = Vec3::new(r as Float, g as Float, b as Float) / last;
+                    table.push(conversion.convert(shaper.from_grid(coords)));
+                }
+            }
+        }
+        Self::new(size, shaper, table)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn shaper(&self) -> &Shaper {
+        &self.shaper
+    }
+
+    /// Returns the table, with red changing fastest.
+    pub fn table(&self) -> &[Vec3] {
+        &self.table
+    }
+
+    /// Returns the entry at the given grid indices.
+    pub fn get(&self, r: usize, g: usize, b: usize) -> Vec3 {
+        self.table[r + self.size * (g + self.size * b)]
+    }
+
+    /// Applies the LUT to a color with trilinear interpolation.
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        self.apply_with(color, Interpolation::Trilinear)
+    }
+
+    /// Applies the LUT to a color with the given interpolation.
+    pub fn apply_with(&self, color: Vec3, interpolation: Interpolation) -> Vec3 {
+        let coords = self.shaper.to_grid(color);
+        let (r, fr) = grid_cell(coords.x, self.size);
+        let (g, fg) = grid_cell(coords.y, self.size);
+        let (b, fb) = grid_cell(coords.z, self.size);
+        let corner = |dr: usize, dg: usize, db: usize| self.get(r + dr, g + dg, b + db);
+        match interpolation {
+            Interpolation::Trilinear => {
+                let lerp = |a: Vec3, b: Vec3, t: Float| a + (b - a) * t;
+                let c00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fr);
+                let c10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fr);
+                let c01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fr);
+                let c11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fr);
+                lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+            }
+            Interpolation::Tetrahedral => {
+                // Walks from the first to the last corner of the cell along the
+                // edges of the tetrahedron containing the input, taking the
+                // largest fraction first.
+                let (c000, c111) = (corner(0, 0, 0), corner(1, 1, 1));
+                let (first, second, t1, t2, t3) = if fr > fg {
+                    if fg > fb {
+                        (corner(1, 0, 0), corner(1, 1, 0), fr, fg, fb)
+                    } else if fr > fb {
+                        (corner(1, 0, 0), corner(1, 0, 1), fr, fb, fg)
+                    } else {
+                        (corner(0, 0, 1), corner(1, 0, 1), fb, fr, fg)
+                    }
+                } else if fb > fg {
+                    (corner(0, 0, 1), corner(0, 1, 1), fb, fg, fr)
+                } else if fb > fr {
+                    (corner(0, 1, 0), corner(0, 1, 1), fg, fb, fr)
+                } else {
+                    (corner(0, 1, 0), corner(1, 1, 0), fg, fr, fb)
+                };
+                c000 + (first - c000) * t1 + (second - first) * t2 + (c111 - second) * t3
+            }
+        }
+    }
+
+    /// Compares the LUT against `conversion` at `samples³` points spread
+    /// evenly over the grid, offset so that they fall between grid points
+    /// where the interpolation error is largest.
+    pub fn max_error(&self, conversion: &ColorConversion, samples: usize) -> InterpolationError {
+        let mut result = InterpolationError {
+            max_error: 0.0,
+            input: self.shaper.from_grid(Vec3::splat(0.0)),
+        };
+        let coord = |i: usize| (i as Float + 0.5) / samples as Float;
+        for b in 0..samples {
+            for g in 0..samples {
+                for r in 0..samples {
+                    let input = self
+                        .shaper
+                        .from_grid(Vec3::new(coord(r), coord(g), coord(b)));
+                    let difference = self.apply(input) - conversion.convert(input);
+                    let error = difference
+                        .x
+                        .abs()
+                        .max(difference.y.abs())
+                        .max(difference.z.abs());
+                    if error > result.max_error {
+                        result = InterpolationError {
+                            max_error: error,
+                            input,
+                        };
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Writes the LUT in the `.cube` format used by DaVinci Resolve, Nuke and
+    /// Adobe applications.
+    ///
+    /// A [`Shaper::Linear`] is written as `DOMAIN_MIN`/`DOMAIN_MAX`. Other
+    /// shapers are written as a 1D shaper LUT preceding the 3D LUT, which is
+    /// supported by Resolve.
+    pub fn to_cube(&self, title: Option<&str>) -> String {
+        let mut cube = String::new();
+        if let Some(title) = title {
+            writeln!(cube, "TITLE \"{}\"", title).unwrap();
+        }
+        let (min, max) = self.shaper.input_range();
+        match self.shaper {
+            Shaper::Linear { .. } => {
+                writeln!(cube, "LUT_3D_SIZE {}", self.size).unwrap();
+                writeln!(cube, "DOMAIN_MIN {} {} {}", min.x, min.y, min.z).unwrap();
+                writeln!(cube, "DOMAIN_MAX {} {} {}", max.x, max.y, max.z).unwrap();
+                cube.push('\n');
+            }
+            Shaper::Lut1d(ref lut) if min == Vec3::splat(min.x) && max == Vec3::splat(max.x) => {
+                write_cube_shaper(&mut cube, min.x, max.x, self.size, lut.table.len());
+                for &entry in &lut.table {
+                    write_cube_entry(&mut cube, entry);
+                }
+            }
+            // `.cube` shapers share one input range between channels, so
+            // shapers with a different domain per channel are resampled over
+            // the union of the domains.
+            Shaper::Log2 { .. } | Shaper::Lut1d(_) => {
+                let min = min.x.min(min.y).min(min.z);
+                let max = max.x.max(max.y).max(max.z);
+                write_cube_shaper(&mut cube, min, max, self.size, CUBE_SHAPER_SIZE);
+                let last = (CUBE_SHAPER_SIZE - 1) as Float;
+                for i in 0..CUBE_SHAPER_SIZE {
+                    let input = min + (max - min) * (i as Float / last);
+                    write_cube_entry(&mut cube, self.shaper.to_grid(Vec3::splat(input)));
+                }
+            }
+        }
+        for &entry in &self.table {
+            write_cube_entry(&mut cube, entry);
+        }
+        cube
+    }
+
+    /// Returns the table as RGB floats with red changing fastest, for
+    /// uploading to a 3D texture.
+    pub fn to_rgb_f32(&self) -> Vec<f32> {
+        self.table
+            .iter()
+            .flat_map(|entry| [entry.x as f32, entry.y as f32, entry.z as f32])
+            .collect()
+    }
+
+    /// Returns the table as RGBA floats with an alpha of 1, for uploading to
+    /// 3D texture formats without a 3-component variant.
+    pub fn to_rgba_f32(&self) -> Vec<f32> {
+        self.table
+            .iter()
+            .flat_map(|entry| [entry.x as f32, entry.y as f32, entry.z as f32, 1.0])
+            .collect()
+    }
+}
+
+/// Returned when a LUT file can't be parsed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParseLutError {
+    /// The line with the given 1-based number is malformed.
+    InvalidLine(usize),
+    /// The size of the LUT is missing, less than 2 or too large to allocate.
+    InvalidSize,
+    /// The number of table entries doesn't match the size of the LUT.
+    WrongEntryCount { expected: usize, found: usize },
+}
+
+impl core::fmt::Display for ParseLutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParseLutError::InvalidLine(line) => write!(f, "invalid LUT data on line {}", line),
+            ParseLutError::InvalidSize => write!(f, "missing or invalid LUT size"),
+            ParseLutError::WrongEntryCount { expected, found } => {
+                write!(f, "expected {} LUT entries, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseLutError {}
+
+/// A 1D or 3D lookup table, as loaded from a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lut {
+    Lut1d(Lut1d),
+    Lut3d(Lut3d),
+}
+
+impl Lut {
+    /// Applies the LUT to a color. `interpolation` only affects 3D LUTs.
+    pub fn apply_with(&self, color: Vec3, interpolation: Interpolation) -> Vec3 {
+        match self {
+            Lut::Lut1d(lut) => lut.apply(color),
+            Lut::Lut3d(lut) => lut.apply_with(color, interpolation),
+        }
+    }
+
+    /// Parses a `.cube` file, as written by Resolve, Nuke and Adobe
+    /// applications, containing a 1D LUT, a 3D LUT, or a 1D shaper followed by
+    /// a 3D LUT as written by Resolve.
+    pub fn parse_cube(text: &str) -> Result<Lut, ParseLutError> {
+        let mut size_1d = None;
+        let mut size_3d = None;
+        let mut domain_min = Vec3::splat(0.0);
+        let mut domain_max = Vec3::splat(1.0);
+        let mut range_1d = None;
+        let mut range_3d = None;
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let invalid = || ParseLutError::InvalidLine(index + 1);
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) if !keyword.starts_with('#') => keyword,
+                _ => continue,
+            };
+            let values = tokens.map(|token| token.parse::<Float>().map_err(|_| invalid()));
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                    let size = line.trim_start()[keyword.len()..]
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| invalid())?;
+                    if keyword == "LUT_1D_SIZE" {
+                        size_1d = Some(size);
+                    } else {
+                        size_3d = Some(size);
+                    }
+                }
+                "DOMAIN_MIN" => domain_min = parse_vec3(values).ok_or_else(invalid)?,
+                "DOMAIN_MAX" => domain_max = parse_vec3(values).ok_or_else(invalid)?,
+                "LUT_1D_INPUT_RANGE" => range_1d = Some(parse_range(values).ok_or_else(invalid)?),
+                "LUT_3D_INPUT_RANGE" => range_3d = Some(parse_range(values).ok_or_else(invalid)?),
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+                _ => {
+                    let first = keyword.parse::<Float>().map_err(|_| invalid())?;
+                    let mut rest = values;
+                    let entry = match (rest.next(), rest.next(), rest.next()) {
+                        (Some(y), Some(z), None) => Vec3::new(first, y?, z?),
+                        _ => return Err(invalid()),
+                    };
+                    entries.push(entry);
+                }
+            }
+        }
+
+        let range_domain = |range: Option<(Float, Float)>| match range {
+            Some((min, max)) => (Vec3::splat(min), Vec3::splat(max)),
+            None => (domain_min, domain_max),
+        };
+        let invalid_size = |size: Option<usize>| size.is_some_and(|size| size < 2);
+        if (size_1d.is_none() && size_3d.is_none())
+            || invalid_size(size_1d)
+            || invalid_size(size_3d)
+        {
+            return Err(ParseLutError::InvalidSize);
+        }
+        // The sizes come from untrusted files, so guard against overflow.
+        let expected = size_3d
+            .map_or(Some(0), |size| size.checked_mul(size)?.checked_mul(size))
+            .and_then(|entries_3d| entries_3d.checked_add(size_1d.unwrap_or(0)))
+            .ok_or(ParseLutError::InvalidSize)?;
+        if entries.len() != expected {
+            return Err(ParseLutError::WrongEntryCount {
+                expected,
+                found: entries.len(),
+            });
+        }
+        let table_3d = entries.split_off(size_1d.unwrap_or(0));
+        Ok(match (size_1d, size_3d) {
+            (Some(_), None) => {
+                let (min, max) = range_domain(range_1d);
+                Lut::Lut1d(Lut1d::new(min, max, entries))
+            }
+            (None, Some(size)) => {
+                let (min, max) = range_domain(range_3d);
+                Lut::Lut3d(Lut3d::new(size, Shaper::Linear { min, max }, table_3d))
+            }
+            (Some(_), Some(size)) => {
+                // The shaper outputs the input of the 3D LUT, so normalize it
+                // to grid coordinates.
+                let (min_3d, max_3d) = range_3d.unwrap_or((0.0, 1.0));
+                let shaper_table = entries
+                    .into_iter()
+                    .map(|entry| (entry - Vec3::splat(min_3d)) / (max_3d - min_3d))
+                    .collect();
+                let (min, max) = range_domain(range_1d);
+                let shaper = Shaper::Lut1d(Lut1d::new(min, max, shaper_table));
+                Lut::Lut3d(Lut3d::new(size, shaper, table_3d))
+            }
+            (None, None) => unreachable!(),
+        })
+    }
+
+    /// Parses a `.3dl` file, as used by Autodesk Lustre and Flame.
+    ///
+    /// The first line of numbers gives the input values of the grid points,
+    /// which are assumed to be evenly spaced. The bit depths of the input and
+    /// output values are taken from a `Mesh` header if present, and otherwise
+    /// inferred from the largest value.
+    pub fn parse_3dl(text: &str) -> Result<Lut, ParseLutError> {
+        let mut mesh: Option<Vec<Float>> = None;
+        let mut output_bits = None;
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let invalid = || ParseLutError::InvalidLine(index + 1);
+            let mut tokens = line.split_whitespace().peekable();
+            match tokens.peek() {
+                None => continue,
+                Some(token) if token.starts_with('#') => continue,
+                Some(&"Mesh") => {
+                    let bits = tokens.nth(2).and_then(|token| token.parse::<u32>().ok());
+                    output_bits = Some(
+                        bits.filter(|bits| (1..=32).contains(bits))
+                            .ok_or_else(invalid)?,
+                    );
+                    continue;
+                }
+                Some(token)
+                    if token.parse::<Float>().is_err()
+                        && token.contains(|c: char| c.is_ascii_alphabetic()) =>
+                {
+                    continue
+                }
+                _ => {}
+            }
+            let values = tokens
+                .map(|token| token.parse::<Float>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>, _>>()?;
+            if mesh.is_none() {
+                if values.len() < 2 {
+                    return Err(invalid());
+                }
+                mesh = Some(values);
+            } else if values.len() == 3 {
+                entries.push(Vec3::from_slice(&values));
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        let mesh = mesh.ok_or(ParseLutError::InvalidSize)?;
+        let size = mesh.len();
+        let expected = size
+            .checked_mul(size)
+            .and_then(|square| square.checked_mul(size))
+            .ok_or(ParseLutError::InvalidSize)?;
+        if entries.len() != expected {
+            return Err(ParseLutError::WrongEntryCount {
+                expected,
+                found: entries.len(),
+            });
+        }
+        let input_max = bit_depth_max(mesh[size - 1]);
+        let output_max = match output_bits {
+            Some(bits) => ((1u64 << bits) - 1) as Float,
+            None => bit_depth_max(entries.iter().fold(0.0, |max: Float, entry| {
+                max.max(entry.x).max(entry.y).max(entry.z)
+            })),
+        };
+        // .3dl files have blue changing fastest.
+        let mut table = Vec::with_capacity(expected);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push(entries[(r * size + g) * size + b] / output_max);
+                }
+            }
+        }
+        let shaper = Shaper::Linear {
+            min: Vec3::splat(mesh[0] / input_max),
+            max: Vec3::splat(mesh[size - 1] / input_max),
+        };
+        Ok(Lut::Lut3d(Lut3d::new(size, shaper, table)))
+    }
+}
+
+/// A LUT declared as the conversion from `src_space` to `dst_space`, so that
+/// it can be chained with [`ColorConversion`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LutConversion {
+    lut: Lut,
+    src_space: ColorSpace,
+    dst_space: ColorSpace,
+    interpolation: Interpolation,
+}
+
+impl LutConversion {
+    pub fn new(lut: Lut, src_space: ColorSpace, dst_space: ColorSpace) -> Self {
+        Self {
+            lut,
+            src_space,
+            dst_space,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn lut(&self) -> &Lut {
+        &self.lut
+    }
+
+    pub fn src_space(&self) -> ColorSpace {
+        self.src_space
+    }
+
+    pub fn dst_space(&self) -> ColorSpace {
+        self.dst_space
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Applies the LUT to a color in `src_space`, returning a color in
+    /// `dst_space`.
+    pub fn convert(&self, color: Vec3) -> Vec3 {
+        self.lut.apply_with(color, self.interpolation)
+    }
+
+    /// Converts `color` into `src_space` if needed and applies the LUT,
+    /// returning a color in `dst_space`.
+    pub fn apply(&self, color: Color) -> Color {
+        let color = if color.space == self.src_space {
+            color
+        } else {
+            color.to(self.src_space)
+        };
+        Color {
+            value: self.convert(color.value),
+            space: self.dst_space,
+        }
+    }
+}
+
+fn parse_vec3(mut values: impl Iterator<Item = Result<Float, ParseLutError>>) -> Option<Vec3> {
+    match (values.next(), values.next(), values.next(), values.next()) {
+        (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), None) => Some(Vec3::new(x, y, z)),
+        _ => None,
+    }
+}
+
+fn parse_range(
+    mut values: impl Iterator<Item = Result<Float, ParseLutError>>,
+) -> Option<(Float, Float)> {
+    match (values.next(), values.next(), values.next()) {
+        (Some(Ok(min)), Some(Ok(max)), None) => Some((min, max)),
+        _ => None,
+    }
+}
+
+/// Returns the largest value of the smallest integer bit depth that can hold
+/// `value`.
+fn bit_depth_max(value: Float) -> Float {
+    let mut bits = 1;
+    while bits < 32 && (((1u64 << bits) - 1) as Float) < value {
+        bits += 1;
+    }
+    ((1u64 << bits) - 1) as Float
+}
+
+fn write_cube_shaper(cube: &mut String, min: Float, max: Float, size: usize, shaper_size: usize) {
+    writeln!(cube, "LUT_1D_SIZE {}", shaper_size).unwrap();
+    writeln!(cube, "LUT_1D_INPUT_RANGE {} {}", min, max).unwrap();
+    writeln!(cube, "LUT_3D_SIZE {}", size).unwrap();
+    cube.push('\n');
+}
+
+fn write_cube_entry(cube: &mut String, entry: Vec3) {
+    // Round first so that tiny negative values aren't written as `-0.000000`.
+    let value = |v: Float| (v * 1e6).round() / 1e6 + 0.0;
+    writeln!(
+        cube,
+        "{:.6} {:.6} {:.6}",
+        value(entry.x),
+        value(entry.y),
+        value(entry.z)
+    )
+    .unwrap();
+}
+
+/// Returns the index of the grid cell containing `coord` and the position
+/// within it, clamping to the edges of the grid.
+fn grid_cell(coord: Float, size: usize) -> (usize, Float) {
+    let last = (size - 1) as Float;
+    let scaled = if coord > 0.0 {
+        (coord * last).min(last)
+    } else {
+        0.0
+    };
+    let index = (scaled as usize).min(size - 2);
+    (index, scaled - index as Float)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    #[test]
+    fn bake_and_apply() {
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::ACES_CG);
+        let lut = Lut3d::bake(&conversion, 5, Shaper::default());
+        assert_eq!(lut.table().len(), 125);
+        assert_eq!(
+            lut.get(4, 0, 0),
+            conversion.convert(Vec3::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            lut.get(0, 2, 0),
+            conversion.convert(Vec3::new(0.0, 0.5, 0.0))
+        );
+
+        // A linear conversion is reproduced exactly by trilinear interpolation.
+        let color = Vec3::new(0.3, 0.7, 0.1);
+        assert!(lut
+            .apply(color)
+            .abs_diff_eq(conversion.convert(color), 1e-5));
+        assert!(lut.max_error(&conversion, 8).max_error < 1e-5);
+        assert_eq!(
+            lut.apply(Vec3::new(2.0, -1.0, 1.0)),
+            lut.get(4, 0, 4),
+            "input is clamped to the grid"
+        );
+    }
+
+    #[test]
+    fn interpolation_error() {
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::ENCODED_SRGB);
+        let coarse = Lut3d::bake(&conversion, 9, Shaper::default());
+        let fine = Lut3d::bake(&conversion, 65, Shaper::default());
+        let coarse_error = coarse.max_error(&conversion, 16);
+        let fine_error = fine.max_error(&conversion, 16);
+        assert!(fine_error.max_error < coarse_error.max_error);
+        assert!(coarse_error.max_error > 0.01);
+
+        let shaper = Shaper::Log2 {
+            min: 1.0 / 1024.0,
+            max: 16.0,
+        };
+        let lut = Lut3d::bake(&conversion, 33, shaper.clone());
+        let input = Vec3::new(0.01, 1.0, 16.0);
+        assert!(shaper
+            .from_grid(shaper.to_grid(input))
+            .abs_diff_eq(input, 1e-4));
+        assert_eq!(lut.get(32, 32, 32), conversion.convert(Vec3::splat(16.0)));
+    }
+
+    #[test]
+    fn cube_export() {
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::ENCODED_SRGB);
+        let lut = Lut3d::bake(&conversion, 2, Shaper::default());
+        let cube = lut.to_cube(Some("sRGB"));
+        let mut lines = cube.lines();
+        assert_eq!(lines.next(), Some("TITLE \"sRGB\""));
+        assert_eq!(lines.next(), Some("LUT_3D_SIZE 2"));
+        assert_eq!(lines.next(), Some("DOMAIN_MIN 0 0 0"));
+        assert_eq!(lines.next(), Some("DOMAIN_MAX 1 1 1"));
+        assert_eq!(lines.next(), Some(""));
+        assert_eq!(lines.next(), Some("0.000000 0.000000 0.000000"));
+        assert_eq!(lines.next(), Some("1.000000 0.000000 0.000000"));
+        assert_eq!(lines.count(), 6);
+
+        let shaped = Lut3d::bake(
+            &conversion,
+            2,
+            Shaper::Log2 {
+                min: 0.25,
+                max: 4.0,
+            },
+        );
+        let cube = shaped.to_cube(None);
+        assert!(cube.starts_with("LUT_1D_SIZE 4096\nLUT_1D_INPUT_RANGE 0.25 4\nLUT_3D_SIZE 2\n"));
+        assert_eq!(cube.lines().count(), 4 + 4096 + 8);
+
+        // Per-channel shaper domains are resampled over their union.
+        let shaper = Shaper::Lut1d(Lut1d::new(
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, 1.0),
+            vec![Vec3::splat(0.0), Vec3::splat(1.0)],
+        ));
+        let cube = Lut3d::bake(&conversion, 2, shaper.clone()).to_cube(None);
+        assert!(cube.starts_with("LUT_1D_SIZE 4096\nLUT_1D_INPUT_RANGE -1 2\nLUT_3D_SIZE 2\n"));
+        let Ok(Lut::Lut3d(parsed)) = Lut::parse_cube(&cube) else {
+            panic!("expected a 3D LUT");
+        };
+        for input in [Vec3::new(0.5, 1.0, 0.0), Vec3::new(-0.5, 1.5, 0.5)] {
+            assert!(parsed
+                .shaper()
+                .to_grid(input)
+                .abs_diff_eq(shaper.to_grid(input), 1e-3));
+        }
+
+        let rgba = lut.to_rgba_f32();
+        assert_eq!(rgba.len(), 8 * 4);
+        let red = lut.get(1, 0, 0);
+        assert_eq!(
+            &rgba[4..8],
+            &[red.x as f32, red.y as f32, red.z as f32, 1.0]
+        );
+        assert_eq!(lut.to_rgb_f32().len(), 8 * 3);
+    }
+
+    #[test]
+    fn tetrahedral_interpolation() {
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::ACES_CG);
+        let lut = Lut3d::bake(&conversion, 3, Shaper::default());
+        let color = Vec3::new(0.3, 0.7, 0.1);
+        let tetrahedral = lut.apply_with(color, Interpolation::Tetrahedral);
+        assert!(tetrahedral.abs_diff_eq(conversion.convert(color), 1e-5));
+
+        // Neutral inputs only use the corners on the diagonal of the cell.
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::OK_LAB);
+        let lut = Lut3d::bake(&conversion, 3, Shaper::default());
+        let grey = lut.apply_with(Vec3::splat(0.2), Interpolation::Tetrahedral);
+        let expected = lut.get(0, 0, 0) + (lut.get(1, 1, 1) - lut.get(0, 0, 0)) * 0.4;
+        assert!(grey.abs_diff_eq(expected, 1e-6));
+        for color in [Vec3::new(0.9, 0.6, 0.2), Vec3::new(0.1, 0.8, 0.95)] {
+            let trilinear = lut.apply(color);
+            let tetrahedral = lut.apply_with(color, Interpolation::Tetrahedral);
+            assert!(tetrahedral.abs_diff_eq(trilinear, 0.1));
+            assert_ne!(tetrahedral, trilinear);
+        }
+    }
+
+    #[test]
+    fn parse_cube() {
+        let cube = "# comment
+TITLE \"Invert\"
+LUT_1D_SIZE 3
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 2 2 2
+
+1 1 1
+0.5 0.5 0.5
+0 0 0
+";
+        let Lut::Lut1d(lut) = Lut::parse_cube(cube).unwrap() else {
+            panic!("expected a 1D LUT");
+        };
+        assert_eq!(lut.table().len(), 3);
+        assert_eq!(
+            lut.apply(Vec3::new(0.0, 1.5, 2.0)),
+            Vec3::new(1.0, 0.25, 0.0)
+        );
+
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::ENCODED_SRGB);
+        let shapers = [
+            Shaper::Linear {
+                min: Vec3::splat(-0.5),
+                max: Vec3::new(1.0, 2.0, 4.0),
+            },
+            Shaper::Log2 {
+                min: 1.0 / 256.0,
+                max: 4.0,
+            },
+        ];
+        for shaper in shapers {
+            let baked = Lut3d::bake(&conversion, 9, shaper);
+            let Lut::Lut3d(parsed) = Lut::parse_cube(&baked.to_cube(Some("sRGB"))).unwrap() else {
+                panic!("expected a 3D LUT");
+            };
+            assert_eq!(parsed.size(), 9);
+            for (a, b) in parsed.table().iter().zip(baked.table()) {
+                assert!(a.abs_diff_eq(*b, 1e-6));
+            }
+            for color in [Vec3::new(0.01, 0.5, 0.9), Vec3::new(1.0, 0.02, 0.3)] {
+                assert!(parsed.apply(color).abs_diff_eq(baked.apply(color), 1e-3));
+            }
+            let input = Vec3::new(0.05, 0.5, 1.5);
+            let grid = parsed.shaper().to_grid(input);
+            assert!(parsed.shaper().from_grid(grid).abs_diff_eq(input, 1e-3));
+        }
+
+        let mismatched = "LUT_3D_SIZE 2\n0 0 0\n1 1 1\n";
+        assert_eq!(
+            Lut::parse_cube(mismatched),
+            Err(ParseLutError::WrongEntryCount {
+                expected: 8,
+                found: 2
+            })
+        );
+        assert_eq!(
+            Lut::parse_cube("LUT_3D_SIZE 2\n0 0\n"),
+            Err(ParseLutError::InvalidLine(2))
+        );
+        assert_eq!(Lut::parse_cube("0 0 0\n"), Err(ParseLutError::InvalidSize));
+        assert_eq!(
+            Lut::parse_cube("LUT_3D_SIZE 3000000\n0 0 0\n"),
+            Err(ParseLutError::InvalidSize)
+        );
+        assert_eq!(
+            Lut::parse_cube(&format!("LUT_1D_SIZE {}\nLUT_3D_SIZE 2\n", usize::MAX)),
+            Err(ParseLutError::InvalidSize)
+        );
+        assert_eq!(
+            Lut::parse_cube("LUT_3D_SIZE -2\n"),
+            Err(ParseLutError::InvalidLine(1))
+        );
+    }
+
+    #[test]
+    fn parse_3dl() {
+        let mut text = String::from("3DMESH\nMesh 1 12\n0 1023\n");
+        for r in 0..2 {
+            for g in 0..2 {
+                for b in 0..2 {
+                    writeln!(text, "{} {} {}", r * 4095, g * 2048, b * 4095).unwrap();
+                }
+            }
+        }
+        let Lut::Lut3d(lut) = Lut::parse_3dl(&text).unwrap() else {
+            panic!("expected a 3D LUT");
+        };
+        assert_eq!(lut.size(), 2);
+        assert_eq!(lut.get(1, 0, 0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(lut.get(0, 1, 1), Vec3::new(0.0, 2048.0 / 4095.0, 1.0));
+        for mesh in ["Mesh 0 64", "Mesh 1 0", "Mesh 1", "Mesh 1 twelve"] {
+            let malformed = text.replace("Mesh 1 12", mesh);
+            assert_eq!(
+                Lut::parse_3dl(&malformed),
+                Err(ParseLutError::InvalidLine(2)),
+                "{}",
+                mesh
+            );
+        }
+
+        // Without a Mesh header the bit depth is inferred from the values.
+        let text = text.replace("Mesh 1 12\n", "");
+        assert_eq!(Lut::parse_3dl(&text), Ok(Lut::Lut3d(lut)));
+        assert_eq!(
+            Lut::parse_3dl("0 1023\n0 0 0\n"),
+            Err(ParseLutError::WrongEntryCount {
+                expected: 8,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn lut_conversion() {
+        let conversion = ColorConversion::new(spaces::LINEAR_SRGB, spaces::ACES_CG);
+        let lut = Lut::Lut3d(Lut3d::bake(&conversion, 2, Shaper::default()));
+        let lut_conversion = LutConversion::new(lut, spaces::LINEAR_SRGB, spaces::ACES_CG)
+            .with_interpolation(Interpolation::Tetrahedral);
+
+        let color = Color::srgb(0.8, 0.4, 0.1);
+        let result = lut_conversion.apply(color);
+        assert_eq!(result.space, spaces::ACES_CG);
+        assert!(result
+            .value
+            .abs_diff_eq(color.to(spaces::ACES_CG).value, 1e-5));
+        let display = result.to(spaces::ENCODED_SRGB);
+        assert!(display.value.abs_diff_eq(color.value, 1e-4));
+    }
+}
@@ -24,8 +24,8 @@ mod math {
     #[cfg(feature = "f64")]
     pub use glam::f64::DVec3 as Vec3;
 
-    #[cfg(all(not(feature = "std"), feature = "libm"))]
-    use num_traits::Float;
+    #[cfg(all(not(feature = "std"), not(test), feature = "libm"))]
+    use num_traits::Float as _;
 
     impl super::Cuberoot for Vec3 {
         #[inline]
@@ -40,11 +40,11 @@ mod math {
 mod math {
     use crate::Float;
     #[cfg(all(not(feature = "std"), feature = "libm"))]
-    use core::ops::{Add, Div, Mul, MulAssign, Sub};
-    #[cfg(all(not(feature = "std"), feature = "libm"))]
-    use num_traits::Float;
+    use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub};
+    #[cfg(all(not(feature = "std"), not(test), feature = "libm"))]
+    use num_traits::Float as _;
     #[cfg(all(not(feature = "libm"), feature = "std"))]
-    use std::ops::{Add, Div, Mul, MulAssign, Sub};
+    use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub};
 
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     #[derive(Debug, Clone, Copy, PartialEq)]
@@ -142,6 +142,12 @@ mod math {
         }
     }
 
+    impl AddAssign for Vec3 {
+        fn add_assign(&mut self, other: Self) {
+            *self = *self + other
+        }
+    }
+
     impl Sub for Vec3 {
         type Output = Self;
 
@@ -247,6 +253,26 @@ mod math {
             }
         }
 
+        pub fn determinant(&self) -> Float {
+            self.x_axis.dot(Vec3::new(
+                self.y_axis.y * self.z_axis.z - self.z_axis.y * self.y_axis.z,
+                self.z_axis.y * self.x_axis.z - self.x_axis.y * self.z_axis.z,
+                self.x_axis.y * self.y_axis.z - self.y_axis.y * self.x_axis.z,
+            ))
+        }
+
+        pub fn abs_diff_eq(&self, other: Self, max_abs_diff: Float) -> bool {
+            self.x_axis.abs_diff_eq(other.x_axis, max_abs_diff)
+                && self.y_axis.abs_diff_eq(other.y_axis, max_abs_diff)
+                && self.z_axis.abs_diff_eq(other.z_axis, max_abs_diff)
+        }
+
+        pub fn is_finite(&self) -> bool {
+            [self.x_axis, self.y_axis, self.z_axis]
+                .iter()
+                .all(|v| v.x.is_finite() && v.y.is_finite() && v.z.is_finite())
+        }
+
         pub fn inverse(&self) -> Self {
             let m00 = self.x_axis.x;
             let m01 = self.y_axis.x;
@@ -265,7 +291,7 @@ mod math {
 
             let inv_det = 1.0 / det;
 
-            let result = Self {
+            Self {
                 x_axis: Vec3::new(
                     (m11 * m22 - m21 * m12) * inv_det,
                     (m12 * m20 - m10 * m22) * inv_det,
@@ -281,9 +307,7 @@ mod math {
                     (m10 * m02 - m00 * m12) * inv_det,
                     (m00 * m11 - m10 * m01) * inv_det,
                 ),
-            };
-
-            result
+            }
         }
     }
 
@@ -0,0 +1,341 @@
+//! OpenColorIO v2 configs generated from kolor color spaces.
+//!
+//! [`OcioConfig`] writes a `config.ocio` file in which every color space
+//! converts to a chosen linear reference space, such as ACES2065-1, so that
+//! OCIO-based applications agree with kolor's conversions. The linear part of
+//! each conversion becomes a `MatrixTransform`, the sRGB and BT.601 transfer
+//! functions become `ExponentWithLinearTransform`s, and PQ uses the built-in
+//! `CURVE - ST-2084_to_LINEAR` transform.
+use super::{
+    color::{color_spaces, ColorSpace, TransformFn},
+    conversion::{ConversionOptions, LinearColorConversion},
+};
+use crate::{Float, Mat3};
+use std::fmt::Write;
+
+/// Returned when an [`OcioConfig`] can't be written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OcioError {
+    /// The reference space must be linear.
+    NonLinearReference(ColorSpace),
+    /// The transform function can't be expressed with OCIO transforms.
+    UnsupportedTransform(TransformFn),
+    /// Two color spaces have the same name.
+    DuplicateName(String),
+    /// A display view refers to a color space which isn't in the config.
+    UnknownColorSpace(String),
+}
+
+impl core::fmt::Display for OcioError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            OcioError::NonLinearReference(space) => {
+                write!(f, "the reference space {:?} is not linear", space)
+            }
+            OcioError::UnsupportedTransform(transform) => {
+                write!(f, "{:?} can't be expressed as OCIO transforms", transform)
+            }
+            OcioError::DuplicateName(name) => write!(f, "duplicate color space name {}", name),
+            OcioError::UnknownColorSpace(name) => write!(f, "unknown color space {}", name),
+        }
+    }
+}
+
+impl std::error::Error for OcioError {}
+
+/// A view of a display in an [`OcioConfig`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct View {
+    display: String,
+    view: String,
+    color_space: String,
+}
+
+/// Builds an OCIO v2 config from named [`ColorSpace`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcioConfig {
+    reference: (String, ColorSpace),
+    color_spaces: Vec<(String, ColorSpace)>,
+    views: Vec<View>,
+    options: ConversionOptions,
+}
+
+impl OcioConfig {
+    /// Creates a config whose reference space is `reference`, which must be
+    /// linear. The reference space is included in the config as `name`.
+    pub fn new(name: &str, reference: ColorSpace) -> Self {
+        Self {
+            reference: (name.to_string(), reference),
+            color_spaces: Vec::new(),
+            views: Vec::new(),
+            options: ConversionOptions::default(),
+        }
+    }
+
+    /// Adds a color space to the config.
+    pub fn with_color_space(mut self, name: &str, space: ColorSpace) -> Self {
+        self.color_spaces.push((name.to_string(), space));
+        self
+    }
+
+    /// Adds a view which shows images in the color space named `color_space`
+    /// on `display`. OCIO applications need at least one display to show
+    /// images.
+    pub fn with_display(mut self, display: &str, view: &str, color_space: &str) -> Self {
+        self.views.push(View {
+            display: display.to_string(),
+            view: view.to_string(),
+            color_space: color_space.to_string(),
+        });
+        self
+    }
+
+    /// Sets the options used to calculate the matrices to the reference
+    /// space, such as the chromatic adaptation method.
+    pub fn with_options(mut self, options: ConversionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Writes the config as `config.ocio` YAML.
+    pub fn to_yaml(&self) -> Result<String, OcioError> {
+        let (reference_name, reference) = &self.reference;
+        if !reference.is_linear() {
+            return Err(OcioError::NonLinearReference(*reference));
+        }
+        let spaces = core::iter::once(&self.reference)
+            .chain(&self.color_spaces)
+            .collect::<Vec<_>>();
+        for (i, (name, _)) in spaces.iter().enumerate() {
+            if spaces[..i].iter().any(|(other, _)| other == name) {
+                return Err(OcioError::DuplicateName(name.clone()));
+            }
+        }
+        for view in &self.views {
+            if !spaces.iter().any(|(name, _)| *name == view.color_space) {
+                return Err(OcioError::UnknownColorSpace(view.color_space.clone()));
+            }
+        }
+
+        let mut yaml = String::from("ocio_profile_version: 2.1\n\n");
+        yaml.push_str("search_path: \"\"\nstrictparsing: true\n\n");
+
+        yaml.push_str("roles:\n");
+        let mut roles = vec![
+            ("reference", reference_name),
+            ("scene_linear", reference_name),
+        ];
+        if let Some((name, _)) = spaces
+            .iter()
+            .find(|(_, space)| *space == color_spaces::ACES_2065_1)
+        {
+            roles.push(("aces_interchange", name));
+        }
+        if let Some((name, _)) = spaces
+            .iter()
+            .find(|(_, space)| *space == color_spaces::CIE_XYZ)
+        {
+            roles.push(("cie_xyz_d65_interchange", name));
+        }
+        roles.sort();
+        for (role, name) in roles {
+            writeln!(yaml, "  {}: {}", role, quote(name)).unwrap();
+        }
+
+        yaml.push_str("\nfile_rules:\n");
+        writeln!(
+            yaml,
+            "  - !<Rule> {{name: Default, colorspace: {}}}",
+            quote(reference_name)
+        )
+        .unwrap();
+
+        yaml.push_str("\ndisplays:");
+        if self.views.is_empty() {
+            yaml.push_str(" {}\n");
+        } else {
+            yaml.push('\n');
+            let mut displays = Vec::<&str>::new();
+            for view in &self.views {
+                if !displays.contains(&view.display.as_str()) {
+                    displays.push(&view.display);
+                }
+            }
+            for display in &displays {
+                writeln!(yaml, "  {}:", quote(display)).unwrap();
+                for view in self.views.iter().filter(|view| view.display == *display) {
+                    writeln!(
+                        yaml,
+                        "    - !<View> {{name: {}, colorspace: {}}}",
+                        quote(&view.view),
+                        quote(&view.color_space)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        yaml.push_str("\ncolorspaces:\n");
+        for (name, space) in spaces {
+            self.write_color_space(&mut yaml, name, *space)?;
+        }
+        Ok(yaml)
+    }
+
+    fn write_color_space(
+        &self,
+        yaml: &mut String,
+        name: &str,
+        space: ColorSpace,
+    ) -> Result<(), OcioError> {
+        let transform_fn = space.transform_function();
+        let (encoding, curve, scale) = match transform_fn {
+            TransformFn::None => ("scene-linear", None, 1.0),
+            TransformFn::Srgb => (
+                "sdr-video",
+                Some("!<ExponentWithLinearTransform> {gamma: 2.4, offset: 0.055}".to_string()),
+                1.0,
+            ),
+            TransformFn::Bt601 => (
+                "sdr-video",
+                Some(format!(
+                    "!<ExponentWithLinearTransform> {{gamma: {}, offset: 0.0993}}",
+                    1.0 / 0.45 as Float
+                )),
+                1.0,
+            ),
+            // The built-in curve is scaled so that 1.0 is 100 nits, while
+            // kolor's is in nits.
+            TransformFn::Pq => (
+                "hdr-video",
+                Some("!<BuiltinTransform> {style: \"CURVE - ST-2084_to_LINEAR\"}".to_string()),
+                100.0,
+            ),
+            transform => return Err(OcioError::UnsupportedTransform(transform)),
+        };
+
+        yaml.push_str("  - !<ColorSpace>\n");
+        writeln!(yaml, "    name: {}", quote(name)).unwrap();
+        writeln!(yaml, "    encoding: {}", encoding).unwrap();
+        yaml.push_str("    isdata: false\n");
+
+        let linear = space.as_linear();
+        let matrix =
+            LinearColorConversion::new_with_options(linear, self.reference.1, self.options)
+                .matrix();
+        let mut transforms = Vec::new();
+        transforms.extend(curve);
+        // Without the `color-matrices` feature the matrix between spaces
+        // with the same primaries and white point is calculated, and only
+        // approximately identity.
+        if !matrix.abs_diff_eq(Mat3::IDENTITY, 1e-6) || scale != 1.0 {
+            transforms.push(matrix_transform(matrix, scale));
+        }
+        match transforms.as_slice() {
+            [] => {}
+            [transform] => writeln!(yaml, "    to_scene_reference: {}", transform).unwrap(),
+            transforms => {
+                yaml.push_str("    to_scene_reference: !<GroupTransform>\n");
+                yaml.push_str("      children:\n");
+                for transform in transforms {
+                    writeln!(yaml, "        - {}", transform).unwrap();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns a `MatrixTransform` applying `matrix` scaled by `scale`, as the
+/// row-major 4×4 matrix OCIO expects.
+fn matrix_transform(matrix: Mat3, scale: Float) -> String {
+    let rows = [
+        [matrix.x_axis.x, matrix.y_axis.x, matrix.z_axis.x],
+        [matrix.x_axis.y, matrix.y_axis.y, matrix.z_axis.y],
+        [matrix.x_axis.z, matrix.y_axis.z, matrix.z_axis.z],
+    ];
+    let mut values = Vec::with_capacity(16);
+    for row in rows {
+        values.extend(row.map(|value| (value * scale).to_string()));
+        values.push("0".to_string());
+    }
+    values.extend(["0", "0", "0", "1"].map(str::to_string));
+    format!("!<MatrixTransform> {{matrix: [{}]}}", values.join(", "))
+}
+
+/// Quotes a string for YAML.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    #[test]
+    fn aces_config() {
+        let config = OcioConfig::new("ACES2065-1", spaces::ACES_2065_1)
+            .with_color_space("ACEScg", spaces::ACES_CG)
+            .with_color_space("sRGB - Texture", spaces::ENCODED_SRGB)
+            .with_color_space("Rec.2100-PQ", spaces::ENCODED_BT_2100_PQ)
+            .with_color_space("CIE-XYZ-D65", spaces::CIE_XYZ)
+            .with_display("sRGB", "Standard", "sRGB - Texture");
+        let yaml = config.to_yaml().unwrap();
+
+        assert!(yaml.starts_with("ocio_profile_version: 2.1\n"));
+        assert!(yaml.contains("  aces_interchange: \"ACES2065-1\"\n"));
+        assert!(yaml.contains("  cie_xyz_d65_interchange: \"CIE-XYZ-D65\"\n"));
+        assert!(yaml.contains("  reference: \"ACES2065-1\"\n"));
+        assert!(yaml.contains(
+            "displays:\n  \"sRGB\":\n    - !<View> {name: \"Standard\", colorspace: \"sRGB - Texture\"}\n"
+        ));
+        assert!(yaml.contains("    name: \"ACES2065-1\"\n    encoding: scene-linear\n    isdata: false\n  - !<ColorSpace>\n"));
+
+        let matrix = LinearColorConversion::new(spaces::ACES_CG, spaces::ACES_2065_1).matrix();
+        let expected = format!(
+            "    to_scene_reference: !<MatrixTransform> {{matrix: [{}, {}, {}, 0, ",
+            matrix.x_axis.x, matrix.y_axis.x, matrix.z_axis.x
+        );
+        assert!(yaml.contains(&expected), "{}", yaml);
+
+        assert!(yaml.contains(
+            "    encoding: sdr-video\n    isdata: false\n    to_scene_reference: !<GroupTransform>\n      children:\n        - !<ExponentWithLinearTransform> {gamma: 2.4, offset: 0.055}\n        - !<MatrixTransform>"
+        ));
+        assert!(
+            yaml.contains("        - !<BuiltinTransform> {style: \"CURVE - ST-2084_to_LINEAR\"}\n")
+        );
+
+        // The PQ matrix is scaled from 100 nits to kolor's nits.
+        let matrix = LinearColorConversion::new(spaces::BT_2020, spaces::ACES_2065_1).matrix();
+        assert!(yaml.contains(&format!("{{matrix: [{}, ", matrix.x_axis.x * 100.0)));
+    }
+
+    #[test]
+    fn config_errors() {
+        let config = OcioConfig::new("sRGB", spaces::ENCODED_SRGB);
+        assert_eq!(
+            config.to_yaml(),
+            Err(OcioError::NonLinearReference(spaces::ENCODED_SRGB))
+        );
+        let config = OcioConfig::new("ACEScg", spaces::ACES_CG);
+        assert_eq!(
+            config
+                .clone()
+                .with_color_space("Oklab", spaces::OK_LAB)
+                .to_yaml(),
+            Err(OcioError::UnsupportedTransform(TransformFn::OkLab))
+        );
+        assert_eq!(
+            config
+                .clone()
+                .with_color_space("ACEScg", spaces::ACES_CG)
+                .to_yaml(),
+            Err(OcioError::DuplicateName("ACEScg".into()))
+        );
+        assert_eq!(
+            config.with_display("sRGB", "Standard", "sRGB").to_yaml(),
+            Err(OcioError::UnknownColorSpace("sRGB".into()))
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Packed pixel formats, as used by GPU textures.
+//!
+//! Each format is a newtype around its packed representation which
+//! implements [`PixelFormat`] to decode into a color and alpha, and to encode
+//! back. Encoding rounds to the nearest representable value and clamps values
+//! outside of the format's range, following the D3D and Vulkan conversion
+//! rules.
+//!
+//! The packed layouts put the first channel in the least significant bits,
+//! matching `DXGI_FORMAT_R10G10B10A2_UNORM`, `DXGI_FORMAT_R11G11B10_FLOAT` and
+//! `DXGI_FORMAT_R9G9B9E5_SHAREDEXP` (`A2B10G10R10_UNORM_PACK32`,
+//! `B10G11R11_UFLOAT_PACK32` and `E5B9G9R9_UFLOAT_PACK32` in Vulkan).
+//!
+//! [`ColorConversion::convert_pixels`] and
+//! [`ColorConversion::convert_pixels_into`] convert buffers of pixels.
+//!
+//! [`ColorConversion::convert_pixels`]: super::conversion::ColorConversion::convert_pixels
+//! [`ColorConversion::convert_pixels_into`]: super::conversion::ColorConversion::convert_pixels_into
+use crate::{Float, Vec3};
+
+/// A packed pixel representation which can be decoded into a color and an
+/// alpha value.
+pub trait PixelFormat: Copy {
+    /// Decodes the pixel into a color and alpha. Formats without alpha
+    /// decode to an alpha of 1.
+    fn decode(self) -> (Vec3, Float);
+    /// Encodes a color and alpha into a pixel. Formats without alpha ignore
+    /// `alpha`.
+    fn encode(color: Vec3, alpha: Float) -> Self;
+}
+
+/// 8-bit unsigned normalized RGBA.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Rgba8Unorm(pub [u8; 4]);
+
+/// 16-bit unsigned normalized RGBA.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Rgba16Unorm(pub [u16; 4]);
+
+/// 10-bit unsigned normalized RGB with 2-bit alpha.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Rgb10A2Unorm(pub u32);
+
+/// Unsigned floats with 11 bits for red and green and 10 bits for blue, each
+/// with a 5-bit exponent and no sign bit. Has no alpha.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Rg11B10Float(pub u32);
+
+/// Unsigned 9-bit mantissas for RGB sharing a 5-bit exponent. Has no alpha.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Rgb9E5(pub u32);
+
+/// Converts a value in `0.0..=1.0` to an unsigned normalized integer with
+/// `max` as its largest value, rounding to nearest. NaN encodes as 0.
+pub(crate) fn encode_unorm(value: Float, max: u32) -> u32 {
+    if value.is_nan() || value <= 0.0 {
+        return 0;
+    }
+    (value.min(1.0) * max as Float + 0.5) as u32
+}
+
+pub(crate) fn decode_unorm(value: u32, max: u32) -> Float {
+    value as Float / max as Float
+}
+
+impl PixelFormat for Rgba8Unorm {
+    fn decode(self) -> (Vec3, Float) {
+        let [r, g, b, a] = self.0.map(|c| decode_unorm(c as u32, 255));
+        (Vec3::new(r, g, b), a)
+    }
+
+    fn encode(color: Vec3, alpha: Float) -> Self {
+        Self([color.x, color.y, color.z, alpha].map(|c| encode_unorm(c, 255) as u8))
+    }
+}
+
+impl PixelFormat for Rgba16Unorm {
+    fn decode(self) -> (Vec3, Float) {
+        let [r, g, b, a] = self.0.map(|c| decode_unorm(c as u32, 65535));
+        (Vec3::new(r, g, b), a)
+    }
+
+    fn encode(color: Vec3, alpha: Float) -> Self {
+        Self([color.x, color.y, color.z, alpha].map(|c| encode_unorm(c, 65535) as u16))
+    }
+}
+
+impl PixelFormat for Rgb10A2Unorm {
+    fn decode(self) -> (Vec3, Float) {
+        let channel = |shift: u32| decode_unorm((self.0 >> shift) & 0x3ff, 1023);
+        let alpha = decode_unorm(self.0 >> 30, 3);
+        (Vec3::new(channel(0), channel(10), channel(20)), alpha)
+    }
+
+    fn encode(color: Vec3, alpha: Float) -> Self {
+        Self(
+            encode_unorm(color.x, 1023)
+                | encode_unorm(color.y, 1023) << 10
+                | encode_unorm(color.z, 1023) << 20
+                | encode_unorm(alpha, 3) << 30,
+        )
+    }
+}
+
+/// Returns `2^exponent` for exponents in the normal range of `f32`.
+fn exp2i(exponent: i32) -> f32 {
+    f32::from_bits(((exponent + 127) as u32) << 23)
+}
+
+/// Encodes a value as an unsigned float with a 5-bit exponent and
+/// `mantissa_bits` bits of mantissa, rounding to nearest even. Negative values
+/// encode as 0 and values too large to represent as the largest finite value.
+fn encode_small_float(value: Float, mantissa_bits: u32) -> u32 {
+    let value = value as f32;
+    let exponent_mask = 0x1f << mantissa_bits;
+    if value.is_nan() {
+        return exponent_mask | 1;
+    }
+    if value == f32::INFINITY {
+        return exponent_mask;
+    }
+    if value <= 0.0 {
+        return 0;
+    }
+    let max_finite = exponent_mask - 1;
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent >= 31 {
+        return max_finite;
+    }
+    let encoded = if exponent <= 0 {
+        // Denormal, the result of rounding may carry into the smallest normal.
+        (value * exp2i(14 + mantissa_bits as i32) + 0.5) as u32
+    } else {
+        let shift = 23 - mantissa_bits;
+        let mantissa = bits & 0x7f_ffff;
+        let truncated = ((exponent as u32) << mantissa_bits) | mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        if remainder > half || (remainder == half && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+    encoded.min(max_finite)
+}
+
+fn decode_small_float(bits: u32, mantissa_bits: u32) -> Float {
+    let exponent = (bits >> mantissa_bits) as i32;
+    let mantissa = bits & ((1 << mantissa_bits) - 1);
+    let scale = (1u32 << mantissa_bits) as f32;
+    let value = match exponent {
+        0 => mantissa as f32 / scale * exp2i(-14),
+        31 if mantissa == 0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa as f32 / scale) * exp2i(exponent - 15),
+    };
+    value as Float
+}
+
+impl PixelFormat for Rg11B10Float {
+    fn decode(self) -> (Vec3, Float) {
+        let r = decode_small_float(self.0 & 0x7ff, 6);
+        let g = decode_small_float((self.0 >> 11) & 0x7ff, 6);
+        let b = decode_small_float(self.0 >> 22, 5);
+        (Vec3::new(r, g, b), 1.0)
+    }
+
+    fn encode(color: Vec3, _alpha: Float) -> Self {
+        Self(
+            encode_small_float(color.x, 6)
+                | encode_small_float(color.y, 6) << 11
+                | encode_small_float(color.z, 5) << 22,
+        )
+    }
+}
+
+const RGB9E5_MANTISSA_BITS: i32 = 9;
+const RGB9E5_EXPONENT_BIAS: i32 = 15;
+/// The largest value representable in RGB9E5, `511/512 · 2^16`.
+const RGB9E5_MAX: f32 = 65408.0;
+
+impl PixelFormat for Rgb9E5 {
+    fn decode(self) -> (Vec3, Float) {
+        let exponent = (self.0 >> 27) as i32;
+        let scale = exp2i(exponent - RGB9E5_EXPONENT_BIAS - RGB9E5_MANTISSA_BITS);
+        let channel = |shift: u32| (((self.0 >> shift) & 0x1ff) as f32 * scale) as Float;
+        (Vec3::new(channel(0), channel(9), channel(18)), 1.0)
+    }
+
+    /// Encodes following `EXT_texture_shared_exponent`.
+    fn encode(color: Vec3, _alpha: Float) -> Self {
+        let clamp = |value: Float| {
+            let value = value as f32;
+            if value > 0.0 {
+                value.min(RGB9E5_MAX)
+            } else {
+                0.0
+            }
+        };
+        let [r, g, b] = [clamp(color.x), clamp(color.y), clamp(color.z)];
+        let max = r.max(g).max(b);
+        let floor_log2 = ((max.to_bits() >> 23) & 0xff) as i32 - 127;
+        let mut exponent = floor_log2.max(-RGB9E5_EXPONENT_BIAS - 1) + 1 + RGB9E5_EXPONENT_BIAS;
+        let quantize = |value: f32, exponent: i32| {
+            (value / exp2i(exponent - RGB9E5_EXPONENT_BIAS - RGB9E5_MANTISSA_BITS) + 0.5) as u32
+        };
+        if quantize(max, exponent) == 1 << RGB9E5_MANTISSA_BITS {
+            exponent += 1;
+        }
+        Self(
+            quantize(r, exponent)
+                | quantize(g, exponent) << 9
+                | quantize(b, exponent) << 18
+                | (exponent as u32) << 27,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unorm_rounding() {
+        let pixel = Rgba8Unorm::encode(Vec3::new(0.5, -1.0, 2.0), Float::NAN);
+        assert_eq!(pixel, Rgba8Unorm([128, 0, 255, 0]));
+        for value in 0..=255u8 {
+            let (color, alpha) = Rgba8Unorm([value, value, value, value]).decode();
+            assert_eq!(Rgba8Unorm::encode(color, alpha).0, [value; 4]);
+        }
+
+        let pixel = Rgba16Unorm::encode(Vec3::new(0.5, 0.25, 1.0), 1.0);
+        assert_eq!(pixel, Rgba16Unorm([32768, 16384, 65535, 65535]));
+
+        let pixel = Rgb10A2Unorm::encode(Vec3::new(1.0, 0.5, 0.0), 1.0 / 3.0);
+        assert_eq!(pixel.0, 1023 | 512 << 10 | 1 << 30);
+        let (color, alpha) = pixel.decode();
+        assert!(color.abs_diff_eq(Vec3::new(1.0, 512.0 / 1023.0, 0.0), 1e-6));
+        assert!((alpha - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rg11b10_float() {
+        let one = Rg11B10Float::encode(Vec3::new(1.0, 1.0, 1.0), 1.0);
+        assert_eq!(one.0, 0x3c0 | 0x3c0 << 11 | 0x1e0 << 22);
+        assert_eq!(one.decode(), (Vec3::new(1.0, 1.0, 1.0), 1.0));
+
+        let limits = Rg11B10Float::encode(Vec3::new(1e9, -1.0, 1e9), 1.0);
+        assert_eq!(limits.decode().0, Vec3::new(65024.0, 0.0, 64512.0));
+
+        // Halfway between 1 and the next value rounds to even, and slightly
+        // more rounds up.
+        let halfway = 1.0 + 0.5 / 64.0;
+        let pixel = Rg11B10Float::encode(Vec3::new(halfway, halfway + 1e-4, 0.0), 1.0);
+        assert_eq!(pixel.0 & 0x7ff, 0x3c0);
+        assert_eq!((pixel.0 >> 11) & 0x7ff, 0x3c1);
+
+        let denormal = 3.0 / 64.0 * (2.0 as Float).powi(-14);
+        let pixel = Rg11B10Float::encode(Vec3::new(denormal, 0.0, 0.0), 1.0);
+        assert_eq!(pixel.0, 3);
+        assert_eq!(pixel.decode().0.x, denormal);
+    }
+
+    #[test]
+    fn rgb9e5() {
+        let one = Rgb9E5::encode(Vec3::new(1.0, 0.5, 0.0), 1.0);
+        assert_eq!(one.0, 256 | 128 << 9 | 16 << 27);
+        assert_eq!(one.decode().0, Vec3::new(1.0, 0.5, 0.0));
+
+        // Rounding the largest channel up to 512 bumps the shared exponent.
+        let pixel = Rgb9E5::encode(Vec3::new(1.999, 0.0, 0.0), 1.0);
+        assert_eq!(pixel.0, 256 | 17 << 27);
+
+        let limits = Rgb9E5::encode(Vec3::new(1e9, -1.0, Float::NAN), 1.0);
+        assert_eq!(limits.decode().0, Vec3::new(65408.0, 0.0, 0.0));
+
+        for value in [0.001, 0.3, 7.5, 1000.0] {
+            let (color, _) = Rgb9E5::encode(Vec3::new(value, value, value), 1.0).decode();
+            assert!((color.x - value).abs() <= value / 256.0);
+        }
+    }
+}
@@ -0,0 +1,581 @@
+//! Upsampling of RGB reflectances to smooth spectra.
+//!
+//! Two methods are implemented:
+//!
+//! - The sigmoid-polynomial model by Jakob and Hanika, "A Low-Dimensional
+//!   Function Space for Efficient Spectral Upsampling" (2019). A
+//!   [`RgbToSpectrumTable`] is generated for a set of [`RgbPrimaries`] and an
+//!   illuminant, and maps any RGB reflectance to the three coefficients of a
+//!   [`SigmoidPolynomial`].
+//!
+//! - Smits' method, "An RGB to Spectrum Conversion for Reflectances" (1999),
+//!   which builds a spectrum from the published set of seven basis spectra.
+//!   It is much cheaper, but doesn't round trip as accurately and is only
+//!   intended for RGB spaces with primaries similar to sRGB.
+//!
+//! ## Round Trip Error
+//!
+//! A `RgbToSpectrumTable` treats the white point of the illuminant as the
+//! white point of the RGB space, so `(1, 1, 1)` maps to a constant reflectance
+//! of 1. Converting the spectrum back with
+//! [`RgbToSpectrumTable::spectrum_to_rgb`] under the same illuminant has a
+//! maximum absolute error per component, for reflectances inside the sRGB
+//! gamut, of:
+//!
+//! | Table | Resolution 16 | Resolution 64 |
+//! |-|-|-|
+//! | sRGB primaries | 0.04 | 0.002 |
+//! | ACEScg primaries | 0.06 | 0.03 |
+//!
+//! The error is largest for saturated blues, and is mostly caused by the
+//! interpolation between table entries. ACEScg colors outside of the spectral
+//! locus have no matching reflectance and can't round trip.
+//!
+//! Smits' method has errors of up to 0.04 for sRGB colors under D65.
+use super::{
+    color::{color_spaces, Color, RgbPrimaries},
+    spectral::{Observer, Spectrum},
+    spectral_data::{TABLE_LEN, TABLE_START, TABLE_STEP},
+    xyz::rgb_to_xyz,
+};
+use crate::{Float, Mat3, Vec3};
+
+const LAMBDA_RANGE: f64 = TABLE_STEP as f64 * (TABLE_LEN - 1) as f64;
+
+/// A reflectance spectrum defined as `sigmoid(c0·t² + c1·t + c2)`, where `t`
+/// is the wavelength remapped from 380..=780 nm to 0..=1, and
+/// `sigmoid(x) = 0.5 + x / (2·sqrt(1 + x²))`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SigmoidPolynomial {
+    pub coefficients: [Float; 3],
+}
+
+impl SigmoidPolynomial {
+    /// Creates the polynomial of a spectrum with the same `value` at every
+    /// wavelength.
+    pub fn constant(value: Float) -> Self {
+        let value = value.clamp(0.0, 1.0);
+        let c2 = (value - 0.5) / (value * (1.0 - value)).sqrt();
+        Self {
+            coefficients: [0.0, 0.0, c2],
+        }
+    }
+
+    /// Evaluates the reflectance at `wavelength` (in nm).
+    pub fn evaluate(&self, wavelength: Float) -> Float {
+        let t = (wavelength - TABLE_START) / LAMBDA_RANGE as Float;
+        let [c0, c1, c2] = self.coefficients;
+        sigmoid((c0 * t + c1) * t + c2)
+    }
+
+    /// Creates a [`Spectrum`] with `len` samples of the reflectance taken every
+    /// `step` nm, starting at `start` nm.
+    pub fn to_spectrum(&self, start: Float, step: Float, len: usize) -> Spectrum {
+        let values = (0..len)
+            .map(|i| self.evaluate(start + step * i as Float))
+            .collect();
+        Spectrum::from_uniform(start, step, values)
+    }
+}
+
+fn sigmoid(x: Float) -> Float {
+    if x.is_infinite() {
+        return if x > 0.0 { 1.0 } else { 0.0 };
+    }
+    0.5 + x / (2.0 * (1.0 + x * x).sqrt())
+}
+
+/// A table of [`SigmoidPolynomial`] coefficients covering all RGB
+/// reflectances in `0.0..=1.0` for a set of [`RgbPrimaries`].
+///
+/// The table is indexed by the largest RGB component, and the ratios of the
+/// two other components to it, as described in the paper.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbToSpectrumTable {
+    primaries: RgbPrimaries,
+    resolution: usize,
+    scale: Vec<Float>,
+    coefficients: Vec<[Float; 3]>,
+    rgb_to_xyz: Mat3,
+    /// `xyz_to_rgb * cmf * illuminant` for each table wavelength, normalized
+    /// so that a constant reflectance of 1 maps to RGB `(1, 1, 1)`.
+    rgb_weights: Vec<Vec3>,
+}
+
+impl RgbToSpectrumTable {
+    /// Generates a table by optimizing the coefficients for each of the
+    /// `3 × resolution³` entries.
+    ///
+    /// The RGB space is defined by `primaries`, with the white point of
+    /// `illuminant` under the CIE 1931 observer. Generation is slow for high
+    /// resolutions; 64 is the resolution used in the paper, but 16 already
+    /// gives good results. See the [module documentation](self) for the
+    /// expected round trip error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is less than 2.
+    pub fn generate(primaries: RgbPrimaries, illuminant: &Spectrum, resolution: usize) -> Self {
+        assert!(resolution >= 2, "resolution must be at least 2");
+        let optimizer = Optimizer::new(primaries, illuminant);
+
+        let scale: Vec<Float> = (0..resolution)
+            .map(|k| smoothstep(smoothstep(k as f64 / (resolution - 1) as f64)) as Float)
+            .collect();
+        let mut coefficients = vec![[0.0; 3]; 3 * resolution * resolution * resolution];
+
+        for l in 0..3 {
+            for j in 0..resolution {
+                let y = j as f64 / (resolution - 1) as f64;
+                for i in 0..resolution {
+                    let x = i as f64 / (resolution - 1) as f64;
+                    let mut solve = |k: usize, coeffs: [f64; 3]| {
+                        let b = scale[k] as f64;
+                        let mut rgb = [0.0; 3];
+                        rgb[l] = b;
+                        rgb[(l + 1) % 3] = x * b;
+                        rgb[(l + 2) % 3] = y * b;
+                        let coeffs = optimizer.gauss_newton(rgb, coeffs);
+                        let index = ((l * resolution + k) * resolution + j) * resolution + i;
+                        coefficients[index] = coeffs.map(|c| c as Float);
+                        coeffs
+                    };
+                    // Start from a dark color and walk outwards, using each
+                    // solution as the initial guess for its neighbor.
+                    let start = resolution / 5;
+                    let mut coeffs = [0.0; 3];
+                    for k in start..resolution {
+                        coeffs = solve(k, coeffs);
+                    }
+                    let mut coeffs = [0.0; 3];
+                    for k in (0..start).rev() {
+                        coeffs = solve(k, coeffs);
+                    }
+                }
+            }
+        }
+
+        Self {
+            primaries,
+            resolution,
+            scale,
+            coefficients,
+            rgb_to_xyz: optimizer.rgb_to_xyz,
+            rgb_weights: optimizer.rgb_weights,
+        }
+    }
+
+    pub fn primaries(&self) -> RgbPrimaries {
+        self.primaries
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    /// Returns the polynomial for a linear RGB reflectance in the table's
+    /// primaries. Components are clamped to `0.0..=1.0`.
+    pub fn polynomial(&self, rgb: Vec3) -> SigmoidPolynomial {
+        let rgb: [Float; 3] = [
+            rgb.x.clamp(0.0, 1.0),
+            rgb.y.clamp(0.0, 1.0),
+            rgb.z.clamp(0.0, 1.0),
+        ];
+        if rgb[0] == rgb[1] && rgb[1] == rgb[2] {
+            return SigmoidPolynomial::constant(rgb[0]);
+        }
+
+        let max_component = if rgb[0] > rgb[1] {
+            if rgb[0] > rgb[2] {
+                0
+            } else {
+                2
+            }
+        } else if rgb[1] > rgb[2] {
+            1
+        } else {
+            2
+        };
+        let res = self.resolution;
+        let z = rgb[max_component];
+        let x = rgb[(max_component + 1) % 3] * (res - 1) as Float / z;
+        let y = rgb[(max_component + 2) % 3] * (res - 1) as Float / z;
+
+        let xi = (x as usize).min(res - 2);
+        let yi = (y as usize).min(res - 2);
+        let zi = self.scale.partition_point(|&s| s <= z).clamp(1, res - 1) - 1;
+        let dx = x - xi as Float;
+        let dy = y - yi as Float;
+        let dz = (z - self.scale[zi]) / (self.scale[zi + 1] - self.scale[zi]);
+
+        let coefficient = |dxi: usize, dyi: usize, dzi: usize, c: usize| {
+            let index = ((max_component * res + zi + dzi) * res + yi + dyi) * res + xi + dxi;
+            self.coefficients[index][c]
+        };
+        let lerp = |t: Float, a: Float, b: Float| a + (b - a) * t;
+        let mut result = [0.0; 3];
+        for (c, value) in result.iter_mut().enumerate() {
+            *value = lerp(
+                dz,
+                lerp(
+                    dy,
+                    lerp(dx, coefficient(0, 0, 0, c), coefficient(1, 0, 0, c)),
+                    lerp(dx, coefficient(0, 1, 0, c), coefficient(1, 1, 0, c)),
+                ),
+                lerp(
+                    dy,
+                    lerp(dx, coefficient(0, 0, 1, c), coefficient(1, 0, 1, c)),
+                    lerp(dx, coefficient(0, 1, 1, c), coefficient(1, 1, 1, c)),
+                ),
+            );
+        }
+        SigmoidPolynomial {
+            coefficients: result,
+        }
+    }
+
+    /// Returns the polynomial for `color`, which is first converted to the
+    /// table's linear RGB space.
+    ///
+    /// The color's white point is assumed to match the table's illuminant.
+    pub fn color_to_polynomial(&self, color: Color) -> SigmoidPolynomial {
+        let linear = color.to(color.space.as_linear().with_primaries(self.primaries));
+        self.polynomial(linear.value)
+    }
+
+    /// Converts a reflectance back to linear RGB under the table's
+    /// illuminant.
+    pub fn spectrum_to_rgb(&self, polynomial: &SigmoidPolynomial) -> Vec3 {
+        let mut rgb = Vec3::new(0.0, 0.0, 0.0);
+        for (i, weight) in self.rgb_weights.iter().enumerate() {
+            let wavelength = TABLE_START + TABLE_STEP * i as Float;
+            rgb += *weight * polynomial.evaluate(wavelength);
+        }
+        rgb
+    }
+
+    /// Converts a reflectance back to CIE XYZ under the table's illuminant.
+    pub fn spectrum_to_xyz(&self, polynomial: &SigmoidPolynomial) -> Color {
+        let xyz = self.rgb_to_xyz * self.spectrum_to_rgb(polynomial);
+        Color::new(xyz.x, xyz.y, xyz.z, color_spaces::CIE_XYZ)
+    }
+}
+
+fn smoothstep(x: f64) -> f64 {
+    x * x * (3.0 - 2.0 * x)
+}
+
+/// Gauss-Newton optimizer for the coefficients. Runs in `f64` regardless of
+/// `Float` since the finite difference Jacobian needs the precision.
+struct Optimizer {
+    rgb_to_xyz: Mat3,
+    rgb_to_xyz_f64: [[f64; 3]; 3],
+    white: [f64; 3],
+    rgb_weights: Vec<Vec3>,
+    rgb_weights_f64: Vec<[f64; 3]>,
+}
+
+impl Optimizer {
+    fn new(primaries: RgbPrimaries, illuminant: &Spectrum) -> Self {
+        let observer = Observer::Cie1931;
+        let mut white = Vec3::new(0.0, 0.0, 0.0);
+        for i in 0..TABLE_LEN {
+            let wavelength = TABLE_START + TABLE_STEP * i as Float;
+            white += observer.cmf(wavelength) * illuminant.sample(wavelength);
+        }
+        let normalization = 1.0 / white.y;
+        let white = white * normalization;
+
+        let white_values: [Float; 3] = white.into();
+        let rgb_to_xyz = rgb_to_xyz(primaries.values(), &white_values);
+        let xyz_to_rgb = rgb_to_xyz.inverse();
+        let rgb_weights: Vec<Vec3> = (0..TABLE_LEN)
+            .map(|i| {
+                let wavelength = TABLE_START + TABLE_STEP * i as Float;
+                xyz_to_rgb
+                    * observer.cmf(wavelength)
+                    * (illuminant.sample(wavelength) * normalization)
+            })
+            .collect();
+
+        let to_f64 = |v: Vec3| [v.x as f64, v.y as f64, v.z as f64];
+        let columns = [
+            to_f64(rgb_to_xyz.x_axis),
+            to_f64(rgb_to_xyz.y_axis),
+            to_f64(rgb_to_xyz.z_axis),
+        ];
+        let mut rgb_to_xyz_f64 = [[0.0; 3]; 3];
+        for (row, values) in rgb_to_xyz_f64.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = columns[column][row];
+            }
+        }
+        Self {
+            rgb_to_xyz,
+            rgb_to_xyz_f64,
+            white: to_f64(white),
+            rgb_weights_f64: rgb_weights.iter().map(|w| to_f64(*w)).collect(),
+            rgb_weights,
+        }
+    }
+
+    fn cie_lab(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let mut xyz = [0.0; 3];
+        for (row, value) in xyz.iter_mut().enumerate() {
+            *value = (0..3).map(|j| self.rgb_to_xyz_f64[row][j] * rgb[j]).sum();
+        }
+        let f = |t: f64| {
+            const DELTA: f64 = 6.0 / 29.0;
+            if t > DELTA * DELTA * DELTA {
+                t.cbrt()
+            } else {
+                t / (DELTA * DELTA * 3.0) + 4.0 / 29.0
+            }
+        };
+        let fx = f(xyz[0] / self.white[0]);
+        let fy = f(xyz[1] / self.white[1]);
+        let fz = f(xyz[2] / self.white[2]);
+        [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+    }
+
+    fn residual(&self, coeffs: [f64; 3], rgb: [f64; 3]) -> [f64; 3] {
+        let mut out = [0.0; 3];
+        for (i, weight) in self.rgb_weights_f64.iter().enumerate() {
+            let t = i as f64 * TABLE_STEP as f64 / LAMBDA_RANGE;
+            let x = (coeffs[0] * t + coeffs[1]) * t + coeffs[2];
+            let s = 0.5 + x / (2.0 * (1.0 + x * x).sqrt());
+            for c in 0..3 {
+                out[c] += weight[c] * s;
+            }
+        }
+        let target = self.cie_lab(rgb);
+        let out = self.cie_lab(out);
+        [target[0] - out[0], target[1] - out[1], target[2] - out[2]]
+    }
+
+    fn gauss_newton(&self, rgb: [f64; 3], mut coeffs: [f64; 3]) -> [f64; 3] {
+        const EPSILON: f64 = 1e-4;
+        let norm = |r: [f64; 3]| r.iter().map(|r| r * r).sum::<f64>();
+        let mut residual = self.residual(coeffs, rgb);
+        for _ in 0..15 {
+            if norm(residual) < 1e-6 {
+                break;
+            }
+            let mut jacobian = [[0.0; 3]; 3];
+            for i in 0..3 {
+                let mut lower = coeffs;
+                lower[i] -= EPSILON;
+                let mut upper = coeffs;
+                upper[i] += EPSILON;
+                let r0 = self.residual(lower, rgb);
+                let r1 = self.residual(upper, rgb);
+                for j in 0..3 {
+                    jacobian[j][i] = (r1[j] - r0[j]) / (2.0 * EPSILON);
+                }
+            }
+            let Some(step) = solve_3x3(jacobian, residual) else {
+                break;
+            };
+            // Backtrack along the Gauss-Newton step until the residual
+            // decreases, since colors on the edge of the gamut can't be
+            // matched exactly and the full step tends to overshoot there.
+            let mut scale = 1.0;
+            let improved = loop {
+                let mut candidate = coeffs;
+                for i in 0..3 {
+                    candidate[i] -= step[i] * scale;
+                }
+                let max = candidate[0].max(candidate[1]).max(candidate[2]);
+                if max > 200.0 {
+                    for c in candidate.iter_mut() {
+                        *c *= 200.0 / max;
+                    }
+                }
+                let candidate_residual = self.residual(candidate, rgb);
+                if norm(candidate_residual) < norm(residual) {
+                    break Some((candidate, candidate_residual));
+                }
+                scale *= 0.5;
+                if scale < 1e-3 {
+                    break None;
+                }
+            };
+            let Some((candidate, candidate_residual)) = improved else {
+                break;
+            };
+            coeffs = candidate;
+            residual = candidate_residual;
+        }
+        coeffs
+    }
+}
+
+/// Solves `a·x = b` with Cramer's rule.
+fn solve_3x3(a: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(a);
+    if d.abs() < 1e-15 {
+        return None;
+    }
+    let mut x = [0.0; 3];
+    for (i, value) in x.iter_mut().enumerate() {
+        let mut m = a;
+        for row in 0..3 {
+            m[row][i] = b[row];
+        }
+        *value = det(m) / d;
+    }
+    Some(x)
+}
+
+/// Smits' basis spectra, sampled at the centers of ten bins spanning 380 nm
+/// to 720 nm.
+#[rustfmt::skip]
+mod smits_basis {
+    use crate::Float;
+    pub const WHITE: [Float; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+    pub const CYAN: [Float; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+    pub const MAGENTA: [Float; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+    pub const YELLOW: [Float; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+    pub const RED: [Float; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+    pub const GREEN: [Float; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+    pub const BLUE: [Float; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+}
+
+/// Converts a linear RGB reflectance to a spectrum with Smits' method.
+///
+/// The result is sampled at the centers of ten equally wide bins between
+/// 380 nm and 720 nm. The basis spectra were optimized for primaries close to
+/// [`RgbPrimaries::Bt709`].
+pub fn smits(rgb: Vec3) -> Spectrum {
+    use smits_basis::*;
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    let mut values = [0.0; 10];
+    let mut add = |weight: Float, basis: &[Float; 10]| {
+        for (value, basis) in values.iter_mut().zip(basis) {
+            *value += weight * basis;
+        }
+    };
+    if r <= g && r <= b {
+        add(r, &WHITE);
+        if g <= b {
+            add(g - r, &CYAN);
+            add(b - g, &BLUE);
+        } else {
+            add(b - r, &CYAN);
+            add(g - b, &GREEN);
+        }
+    } else if g <= r && g <= b {
+        add(g, &WHITE);
+        if r <= b {
+            add(r - g, &MAGENTA);
+            add(b - r, &BLUE);
+        } else {
+            add(b - g, &MAGENTA);
+            add(r - b, &RED);
+        }
+    } else {
+        add(b, &WHITE);
+        if r <= g {
+            add(r - b, &YELLOW);
+            add(g - r, &GREEN);
+        } else {
+            add(g - b, &YELLOW);
+            add(r - g, &RED);
+        }
+    }
+    const BIN_WIDTH: Float = (720.0 - 380.0) / 10.0;
+    Spectrum::from_uniform(380.0 + BIN_WIDTH / 2.0, BIN_WIDTH, values.to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spaces;
+
+    fn test_colors() -> impl Iterator<Item = Vec3> {
+        let steps = [0.0, 0.1, 0.35, 0.6, 0.85, 1.0];
+        steps.into_iter().flat_map(move |r| {
+            steps
+                .into_iter()
+                .flat_map(move |g| steps.into_iter().map(move |b| Vec3::new(r, g, b)))
+        })
+    }
+
+    fn max_error(a: Vec3, b: Vec3) -> Float {
+        (a.x - b.x)
+            .abs()
+            .max((a.y - b.y).abs())
+            .max((a.z - b.z).abs())
+    }
+
+    /// Checks the round trip errors documented for `resolution` in the
+    /// module docs.
+    fn check_round_trip(resolution: usize, srgb_error: Float, aces_cg_error: Float) {
+        let d65 = Spectrum::cie_d65();
+        let srgb = RgbToSpectrumTable::generate(RgbPrimaries::Bt709, &d65, resolution);
+        let aces_cg = RgbToSpectrumTable::generate(RgbPrimaries::Ap1, &d65, resolution);
+        let srgb_to_aces_cg = aces_cg.rgb_to_xyz.inverse() * srgb.rgb_to_xyz;
+        for rgb in test_colors() {
+            let result = srgb.spectrum_to_rgb(&srgb.polynomial(rgb));
+            let error = max_error(result, rgb);
+            assert!(error < srgb_error, "sRGB {:?} != {:?}", result, rgb);
+
+            let rgb = srgb_to_aces_cg * rgb;
+            let result = aces_cg.spectrum_to_rgb(&aces_cg.polynomial(rgb));
+            let error = max_error(result, rgb);
+            assert!(error < aces_cg_error, "ACEScg {:?} != {:?}", result, rgb);
+        }
+    }
+
+    #[test]
+    fn jakob_hanika_round_trip() {
+        check_round_trip(16, 0.04, 0.06);
+    }
+
+    /// Generating the tables at resolution 64 takes too long for debug builds,
+    /// run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn jakob_hanika_round_trip_64() {
+        check_round_trip(64, 0.002, 0.03);
+    }
+
+    #[test]
+    fn jakob_hanika_matches_spectral_integration() {
+        let d65 = Spectrum::cie_d65();
+        let table = RgbToSpectrumTable::generate(RgbPrimaries::Bt709, &d65, 16);
+        let color = Color::new(0.2, 0.5, 0.7, spaces::LINEAR_SRGB);
+        let polynomial = table.color_to_polynomial(color);
+        let spectrum = polynomial.to_spectrum(380.0, 1.0, 401);
+        let xyz = spectrum.reflectance_to_xyz(&d65, Observer::Cie1931);
+        let expected = color.to(spaces::CIE_XYZ);
+        assert!(
+            xyz.value.abs_diff_eq(expected.value, 0.01),
+            "{:?} != {:?}",
+            xyz.value,
+            expected.value
+        );
+    }
+
+    #[test]
+    fn smits_round_trip() {
+        let d65 = Spectrum::cie_d65();
+        for rgb in test_colors() {
+            let spectrum = smits(rgb);
+            let result = spectrum
+                .reflectance_to_xyz(&d65, Observer::Cie1931)
+                .to(spaces::LINEAR_SRGB);
+            assert!(
+                result.value.abs_diff_eq(rgb, 0.04),
+                "{:?} != {:?}",
+                result.value,
+                rgb
+            );
+        }
+    }
+}
@@ -0,0 +1,1027 @@
+//! Shader source generation for [`ColorConversion`]s.
+//!
+//! [`ShaderFunction::from_conversion`] lowers a conversion into a short list
+//! of [`ShaderOp`]s: the inverse of the source transform function, the
+//! matrix from [`linear_part`](ColorConversion::linear_part), and the
+//! destination transform function. [`ShaderFunction::to_source`] writes
+//! these as a self-contained WGSL, GLSL or HLSL function taking and returning
+//! a 3-component vector, and [`ShaderFunction::eval`] evaluates the same ops
+//! on the CPU as a reference for the generated math. The tests check both,
+//! interpreting the emitted source, against kolor's own transform functions.
+//!
+//! The sRGB, BT.601, PQ, Oklab, Oklch, CIE xyY and CIELAB transform functions
+//! are supported.
+use super::{
+    color::{TransformFn, WhitePoint},
+    conversion::ColorConversion,
+    transform::{piecewise_power_params, pq, OKLAB_M_1, OKLAB_M_2},
+};
+use crate::{Float, Mat3, Vec3};
+use std::fmt::Write;
+
+/// Returned when a conversion can't be expressed as shader code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShaderError {
+    /// The transform function has no shader implementation.
+    UnsupportedTransform(TransformFn),
+}
+
+impl core::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ShaderError::UnsupportedTransform(transform) => {
+                write!(f, "{:?} has no shader implementation", transform)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// The shading language to generate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderLanguage {
+    Wgsl,
+    /// GLSL 3.30 or GLSL ES 3.00 and later.
+    Glsl,
+    /// HLSL shader model 4 and later.
+    Hlsl,
+}
+
+/// A function applied to each component of a color.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChannelFn {
+    /// `x * linear_scale + linear_offset` for `x < cutoff`, and
+    /// `scale * (a * x + b)^exponent + offset` otherwise. This covers the
+    /// sRGB and BT.601 transfer functions, and the CIELAB non-linearity.
+    PiecewisePower {
+        cutoff: Float,
+        linear_scale: Float,
+        linear_offset: Float,
+        a: Float,
+        b: Float,
+        exponent: Float,
+        scale: Float,
+        offset: Float,
+    },
+    /// The cube root, preserving the sign.
+    Cbrt,
+    /// `x * x * x`.
+    Cube,
+    /// The SMPTE ST 2084 (PQ) EOTF, producing nits.
+    PqEotf,
+    /// The inverse of the SMPTE ST 2084 (PQ) EOTF, taking nits.
+    PqEotfInverse,
+}
+
+impl ChannelFn {
+    /// Evaluates the function on the CPU.
+    pub fn eval(&self, x: Float) -> Float {
+        match *self {
+            ChannelFn::PiecewisePower {
+                cutoff,
+                linear_scale,
+                linear_offset,
+                a,
+                b,
+                exponent,
+                scale,
+                offset,
+            } => {
+                if x < cutoff {
+                    x * linear_scale + linear_offset
+                } else {
+                    scale * (x * a + b).max(0.0).powf(exponent) + offset
+                }
+            }
+            ChannelFn::Cbrt => x.cbrt(),
+            ChannelFn::Cube => x * x * x,
+            ChannelFn::PqEotf => {
+                let p = x.max(0.0).powf(pq::M_2_d);
+                pq::L_p * ((p - pq::C_1).max(0.0) / (pq::C_2 - pq::C_3 * p)).powf(pq::M_1_d)
+            }
+            ChannelFn::PqEotfInverse => {
+                let y = (x / pq::L_p).max(0.0).powf(pq::M_1);
+                ((pq::C_1 + pq::C_2 * y) / (pq::C_3 * y + 1.0)).powf(pq::M_2)
+            }
+        }
+    }
+
+    /// Writes the body of a function of `x` returning the result.
+    fn write_body(&self, out: &mut String, language: ShaderLanguage) {
+        let local = match language {
+            ShaderLanguage::Wgsl => "let",
+            ShaderLanguage::Glsl | ShaderLanguage::Hlsl => "float",
+        };
+        match *self {
+            ChannelFn::PiecewisePower {
+                cutoff,
+                linear_scale,
+                linear_offset,
+                a,
+                b,
+                exponent,
+                scale,
+                offset,
+            } => {
+                let _ = writeln!(out, "    if (x < {}) {{", literal(cutoff));
+                let _ = writeln!(
+                    out,
+                    "        return {};",
+                    scale_and_offset("x", linear_scale, linear_offset)
+                );
+                let _ = writeln!(out, "    }}");
+                let power = format!(
+                    "pow(max({}, 0.0), {})",
+                    scale_and_offset("x", a, b),
+                    literal(exponent)
+                );
+                let _ = writeln!(
+                    out,
+                    "    return {};",
+                    scale_and_offset(&power, scale, offset)
+                );
+            }
+            ChannelFn::Cbrt => {
+                let _ = writeln!(
+                    out,
+                    "    return sign(x) * pow(abs(x), {});",
+                    literal(1.0 / 3.0)
+                );
+            }
+            ChannelFn::Cube => {
+                let _ = writeln!(out, "    return x * x * x;");
+            }
+            ChannelFn::PqEotf => {
+                let _ = writeln!(
+                    out,
+                    "    {} p = pow(max(x, 0.0), {});",
+                    local,
+                    literal(pq::M_2_d)
+                );
+                let _ = writeln!(
+                    out,
+                    "    return {} * pow(max(p - {}, 0.0) / ({} - {} * p), {});",
+                    literal(pq::L_p),
+                    literal(pq::C_1),
+                    literal(pq::C_2),
+                    literal(pq::C_3),
+                    literal(pq::M_1_d)
+                );
+            }
+            ChannelFn::PqEotfInverse => {
+                let _ = writeln!(
+                    out,
+                    "    {} y = pow(max(x / {}, 0.0), {});",
+                    local,
+                    literal(pq::L_p),
+                    literal(pq::M_1)
+                );
+                let _ = writeln!(
+                    out,
+                    "    return pow(({} + {} * y) / ({} * y + 1.0), {});",
+                    literal(pq::C_1),
+                    literal(pq::C_2),
+                    literal(pq::C_3),
+                    literal(pq::M_2)
+                );
+            }
+        }
+    }
+}
+
+/// A step of a [`ShaderFunction`], transforming the color.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShaderOp {
+    /// `matrix * color + offset`.
+    Affine { matrix: Mat3, offset: Vec3 },
+    /// Applies a function to each component.
+    Channels(ChannelFn),
+    /// CIE XYZ to xyY.
+    XyzToXyy,
+    /// CIE xyY to XYZ.
+    XyyToXyz,
+    /// Lab-like coordinates to LCh, with the hue in radians.
+    LabToPolar,
+    /// LCh coordinates with the hue in radians to Lab.
+    PolarToLab,
+}
+
+impl ShaderOp {
+    fn matrix(matrix: Mat3) -> Self {
+        ShaderOp::Affine {
+            matrix,
+            offset: Vec3::splat(0.0),
+        }
+    }
+
+    /// Evaluates the op on the CPU.
+    pub fn eval(&self, color: Vec3) -> Vec3 {
+        match self {
+            ShaderOp::Affine { matrix, offset } => *matrix * color + *offset,
+            ShaderOp::Channels(function) => Vec3::new(
+                function.eval(color.x),
+                function.eval(color.y),
+                function.eval(color.z),
+            ),
+            ShaderOp::XyzToXyy => {
+                let sum = color.x + color.y + color.z;
+                Vec3::new(color.x / sum, color.y / sum, color.y)
+            }
+            ShaderOp::XyyToXyz => Vec3::new(
+                color.z / color.y * color.x,
+                color.z,
+                color.z / color.y * (1.0 - color.x - color.y),
+            ),
+            ShaderOp::LabToPolar => Vec3::new(
+                color.x,
+                (color.y * color.y + color.z * color.z).sqrt(),
+                color.z.atan2(color.y),
+            ),
+            ShaderOp::PolarToLab => {
+                Vec3::new(color.x, color.y * color.z.cos(), color.y * color.z.sin())
+            }
+        }
+    }
+}
+
+/// Formats a float so that it parses as a float literal in all languages.
+fn literal(value: Float) -> String {
+    let text = format!("{:?}", value);
+    if value < 0.0 {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// Writes `value * scale + offset`, leaving out a scale of one and an offset
+/// of zero.
+fn scale_and_offset(value: &str, scale: Float, offset: Float) -> String {
+    let mut text = value.to_owned();
+    if scale != 1.0 {
+        let _ = write!(text, " * {}", literal(scale));
+    }
+    if offset != 0.0 {
+        let _ = write!(text, " + {}", literal(offset));
+    }
+    text
+}
+
+fn diagonal(values: [Float; 3]) -> Mat3 {
+    Mat3::from_cols_array(&[
+        values[0], 0.0, 0.0, 0.0, values[1], 0.0, 0.0, 0.0, values[2],
+    ])
+}
+
+/// The [`PiecewisePower`](ChannelFn::PiecewisePower) function of the sRGB,
+/// BT.601 or CIELAB transform function, or of its inverse.
+fn piecewise_power(transform: TransformFn, inverse: bool) -> ChannelFn {
+    let [cutoff, linear_scale, linear_offset, a, b, exponent, scale, offset] =
+        piecewise_power_params(transform, inverse).expect("not a piecewise power curve");
+    ChannelFn::PiecewisePower {
+        cutoff,
+        linear_scale,
+        linear_offset,
+        a,
+        b,
+        exponent,
+        scale,
+        offset,
+    }
+}
+
+/// The matrix and offset from CIELAB `f(X), f(Y), f(Z)` to `L*, a*, b*`.
+#[rustfmt::skip]
+fn cie_lab_affine() -> (Mat3, Vec3) {
+    let matrix = Mat3::from_cols_array(&[
+        0.0, 500.0, 0.0,
+        116.0, -500.0, 200.0,
+        0.0, 0.0, -200.0,
+    ]);
+    (matrix, Vec3::new(-16.0, 0.0, 0.0))
+}
+
+/// The ops applying `transform` to a linear color.
+fn encode_ops(
+    transform: TransformFn,
+    white_point: WhitePoint,
+) -> Result<Vec<ShaderOp>, ShaderError> {
+    let ops = match transform {
+        TransformFn::None => vec![],
+        TransformFn::Srgb | TransformFn::Bt601 => {
+            vec![ShaderOp::Channels(piecewise_power(transform, false))]
+        }
+        TransformFn::Pq => vec![ShaderOp::Channels(ChannelFn::PqEotfInverse)],
+        TransformFn::OkLab => vec![
+            ShaderOp::matrix(OKLAB_M_1),
+            ShaderOp::Channels(ChannelFn::Cbrt),
+            ShaderOp::matrix(OKLAB_M_2),
+        ],
+        TransformFn::OkLch => {
+            let mut ops = encode_ops(TransformFn::OkLab, white_point)?;
+            ops.push(ShaderOp::LabToPolar);
+            ops
+        }
+        TransformFn::CieXyY => vec![ShaderOp::XyzToXyy],
+        TransformFn::CieLab => {
+            let [x, y, z] = *white_point.values();
+            let (matrix, offset) = cie_lab_affine();
+            vec![
+                ShaderOp::matrix(diagonal([1.0 / x, 1.0 / y, 1.0 / z])),
+                ShaderOp::Channels(piecewise_power(TransformFn::CieLab, false)),
+                ShaderOp::Affine { matrix, offset },
+            ]
+        }
+        _ => return Err(ShaderError::UnsupportedTransform(transform)),
+    };
+    Ok(ops)
+}
+
+/// The ops applying the inverse of `transform`, producing a linear color.
+fn decode_ops(
+    transform: TransformFn,
+    white_point: WhitePoint,
+) -> Result<Vec<ShaderOp>, ShaderError> {
+    let ops = match transform {
+        TransformFn::None => vec![],
+        TransformFn::Srgb | TransformFn::Bt601 => {
+            vec![ShaderOp::Channels(piecewise_power(transform, true))]
+        }
+        TransformFn::Pq => vec![ShaderOp::Channels(ChannelFn::PqEotf)],
+        TransformFn::OkLab => vec![
+            ShaderOp::matrix(OKLAB_M_2.inverse()),
+            ShaderOp::Channels(ChannelFn::Cube),
+            ShaderOp::matrix(OKLAB_M_1.inverse()),
+        ],
+        TransformFn::OkLch => {
+            let mut ops = vec![ShaderOp::PolarToLab];
+            ops.extend(decode_ops(TransformFn::OkLab, white_point)?);
+            ops
+        }
+        TransformFn::CieXyY => vec![ShaderOp::XyyToXyz],
+        TransformFn::CieLab => {
+            let [x, y, z] = *white_point.values();
+            let (matrix, offset) = cie_lab_affine();
+            let matrix = matrix.inverse();
+            vec![
+                ShaderOp::Affine {
+                    matrix,
+                    offset: matrix * (offset * -1.0),
+                },
+                ShaderOp::Channels(piecewise_power(TransformFn::CieLab, true)),
+                ShaderOp::matrix(diagonal([x, y, z])),
+            ]
+        }
+        _ => return Err(ShaderError::UnsupportedTransform(transform)),
+    };
+    Ok(ops)
+}
+
+/// A conversion lowered to [`ShaderOp`]s, which can be written as shader
+/// source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderFunction {
+    name: String,
+    ops: Vec<ShaderOp>,
+}
+
+impl ShaderFunction {
+    /// Lowers `conversion` into a function called `name`. The name is also
+    /// used as the prefix of helper functions.
+    pub fn from_conversion(conversion: &ColorConversion, name: &str) -> Result<Self, ShaderError> {
+        let src = conversion.src_space();
+        let dst = conversion.dst_space();
+        let mut ops = decode_ops(conversion.src_transform_fn(), src.white_point())?;
+        let matrix = conversion.linear_part().matrix();
+        if matrix != Mat3::IDENTITY {
+            ops.push(ShaderOp::matrix(matrix));
+        }
+        ops.extend(encode_ops(
+            conversion.dst_transform_fn(),
+            dst.white_point(),
+        )?);
+        Ok(Self {
+            name: name.to_owned(),
+            ops,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn ops(&self) -> &[ShaderOp] {
+        &self.ops
+    }
+
+    /// Evaluates the ops on the CPU, as a reference for the generated
+    /// shaders.
+    pub fn eval(&self, color: Vec3) -> Vec3 {
+        self.ops.iter().fold(color, |color, op| op.eval(color))
+    }
+
+    /// Writes the function, preceded by its helper functions.
+    pub fn to_source(&self, language: ShaderLanguage) -> String {
+        let (vec3, atan2) = match language {
+            ShaderLanguage::Wgsl => ("vec3<f32>", "atan2"),
+            ShaderLanguage::Glsl => ("vec3", "atan"),
+            ShaderLanguage::Hlsl => ("float3", "atan2"),
+        };
+        let mut out = String::new();
+        for (i, op) in self.ops.iter().enumerate() {
+            if let ShaderOp::Channels(function) = op {
+                let helper = format!("{}_{}", self.name, i);
+                let _ = match language {
+                    ShaderLanguage::Wgsl => writeln!(out, "fn {}(x: f32) -> f32 {{", helper),
+                    _ => writeln!(out, "float {}(float x) {{", helper),
+                };
+                function.write_body(&mut out, language);
+                let _ = writeln!(out, "}}\n");
+            }
+        }
+
+        let _ = match language {
+            ShaderLanguage::Wgsl => writeln!(
+                out,
+                "fn {}(color: {}) -> {} {{\n    var c = color;",
+                self.name, vec3, vec3
+            ),
+            _ => writeln!(
+                out,
+                "{} {}({} color) {{\n    {} c = color;",
+                vec3, self.name, vec3, vec3
+            ),
+        };
+        for (i, op) in self.ops.iter().enumerate() {
+            let _ = match op {
+                ShaderOp::Affine { matrix, offset } => {
+                    let product = matrix_product(*matrix, language);
+                    if *offset == Vec3::splat(0.0) {
+                        writeln!(out, "    c = {};", product)
+                    } else {
+                        writeln!(out, "    c = {} + {};", product, vector(*offset, vec3))
+                    }
+                }
+                ShaderOp::Channels(_) => writeln!(
+                    out,
+                    "    c = {}({name}_{i}(c.x), {name}_{i}(c.y), {name}_{i}(c.z));",
+                    vec3,
+                    name = self.name,
+                    i = i
+                ),
+                ShaderOp::XyzToXyy => writeln!(
+                    out,
+                    "    c = {}(c.x, c.y, c.y) / {}(c.x + c.y + c.z, c.x + c.y + c.z, 1.0);",
+                    vec3, vec3
+                ),
+                ShaderOp::XyyToXyz => writeln!(
+                    out,
+                    "    c = {}(c.z / c.y * c.x, c.z, c.z / c.y * (1.0 - c.x - c.y));",
+                    vec3
+                ),
+                ShaderOp::LabToPolar => writeln!(
+                    out,
+                    "    c = {}(c.x, length(c.yz), {}(c.z, c.y));",
+                    vec3, atan2
+                ),
+                ShaderOp::PolarToLab => writeln!(
+                    out,
+                    "    c = {}(c.x, c.y * cos(c.z), c.y * sin(c.z));",
+                    vec3
+                ),
+            };
+        }
+        let _ = writeln!(out, "    return c;\n}}");
+        out
+    }
+}
+
+fn vector(value: Vec3, vec3: &str) -> String {
+    format!(
+        "{}({}, {}, {})",
+        vec3,
+        literal(value.x),
+        literal(value.y),
+        literal(value.z)
+    )
+}
+
+/// Writes `matrix * c`. GLSL and WGSL matrices are constructed from columns,
+/// and HLSL matrices from rows.
+fn matrix_product(matrix: Mat3, language: ShaderLanguage) -> String {
+    match language {
+        ShaderLanguage::Wgsl => format!(
+            "mat3x3<f32>({}, {}, {}) * c",
+            vector(matrix.x_axis, "vec3<f32>"),
+            vector(matrix.y_axis, "vec3<f32>"),
+            vector(matrix.z_axis, "vec3<f32>")
+        ),
+        ShaderLanguage::Glsl => format!(
+            "mat3({}, {}, {}) * c",
+            vector(matrix.x_axis, "vec3"),
+            vector(matrix.y_axis, "vec3"),
+            vector(matrix.z_axis, "vec3")
+        ),
+        ShaderLanguage::Hlsl => {
+            let rows = matrix.transpose();
+            format!(
+                "mul(float3x3({}, {}, {}), c)",
+                vector(rows.x_axis, "float3"),
+                vector(rows.y_axis, "float3"),
+                vector(rows.z_axis, "float3")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{spaces, ColorSpace};
+
+    /// Compares colors component-wise, skipping the hue of colors in Oklch
+    /// without chroma, where it's ill-conditioned.
+    fn assert_matches(src: ColorSpace, dst: ColorSpace, actual: Vec3, expected: Vec3) {
+        let mut pairs = vec![(actual.x, expected.x), (actual.y, expected.y)];
+        if dst != spaces::OK_LCH || expected.y > 1e-3 {
+            pairs.push((actual.z, expected.z));
+        }
+        for (a, e) in pairs {
+            assert!(
+                (a - e).abs() <= 1e-3 * e.abs().max(1.0),
+                "{:?} -> {:?}: {:?} != {:?}",
+                src,
+                dst,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn ops_match_conversions() {
+        let supported = [
+            spaces::LINEAR_SRGB,
+            spaces::ENCODED_SRGB,
+            spaces::ENCODED_BT_709,
+            spaces::ENCODED_BT_2100_PQ,
+            spaces::ACES_CG,
+            spaces::OK_LAB,
+            spaces::OK_LCH,
+            spaces::LINEAR_SRGB.to_cie_xyy(),
+            spaces::LINEAR_SRGB.to_cie_lab(),
+            spaces::DISPLAY_P3.to_cie_lab(),
+        ];
+        let colors = [
+            Vec3::new(0.2, 0.5, 0.8),
+            Vec3::new(0.9, 0.3, 0.1),
+            Vec3::new(0.01, 0.02, 0.005),
+            Vec3::new(0.6, 0.6, 0.6),
+            // Outside the sRGB gamut, with negative components.
+            Vec3::new(-0.5, 0.05, 0.6),
+        ];
+        let languages = [
+            ShaderLanguage::Wgsl,
+            ShaderLanguage::Glsl,
+            ShaderLanguage::Hlsl,
+        ];
+        for src in supported {
+            for dst in supported {
+                let conversion = ColorConversion::new(src, dst);
+                let function = ShaderFunction::from_conversion(&conversion, "convert").unwrap();
+                let programs = languages
+                    .map(|language| interpreter::Program::parse(&function.to_source(language)));
+                for color in colors {
+                    // Start from the color in linear sRGB, so that the input
+                    // is valid in every source space.
+                    let color = ColorConversion::new(spaces::LINEAR_SRGB, src).convert(color);
+                    let expected = conversion.convert(color);
+                    // kolor's PQ isn't defined outside of the BT.2020 gamut,
+                    // while the shaders clamp negative values.
+                    if [expected.x, expected.y, expected.z]
+                        .iter()
+                        .any(|v| v.is_nan())
+                    {
+                        continue;
+                    }
+                    assert_matches(src, dst, function.eval(color), expected);
+                    for program in &programs {
+                        assert_matches(src, dst, program.call("convert", color), expected);
+                    }
+                }
+            }
+        }
+
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::ICT_CP_PQ);
+        assert_eq!(
+            ShaderFunction::from_conversion(&conversion, "convert"),
+            Err(ShaderError::UnsupportedTransform(TransformFn::IctCpPq))
+        );
+    }
+
+    #[test]
+    fn source() {
+        let conversion = ColorConversion::new(spaces::ENCODED_SRGB, spaces::OK_LCH);
+        let function = ShaderFunction::from_conversion(&conversion, "srgb_to_oklch").unwrap();
+        assert_eq!(function.ops().len(), 6);
+
+        let wgsl = function.to_source(ShaderLanguage::Wgsl);
+        assert!(wgsl.starts_with("fn srgb_to_oklch_0(x: f32) -> f32 {\n    if (x < 0.04045) {"));
+        assert!(
+            wgsl.contains("fn srgb_to_oklch(color: vec3<f32>) -> vec3<f32> {\n    var c = color;")
+        );
+        assert!(wgsl.contains("    c = mat3x3<f32>(vec3<f32>("));
+        assert!(wgsl.contains("atan2(c.z, c.y)"));
+        assert!(wgsl.ends_with("    return c;\n}\n"));
+
+        let glsl = function.to_source(ShaderLanguage::Glsl);
+        assert!(
+            glsl.contains("float srgb_to_oklch_3(float x) {\n    return sign(x) * pow(abs(x), ")
+        );
+        assert!(glsl.contains("vec3 srgb_to_oklch(vec3 color) {\n    vec3 c = color;"));
+        assert!(glsl.contains(
+            "    c = vec3(srgb_to_oklch_0(c.x), srgb_to_oklch_0(c.y), srgb_to_oklch_0(c.z));"
+        ));
+        assert!(glsl.contains("atan(c.z, c.y)"));
+
+        let hlsl = function.to_source(ShaderLanguage::Hlsl);
+        assert!(hlsl.contains("float3 srgb_to_oklch(float3 color) {\n    float3 c = color;"));
+        let matrix = conversion.linear_part().matrix();
+        let row = matrix.transpose().x_axis;
+        assert!(hlsl.contains(&format!("    c = mul(float3x3({}, ", vector(row, "float3"))));
+
+        let lab = ColorConversion::new(spaces::LINEAR_SRGB.to_cie_lab(), spaces::LINEAR_SRGB);
+        let glsl = ShaderFunction::from_conversion(&lab, "lab_to_rgb")
+            .unwrap()
+            .to_source(ShaderLanguage::Glsl);
+        assert!(glsl.contains(" + vec3(0.13793"));
+        assert!(glsl.contains("return x * 0.12841"));
+        assert_eq!(literal(-2.5), "(-2.5)");
+    }
+
+    /// Evaluates the subset of WGSL, GLSL and HLSL written by
+    /// [`ShaderFunction::to_source`], so that the tests check the emitted
+    /// text rather than only the ops it was written from.
+    #[allow(clippy::unnecessary_cast)]
+    mod interpreter {
+        use crate::{Float, Vec3};
+        use std::collections::HashMap;
+
+        #[derive(Debug, Clone)]
+        enum Value {
+            Scalar(f64),
+            Vector(Vec<f64>),
+            /// Stored as columns.
+            Matrix([[f64; 3]; 3]),
+        }
+
+        impl Value {
+            fn scalar(&self) -> f64 {
+                match self {
+                    Value::Scalar(x) => *x,
+                    _ => panic!("expected a scalar, got {:?}", self),
+                }
+            }
+
+            fn components(&self) -> Vec<f64> {
+                match self {
+                    Value::Scalar(x) => vec![*x],
+                    Value::Vector(v) => v.clone(),
+                    Value::Matrix(_) => panic!("unexpected matrix"),
+                }
+            }
+
+            fn map(&self, f: impl Fn(f64) -> f64) -> Value {
+                match self {
+                    Value::Scalar(x) => Value::Scalar(f(*x)),
+                    Value::Vector(v) => Value::Vector(v.iter().map(|&x| f(x)).collect()),
+                    Value::Matrix(_) => panic!("unexpected matrix"),
+                }
+            }
+
+            /// Applies `f` component-wise, broadcasting scalars.
+            fn zip(&self, other: &Value, f: impl Fn(f64, f64) -> f64) -> Value {
+                match (self, other) {
+                    (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(f(*a, *b)),
+                    (Value::Scalar(a), v) => v.map(|b| f(*a, b)),
+                    (v, Value::Scalar(b)) => v.map(|a| f(a, *b)),
+                    (Value::Vector(a), Value::Vector(b)) => {
+                        assert_eq!(a.len(), b.len());
+                        Value::Vector(a.iter().zip(b).map(|(&a, &b)| f(a, b)).collect())
+                    }
+                    _ => panic!("can't combine {:?} and {:?}", self, other),
+                }
+            }
+        }
+
+        fn multiply(a: &Value, b: &Value) -> Value {
+            match (a, b) {
+                (Value::Matrix(columns), Value::Vector(v)) => Value::Vector(
+                    (0..3)
+                        .map(|row| (0..3).map(|col| columns[col][row] * v[col]).sum())
+                        .collect(),
+                ),
+                _ => a.zip(b, |a, b| a * b),
+            }
+        }
+
+        fn matrix(vectors: &[Value]) -> [[f64; 3]; 3] {
+            let mut columns = [[0.0; 3]; 3];
+            for (column, vector) in columns.iter_mut().zip(vectors) {
+                column.copy_from_slice(&vector.components());
+            }
+            columns
+        }
+
+        fn transpose(m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+            let mut t = [[0.0; 3]; 3];
+            for (i, row) in t.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value = m[j][i];
+                }
+            }
+            t
+        }
+
+        fn tokenize(source: &str) -> Vec<String> {
+            let chars = source.chars().collect::<Vec<_>>();
+            let mut tokens = Vec::new();
+            let mut i = 0;
+            while i < chars.len() {
+                let c = chars[i];
+                let start = i;
+                if c.is_whitespace() {
+                    i += 1;
+                    continue;
+                } else if c.is_ascii_alphabetic() || c == '_' {
+                    while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    // Keep WGSL types like `vec3<f32>` as one token.
+                    if chars[i..].starts_with(&['<', 'f', '3', '2', '>']) {
+                        i += 5;
+                    }
+                } else if c.is_ascii_digit() {
+                    while i < chars.len()
+                        && (chars[i].is_ascii_digit()
+                            || chars[i] == '.'
+                            || chars[i] == 'e'
+                            || (chars[i] == '-' && chars[i - 1] == 'e'))
+                    {
+                        i += 1;
+                    }
+                } else if chars[i..].starts_with(&['-', '>']) {
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+                tokens.push(chars[start..i].iter().collect());
+            }
+            tokens
+        }
+
+        struct Function {
+            param: String,
+            body: Vec<String>,
+        }
+
+        pub struct Program {
+            functions: HashMap<String, Function>,
+        }
+
+        impl Program {
+            pub fn parse(source: &str) -> Self {
+                let tokens = tokenize(source);
+                let mut functions = HashMap::new();
+                let mut i = 0;
+                while i < tokens.len() {
+                    let open = i + tokens[i..].iter().position(|t| t == "(").unwrap();
+                    let close = i + tokens[i..].iter().position(|t| t == ")").unwrap();
+                    let name = tokens[open - 1].clone();
+                    // WGSL writes `x: f32`, GLSL and HLSL `float x`.
+                    let params = &tokens[open + 1..close];
+                    let param = if params.contains(&":".to_string()) {
+                        params[0].clone()
+                    } else {
+                        params[params.len() - 1].clone()
+                    };
+                    let body_start = close + tokens[close..].iter().position(|t| t == "{").unwrap();
+                    let mut depth = 0;
+                    let mut end = body_start;
+                    for (j, token) in tokens.iter().enumerate().skip(body_start) {
+                        match token.as_str() {
+                            "{" => depth += 1,
+                            "}" => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            end = j;
+                            break;
+                        }
+                    }
+                    let body = tokens[body_start + 1..end].to_vec();
+                    functions.insert(name, Function { param, body });
+                    i = end + 1;
+                }
+                Program { functions }
+            }
+
+            pub fn call(&self, name: &str, color: Vec3) -> Vec3 {
+                let color = [color.x, color.y, color.z].map(|x| x as f64).to_vec();
+                let result = self.invoke(name, Value::Vector(color)).components();
+                Vec3::new(result[0] as Float, result[1] as Float, result[2] as Float)
+            }
+
+            fn invoke(&self, name: &str, argument: Value) -> Value {
+                let function = &self.functions[name];
+                let mut locals = HashMap::new();
+                locals.insert(function.param.clone(), argument);
+                let mut eval = Eval {
+                    program: self,
+                    tokens: &function.body,
+                    pos: 0,
+                    locals,
+                };
+                eval.block().expect("function didn't return")
+            }
+        }
+
+        struct Eval<'a> {
+            program: &'a Program,
+            tokens: &'a [String],
+            pos: usize,
+            locals: HashMap<String, Value>,
+        }
+
+        impl Eval<'_> {
+            fn peek(&self) -> &str {
+                self.tokens.get(self.pos).map_or("", String::as_str)
+            }
+
+            fn next(&mut self) -> String {
+                self.pos += 1;
+                self.tokens[self.pos - 1].clone()
+            }
+
+            fn expect(&mut self, token: &str) {
+                assert_eq!(self.next(), token);
+            }
+
+            /// Runs statements until a `return` or the end of a block.
+            fn block(&mut self) -> Option<Value> {
+                while self.pos < self.tokens.len() && self.peek() != "}" {
+                    match self.peek() {
+                        "return" => {
+                            self.next();
+                            let value = self.expr();
+                            self.expect(";");
+                            return Some(value);
+                        }
+                        "if" => {
+                            self.next();
+                            self.expect("(");
+                            let condition = self.expr().scalar();
+                            self.expect(")");
+                            self.expect("{");
+                            if condition != 0.0 {
+                                return self.block();
+                            }
+                            let mut depth = 1;
+                            while depth > 0 {
+                                match self.next().as_str() {
+                                    "{" => depth += 1,
+                                    "}" => depth -= 1,
+                                    _ => {}
+                                }
+                            }
+                        }
+                        _ => {
+                            // `let x = ...`, `float x = ...` or `x = ...`.
+                            let mut name = self.next();
+                            if self.peek() != "=" {
+                                name = self.next();
+                            }
+                            self.expect("=");
+                            let value = self.expr();
+                            self.expect(";");
+                            self.locals.insert(name, value);
+                        }
+                    }
+                }
+                None
+            }
+
+            fn expr(&mut self) -> Value {
+                let lhs = self.additive();
+                if self.peek() == "<" {
+                    self.next();
+                    let rhs = self.additive();
+                    let less = lhs.scalar() < rhs.scalar();
+                    return Value::Scalar(if less { 1.0 } else { 0.0 });
+                }
+                lhs
+            }
+
+            fn additive(&mut self) -> Value {
+                let mut value = self.multiplicative();
+                loop {
+                    match self.peek() {
+                        "+" => {
+                            self.next();
+                            value = value.zip(&self.multiplicative(), |a, b| a + b);
+                        }
+                        "-" => {
+                            self.next();
+                            value = value.zip(&self.multiplicative(), |a, b| a - b);
+                        }
+                        _ => return value,
+                    }
+                }
+            }
+
+            fn multiplicative(&mut self) -> Value {
+                let mut value = self.unary();
+                loop {
+                    match self.peek() {
+                        "*" => {
+                            self.next();
+                            value = multiply(&value, &self.unary());
+                        }
+                        "/" => {
+                            self.next();
+                            value = value.zip(&self.unary(), |a, b| a / b);
+                        }
+                        _ => return value,
+                    }
+                }
+            }
+
+            fn unary(&mut self) -> Value {
+                if self.peek() == "-" {
+                    self.next();
+                    return self.unary().map(|x| -x);
+                }
+                let mut value = self.primary();
+                while self.peek() == "." {
+                    self.next();
+                    let components = value.components();
+                    let swizzle = self
+                        .next()
+                        .chars()
+                        .map(|c| components["xyz".find(c).unwrap()])
+                        .collect::<Vec<_>>();
+                    value = match swizzle.as_slice() {
+                        [x] => Value::Scalar(*x),
+                        _ => Value::Vector(swizzle),
+                    };
+                }
+                value
+            }
+
+            fn primary(&mut self) -> Value {
+                let token = self.next();
+                if token == "(" {
+                    let value = self.expr();
+                    self.expect(")");
+                    return value;
+                }
+                if let Ok(number) = token.parse::<f64>() {
+                    return Value::Scalar(number);
+                }
+                if self.peek() != "(" {
+                    return self.locals[&token].clone();
+                }
+                self.next();
+                let mut args = Vec::new();
+                while self.peek() != ")" {
+                    args.push(self.expr());
+                    if self.peek() == "," {
+                        self.next();
+                    }
+                }
+                self.next();
+                match (token.as_str(), args.as_slice()) {
+                    ("vec3" | "vec3<f32>" | "float3", _) => {
+                        Value::Vector(args.iter().flat_map(Value::components).collect())
+                    }
+                    ("mat3" | "mat3x3<f32>", _) => Value::Matrix(matrix(&args)),
+                    ("float3x3", _) => Value::Matrix(transpose(matrix(&args))),
+                    ("mul", [m, v]) => multiply(m, v),
+                    ("pow", [x, y]) => x.zip(y, f64::powf),
+                    ("max", [x, y]) => x.zip(y, f64::max),
+                    ("atan" | "atan2", [y, x]) => y.zip(x, f64::atan2),
+                    ("abs", [x]) => x.map(f64::abs),
+                    ("cos", [x]) => x.map(f64::cos),
+                    ("sin", [x]) => x.map(f64::sin),
+                    ("sign", [x]) => x.map(|x| if x == 0.0 { 0.0 } else { x.signum() }),
+                    ("length", [v]) => {
+                        Value::Scalar(v.components().iter().map(|x| x * x).sum::<f64>().sqrt())
+                    }
+                    (name, [x]) => self.program.invoke(name, x.clone()),
+                    _ => panic!("unknown call {}", token),
+                }
+            }
+        }
+    }
+}
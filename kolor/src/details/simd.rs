@@ -0,0 +1,352 @@
+//! Conversion of planar (structure-of-arrays) color buffers, [`LANES`] colors
+//! at a time.
+//!
+//! With the `simd` feature the linear matrix stage and the sRGB, BT.601, PQ and
+//! Oklab transform functions are evaluated on [`wide`](https://docs.rs/wide)
+//! vectors, 8 colors at a time with `f32` and 4 with `f64`. Other transform
+//! functions are applied one color at a time within each vector.
+//!
+//! Without the `simd` feature, planar buffers are converted with the scalar
+//! implementation, so the same API is available in every configuration,
+//! including `no_std` with `libm`.
+//!
+//! Use [`ColorConversion::convert_planar`] to convert planar buffers.
+use super::conversion::ColorConversion;
+use crate::Float;
+
+/// The number of colors converted per iteration with the `simd` feature.
+#[cfg(not(feature = "f64"))]
+pub const LANES: usize = 8;
+/// The number of colors converted per iteration with the `simd` feature.
+#[cfg(feature = "f64")]
+pub const LANES: usize = 4;
+
+/// Converts the colors stored in the planes `x`, `y` and `z` in place.
+///
+/// # Panics
+///
+/// Panics if the planes have different lengths.
+pub(crate) fn convert_planar(
+    conversion: &ColorConversion,
+    x: &mut [Float],
+    y: &mut [Float],
+    z: &mut [Float],
+) {
+    assert!(
+        x.len() == y.len() && y.len() == z.len(),
+        "planes must have the same length"
+    );
+    #[cfg(feature = "simd")]
+    let (x, y, z) = vectorized::convert_planar(conversion, x, y, z);
+    convert_planar_scalar(conversion, x, y, z);
+}
+
+fn convert_planar_scalar(
+    conversion: &ColorConversion,
+    x: &mut [Float],
+    y: &mut [Float],
+    z: &mut [Float],
+) {
+    for ((x, y), z) in x.iter_mut().zip(y.iter_mut()).zip(z.iter_mut()) {
+        let color = conversion.convert(crate::Vec3::new(*x, *y, *z));
+        (*x, *y, *z) = (color.x, color.y, color.z);
+    }
+}
+
+#[cfg(feature = "simd")]
+mod vectorized {
+    use super::LANES;
+    use crate::details::{
+        color::{TransformFn, WhitePoint},
+        conversion::ColorConversion,
+        transform::{pq, ColorTransform, OKLAB_M_1, OKLAB_M_2},
+    };
+    use crate::{Float, Mat3, Vec3};
+    use wide::CmpLt;
+
+    #[cfg(not(feature = "f64"))]
+    type FloatX = wide::f32x8;
+    #[cfg(feature = "f64")]
+    type FloatX = wide::f64x4;
+
+    #[derive(Copy, Clone)]
+    struct Vec3X {
+        x: FloatX,
+        y: FloatX,
+        z: FloatX,
+    }
+
+    impl Vec3X {
+        fn map(self, f: impl Fn(FloatX) -> FloatX) -> Self {
+            Self {
+                x: f(self.x),
+                y: f(self.y),
+                z: f(self.z),
+            }
+        }
+
+        fn transform(self, mat: &Mat3) -> Self {
+            let row = |x: Float, y: Float, z: Float| {
+                self.x * FloatX::splat(x) + self.y * FloatX::splat(y) + self.z * FloatX::splat(z)
+            };
+            Self {
+                x: row(mat.x_axis.x, mat.y_axis.x, mat.z_axis.x),
+                y: row(mat.x_axis.y, mat.y_axis.y, mat.z_axis.y),
+                z: row(mat.x_axis.z, mat.y_axis.z, mat.z_axis.z),
+            }
+        }
+    }
+
+    /// A transform function stage, as evaluated on vectors.
+    #[derive(Copy, Clone)]
+    enum Stage {
+        Srgb,
+        SrgbInverse,
+        Bt601,
+        Bt601Inverse,
+        Pq,
+        PqInverse,
+        OkLab,
+        OkLabInverse {
+            m_1_inverse: Mat3,
+            m_2_inverse: Mat3,
+        },
+        Scalar(ColorTransform, WhitePoint),
+    }
+
+    impl Stage {
+        fn new(
+            transform: Option<ColorTransform>,
+            transform_fn: TransformFn,
+            inverse: bool,
+            white_point: WhitePoint,
+        ) -> Option<Self> {
+            let transform = transform?;
+            Some(match (transform_fn, inverse) {
+                (TransformFn::Srgb, false) => Stage::Srgb,
+                (TransformFn::Srgb, true) => Stage::SrgbInverse,
+                (TransformFn::Bt601, false) => Stage::Bt601,
+                (TransformFn::Bt601, true) => Stage::Bt601Inverse,
+                (TransformFn::Pq, false) => Stage::Pq,
+                (TransformFn::Pq, true) => Stage::PqInverse,
+                (TransformFn::OkLab, false) => Stage::OkLab,
+                (TransformFn::OkLab, true) => Stage::OkLabInverse {
+                    m_1_inverse: OKLAB_M_1.inverse(),
+                    m_2_inverse: OKLAB_M_2.inverse(),
+                },
+                _ => Stage::Scalar(transform, white_point),
+            })
+        }
+
+        #[inline(always)]
+        fn apply(&self, color: Vec3X) -> Vec3X {
+            match self {
+                Stage::Srgb => color.map(srgb_oetf),
+                Stage::SrgbInverse => color.map(srgb_eotf),
+                Stage::Bt601 => color.map(bt601_oetf),
+                Stage::Bt601Inverse => color.map(bt601_oetf_inverse),
+                Stage::Pq => color.map(pq_eotf_inverse),
+                Stage::PqInverse => color.map(pq_eotf),
+                Stage::OkLab => color
+                    .transform(&OKLAB_M_1)
+                    .map(|v| v.abs().powf(1.0 / 3.0).copysign(v))
+                    .transform(&OKLAB_M_2),
+                Stage::OkLabInverse {
+                    m_1_inverse,
+                    m_2_inverse,
+                } => color
+                    .transform(m_2_inverse)
+                    .map(|v| v * v * v)
+                    .transform(m_1_inverse),
+                Stage::Scalar(transform, white_point) => {
+                    let (x, y, z) = (color.x.to_array(), color.y.to_array(), color.z.to_array());
+                    let (mut out_x, mut out_y, mut out_z) = (x, y, z);
+                    for i in 0..LANES {
+                        let value = transform.apply(Vec3::new(x[i], y[i], z[i]), *white_point);
+                        (out_x[i], out_y[i], out_z[i]) = (value.x, value.y, value.z);
+                    }
+                    Vec3X {
+                        x: out_x.into(),
+                        y: out_y.into(),
+                        z: out_z.into(),
+                    }
+                }
+            }
+        }
+    }
+
+    fn srgb_oetf(v: FloatX) -> FloatX {
+        let lower = v * FloatX::splat(12.92);
+        let higher = FloatX::splat(1.055) * v.powf(1.0 / 2.4) - FloatX::splat(0.055);
+        v.cmp_lt(FloatX::splat(0.0031308)).blend(lower, higher)
+    }
+
+    fn srgb_eotf(v: FloatX) -> FloatX {
+        let lower = v / FloatX::splat(12.92);
+        let higher = ((v + FloatX::splat(0.055)) / FloatX::splat(1.055)).powf(2.4);
+        v.cmp_lt(FloatX::splat(0.04045)).blend(lower, higher)
+    }
+
+    fn bt601_oetf(v: FloatX) -> FloatX {
+        let lower = v * FloatX::splat(4.5);
+        let higher = FloatX::splat(1.0993) * v.powf(0.45) - FloatX::splat(0.0993);
+        v.cmp_lt(FloatX::splat(0.0181)).blend(lower, higher)
+    }
+
+    fn bt601_oetf_inverse(v: FloatX) -> FloatX {
+        let lower = v / FloatX::splat(4.5);
+        let higher = ((v + FloatX::splat(0.0993)) / FloatX::splat(1.0993)).powf(1.0 / 0.45);
+        v.cmp_lt(FloatX::splat(0.08145)).blend(lower, higher)
+    }
+
+    fn pq_eotf_inverse(v: FloatX) -> FloatX {
+        let y_p = (v / FloatX::splat(pq::L_p)).powf(pq::M_1);
+        ((FloatX::splat(pq::C_1) + FloatX::splat(pq::C_2) * y_p)
+            / (FloatX::splat(pq::C_3) * y_p + FloatX::splat(1.0)))
+        .powf(pq::M_2)
+    }
+
+    fn pq_eotf(v: FloatX) -> FloatX {
+        let v_p = v.powf(pq::M_2_d);
+        let n = (v_p - FloatX::splat(pq::C_1)).max(FloatX::splat(0.0));
+        let l = (n / (FloatX::splat(pq::C_2) - FloatX::splat(pq::C_3) * v_p)).powf(pq::M_1_d);
+        FloatX::splat(pq::L_p) * l
+    }
+
+    fn load(plane: &[Float]) -> FloatX {
+        let lanes: [Float; LANES] = plane.try_into().unwrap();
+        lanes.into()
+    }
+
+    /// Converts as many whole vectors as possible, returning the remaining
+    /// colors.
+    pub(super) fn convert_planar<'a>(
+        conversion: &ColorConversion,
+        x: &'a mut [Float],
+        y: &'a mut [Float],
+        z: &'a mut [Float],
+    ) -> (&'a mut [Float], &'a mut [Float], &'a mut [Float]) {
+        let src_space = conversion.src_space();
+        let dst_space = conversion.dst_space();
+        let src = Stage::new(
+            conversion.src_transform(),
+            src_space.transform_function(),
+            true,
+            src_space.white_point(),
+        );
+        let dst = Stage::new(
+            conversion.dst_transform(),
+            dst_space.transform_function(),
+            false,
+            dst_space.white_point(),
+        );
+        let mat = conversion.linear_part().matrix();
+        let mat = if mat == Mat3::IDENTITY {
+            None
+        } else {
+            Some(mat)
+        };
+
+        let vectorized = x.len() / LANES * LANES;
+        let (x, x_rest) = x.split_at_mut(vectorized);
+        let (y, y_rest) = y.split_at_mut(vectorized);
+        let (z, z_rest) = z.split_at_mut(vectorized);
+        let planes = x
+            .chunks_exact_mut(LANES)
+            .zip(y.chunks_exact_mut(LANES))
+            .zip(z.chunks_exact_mut(LANES));
+        for ((x, y), z) in planes {
+            let mut color = Vec3X {
+                x: load(x),
+                y: load(y),
+                z: load(z),
+            };
+            if let Some(src) = &src {
+                color = src.apply(color);
+            }
+            if let Some(mat) = &mat {
+                color = color.transform(mat);
+            }
+            if let Some(dst) = &dst {
+                color = dst.apply(color);
+            }
+            x.copy_from_slice(&color.x.to_array());
+            y.copy_from_slice(&color.y.to_array());
+            z.copy_from_slice(&color.z.to_array());
+        }
+        (x_rest, y_rest, z_rest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{details::color::TransformFn, spaces, ColorSpace, Vec3};
+
+    fn check<const N: usize>(
+        src: ColorSpace,
+        dst: ColorSpace,
+        colors: &[Vec3; N],
+        tolerance: Float,
+    ) {
+        let conversion = ColorConversion::new(src, dst);
+        let mut x = colors.map(|c| c.x);
+        let mut y = colors.map(|c| c.y);
+        let mut z = colors.map(|c| c.z);
+        conversion.convert_planar(&mut x, &mut y, &mut z);
+        for (i, color) in colors.iter().enumerate() {
+            let expected = conversion.convert(*color);
+            let result = Vec3::new(x[i], y[i], z[i]);
+            let scale = expected
+                .x
+                .abs()
+                .max(expected.y.abs())
+                .max(expected.z.abs())
+                .max(1.0);
+            // Both paths produce NaN for colors a transform isn't defined for,
+            // such as negative values encoded with PQ.
+            let matches = |r: Float, e: Float| {
+                (r.is_nan() && e.is_nan()) || (r - e).abs() <= tolerance * scale
+            };
+            assert!(
+                matches(result.x, expected.x)
+                    && matches(result.y, expected.y)
+                    && matches(result.z, expected.z),
+                "{:?} -> {:?}: {:?} != {:?}",
+                src,
+                dst,
+                result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn planar_matches_scalar() {
+        // Arrays rather than vectors, so that this also runs without std.
+        let colors: [Vec3; 37] = core::array::from_fn(|i| {
+            let t = i as Float / 36.0;
+            Vec3::new(t, (t * 7.0) % 1.0, 1.0 - t)
+        });
+        let pq = spaces::BT_2020.with_transform(TransformFn::Pq);
+        let bt601 = spaces::BT_2020.with_transform(TransformFn::Bt601);
+        let pairs = [
+            (spaces::ENCODED_SRGB, spaces::ACES_CG),
+            (spaces::ACES_CG, spaces::ENCODED_SRGB),
+            (spaces::LINEAR_SRGB, spaces::OK_LAB),
+            (spaces::OK_LAB, spaces::LINEAR_SRGB),
+            (spaces::ENCODED_SRGB, bt601),
+            (bt601, spaces::LINEAR_SRGB),
+            (spaces::ENCODED_SRGB, spaces::CIE_XYZ.to_cie_lab()),
+            (spaces::LINEAR_SRGB, spaces::LINEAR_SRGB),
+        ];
+        for (src, dst) in pairs {
+            check(src, dst, &colors, 0.0001);
+        }
+
+        // PQ covers 0 to 10000 nits.
+        let nits = colors.map(|c| c * 1000.0);
+        check(spaces::BT_2020, pq, &nits, 0.0001);
+        check(pq, spaces::BT_2020, &colors, 0.0001);
+    }
+}
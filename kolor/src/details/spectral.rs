@@ -0,0 +1,277 @@
+//! Spectral power distributions and their integration into CIE XYZ.
+//!
+//! A [`Spectrum`] holds values sampled at increasing wavelengths (in
+//! nanometers), for example the emission of a light source or the reflectance
+//! of a surface. Spectra are converted to tristimulus values by integrating
+//! them against the color matching functions of a standard [`Observer`].
+//!
+//! The color matching functions are embedded as tables in the
+//! [`spectral_data`] module, sampled every 5 nm from
+//! 380 nm to 780 nm. Integration is performed in 1 nm steps over that range,
+//! linearly interpolating both the spectrum and the color matching functions.
+use super::{
+    color::{color_spaces, Color},
+    spectral_data::{self, TABLE_LEN, TABLE_START, TABLE_STEP},
+};
+use crate::{Float, Vec3};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The shortest wavelength, in nanometers, considered during integration.
+pub const WAVELENGTH_MIN: Float = 380.0;
+/// The longest wavelength, in nanometers, considered during integration.
+pub const WAVELENGTH_MAX: Float = 780.0;
+
+/// A CIE standard colorimetric observer, defined by its color matching
+/// functions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Observer {
+    /// The CIE 1931 2° standard observer, which is the observer used for all
+    /// the XYZ values and white points in `kolor`.
+    #[default]
+    Cie1931,
+    /// The CIE 1964 10° supplementary standard observer.
+    Cie1964,
+}
+
+impl Observer {
+    /// Returns the color matching function table of the observer, sampled
+    /// every [`TABLE_STEP`] nm starting at [`TABLE_START`].
+    pub fn table(&self) -> &'static [[Float; 3]; TABLE_LEN] {
+        match self {
+            Observer::Cie1931 => &spectral_data::CIE_1931_2_DEGREE,
+            Observer::Cie1964 => &spectral_data::CIE_1964_10_DEGREE,
+        }
+    }
+
+    /// Evaluates the color matching functions `(x̄, ȳ, z̄)` at `wavelength`
+    /// (in nm) with linear interpolation. Returns zero outside of
+    /// [`WAVELENGTH_MIN`]..=[`WAVELENGTH_MAX`].
+    pub fn cmf(&self, wavelength: Float) -> Vec3 {
+        let table = self.table();
+        let position = (wavelength - TABLE_START) / TABLE_STEP;
+        if !(0.0..=(TABLE_LEN - 1) as Float).contains(&position) {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let index = (position as usize).min(TABLE_LEN - 2);
+        let t = position - index as Float;
+        let a = Vec3::from(table[index]);
+        let b = Vec3::from(table[index + 1]);
+        a * (1.0 - t) + b * t
+    }
+}
+
+/// Values sampled at strictly increasing wavelengths (in nanometers).
+///
+/// `Spectrum` is used both for emissive spectral power distributions and for
+/// reflectances, which are expected to be in `0.0..=1.0`.
+///
+/// Evaluating a spectrum between two samples linearly interpolates them.
+/// Outside of the sampled range the nearest sample is used, as recommended by
+/// CIE 015.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Spectrum {
+    wavelengths: Vec<Float>,
+    values: Vec<Float>,
+}
+
+impl Spectrum {
+    /// Creates a spectrum from matching lists of wavelengths and values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lists are empty, differ in length, or if `wavelengths` is
+    /// not strictly increasing.
+    pub fn new(wavelengths: Vec<Float>, values: Vec<Float>) -> Self {
+        assert!(
+            !wavelengths.is_empty(),
+            "a spectrum needs at least one sample"
+        );
+        assert_eq!(
+            wavelengths.len(),
+            values.len(),
+            "wavelengths and values must have the same length"
+        );
+        assert!(
+            wavelengths.windows(2).all(|w| w[0] < w[1]),
+            "wavelengths must be strictly increasing"
+        );
+        Self {
+            wavelengths,
+            values,
+        }
+    }
+
+    /// Creates a spectrum from values sampled every `step` nm, starting at
+    /// `start` nm.
+    pub fn from_uniform(start: Float, step: Float, values: Vec<Float>) -> Self {
+        let wavelengths = (0..values.len())
+            .map(|i| start + step * i as Float)
+            .collect();
+        Self::new(wavelengths, values)
+    }
+
+    /// Creates a spectrum with the same `value` at every wavelength.
+    pub fn constant(value: Float) -> Self {
+        Self::new(vec![WAVELENGTH_MIN], vec![value])
+    }
+
+    /// The CIE standard illuminant D65, normalized to 100 at 560 nm.
+    pub fn cie_d65() -> Self {
+        Self::from_table(&spectral_data::ILLUMINANT_D65)
+    }
+
+    /// The CIE illuminant D50, normalized to 100 at 560 nm.
+    pub fn cie_d50() -> Self {
+        Self::from_table(&spectral_data::ILLUMINANT_D50)
+    }
+
+    fn from_table(table: &[Float; TABLE_LEN]) -> Self {
+        Self::from_uniform(TABLE_START, TABLE_STEP, table.to_vec())
+    }
+
+    pub fn wavelengths(&self) -> &[Float] {
+        &self.wavelengths
+    }
+
+    pub fn values(&self) -> &[Float] {
+        &self.values
+    }
+
+    /// Evaluates the spectrum at `wavelength` (in nm).
+    pub fn sample(&self, wavelength: Float) -> Float {
+        let upper = self.wavelengths.partition_point(|&w| w < wavelength);
+        if upper == 0 {
+            return self.values[0];
+        }
+        if upper == self.wavelengths.len() {
+            return self.values[upper - 1];
+        }
+        let (w0, w1) = (self.wavelengths[upper - 1], self.wavelengths[upper]);
+        let (v0, v1) = (self.values[upper - 1], self.values[upper]);
+        let t = (wavelength - w0) / (w1 - w0);
+        v0 + (v1 - v0) * t
+    }
+
+    /// Creates a new spectrum with `len` samples taken every `step` nm,
+    /// starting at `start` nm.
+    pub fn resample(&self, start: Float, step: Float, len: usize) -> Self {
+        let values = (0..len)
+            .map(|i| self.sample(start + step * i as Float))
+            .collect();
+        Self::from_uniform(start, step, values)
+    }
+
+    /// Returns a copy of the spectrum with every value multiplied by `factor`.
+    pub fn scaled(&self, factor: Float) -> Self {
+        Self {
+            wavelengths: self.wavelengths.clone(),
+            values: self.values.iter().map(|v| v * factor).collect(),
+        }
+    }
+
+    /// Integrates the spectrum as an emissive spectral power distribution,
+    /// returning a color in [`CIE_XYZ`][color_spaces::CIE_XYZ].
+    ///
+    /// The result is in the units of the spectrum multiplied by nanometers;
+    /// multiply by 683 lm/W to get photometric units from a radiometric
+    /// spectrum in W/(sr·m²·nm).
+    pub fn emissive_to_xyz(&self, observer: Observer) -> Color {
+        let xyz = integrate(observer, |wavelength| self.sample(wavelength));
+        Color::new(xyz.x, xyz.y, xyz.z, color_spaces::CIE_XYZ)
+    }
+
+    /// Integrates the spectrum as a reflectance lit by `illuminant`,
+    /// returning a color in [`CIE_XYZ`][color_spaces::CIE_XYZ].
+    ///
+    /// The result is normalized so that a perfect reflector has a `Y` of 1.
+    pub fn reflectance_to_xyz(&self, illuminant: &Spectrum, observer: Observer) -> Color {
+        let white = integrate(observer, |wavelength| illuminant.sample(wavelength));
+        let xyz = integrate(observer, |wavelength| {
+            self.sample(wavelength) * illuminant.sample(wavelength)
+        });
+        let xyz = xyz * (1.0 / white.y);
+        Color::new(xyz.x, xyz.y, xyz.z, color_spaces::CIE_XYZ)
+    }
+}
+
+/// Integrates `spectrum` against the color matching functions of `observer`
+/// in 1 nm steps from [`WAVELENGTH_MIN`] to [`WAVELENGTH_MAX`].
+pub fn integrate(observer: Observer, spectrum: impl Fn(Float) -> Float) -> Vec3 {
+    let steps = (WAVELENGTH_MAX - WAVELENGTH_MIN) as usize;
+    let mut xyz = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..=steps {
+        let wavelength = WAVELENGTH_MIN + i as Float;
+        xyz += observer.cmf(wavelength) * spectrum(wavelength);
+    }
+    xyz
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::details::color::WhitePoint;
+
+    #[test]
+    fn cmf_interpolation() {
+        let at_555 = Observer::Cie1931.cmf(555.0);
+        assert!(at_555.abs_diff_eq(Vec3::new(0.5121, 1.0, 0.00575), 0.0001));
+        let at_557 = Observer::Cie1931.cmf(557.5);
+        let expected = (Vec3::new(0.5121, 1.0, 0.00575) + Vec3::new(0.5945, 0.995, 0.0039)) * 0.5;
+        assert!(at_557.abs_diff_eq(expected, 0.0001));
+        assert_eq!(Observer::Cie1964.cmf(300.0), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn spectrum_sampling() {
+        let spectrum = Spectrum::new(vec![400.0, 500.0, 700.0], vec![0.0, 1.0, 0.5]);
+        assert_eq!(spectrum.sample(350.0), 0.0);
+        assert_eq!(spectrum.sample(450.0), 0.5);
+        assert_eq!(spectrum.sample(600.0), 0.75);
+        assert_eq!(spectrum.sample(800.0), 0.5);
+        let resampled = spectrum.resample(400.0, 50.0, 7);
+        assert_eq!(
+            resampled.values(),
+            &[0.0, 0.5, 1.0, 0.875, 0.75, 0.625, 0.5]
+        );
+    }
+
+    #[test]
+    fn d65_white_point() {
+        let white =
+            Spectrum::constant(1.0).reflectance_to_xyz(&Spectrum::cie_d65(), Observer::Cie1931);
+        let expected = Vec3::from_slice(WhitePoint::D65.values());
+        assert!(
+            white.value.abs_diff_eq(expected, 0.001),
+            "{:?} != {:?}",
+            white.value,
+            expected
+        );
+
+        let d50 = Spectrum::cie_d50().emissive_to_xyz(Observer::Cie1931);
+        let d50 = d50.value * (1.0 / d50.value.y);
+        let expected = Vec3::from_slice(WhitePoint::D50.values());
+        assert!(
+            d50.abs_diff_eq(expected, 0.001),
+            "{:?} != {:?}",
+            d50,
+            expected
+        );
+    }
+
+    #[test]
+    fn d65_10_degree_white_point() {
+        // CIE 015 tristimulus values of D65 for the 10° observer.
+        let white =
+            Spectrum::constant(1.0).reflectance_to_xyz(&Spectrum::cie_d65(), Observer::Cie1964);
+        let expected = Vec3::new(0.94811, 1.0, 1.07304);
+        assert!(
+            white.value.abs_diff_eq(expected, 0.001),
+            "{:?} != {:?}",
+            white.value,
+            expected
+        );
+    }
+}
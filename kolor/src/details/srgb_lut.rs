@@ -0,0 +1,191 @@
+//! Lookup tables for decoding and encoding 8-bit sRGB.
+//!
+//! [`SrgbLut`] produces exactly the same results as decoding an 8-bit value
+//! with [`srgb_eotf`] and encoding with [`srgb_oetf`] followed by rounding to
+//! the nearest 8-bit value, as done by
+//! [`Rgba8Unorm`](super::pixel_format::Rgba8Unorm) and the
+//! [`Srgb`](super::color::TransformFn::Srgb) transform function, without
+//! calling `powf`.
+//!
+//! Unlike a typical sRGB lookup table, the tables aren't stored as `const`
+//! arrays. [`SrgbLut::new`] builds them at runtime from the transform
+//! functions themselves, because constants would only be exact for one
+//! [`Float`] width and one `powf` implementation, and `std` and `libm` builds
+//! may round differently. Building them takes well under a millisecond, but
+//! it should be done once:
+//!
+//! - with the `std` feature, `SrgbLut::shared` builds them on first use;
+//! - without it, there is no lazily initialized global, so build an
+//!   [`SrgbLut`] during startup and pass it by reference.
+use super::{
+    color::WhitePoint,
+    pixel_format::{decode_unorm, encode_unorm},
+    transform::{srgb_eotf, srgb_oetf},
+};
+use crate::{Float, Vec3};
+
+/// The number of buckets in the encode table, which give a starting point for
+/// the search through the rounding thresholds.
+const ENCODE_BUCKETS: usize = 4096;
+
+/// Precomputed tables for converting between 8-bit sRGB and linear values.
+#[derive(Clone)]
+pub struct SrgbLut {
+    decode: [Float; 256],
+    /// `thresholds[i]` is the smallest linear value which encodes to more
+    /// than `i`.
+    thresholds: [Float; 255],
+    /// The encoded value of the start of each bucket of `0.0..1.0`.
+    buckets: [u8; ENCODE_BUCKETS],
+}
+
+impl core::fmt::Debug for SrgbLut {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SrgbLut").finish_non_exhaustive()
+    }
+}
+
+impl Default for SrgbLut {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes a linear value with the sRGB OETF and rounds it to 8 bits.
+fn encode_exact(value: Float) -> u8 {
+    let encoded = srgb_oetf(Vec3::splat(value), WhitePoint::D65).x;
+    encode_unorm(encoded, 255) as u8
+}
+
+/// Finds the smallest value in `lo..=hi` which encodes to more than `code`,
+/// where `lo` encodes to at most `code` and `hi` to more.
+fn find_threshold(code: u8, mut lo: Float, mut hi: Float) -> Float {
+    loop {
+        let mid = lo + (hi - lo) * 0.5;
+        if mid <= lo || mid >= hi {
+            return hi;
+        }
+        if encode_exact(mid) > code {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+}
+
+impl SrgbLut {
+    /// Builds the tables. This evaluates the transform functions a few
+    /// thousand times, so the result should be reused, for example through
+    /// [`SrgbLut::shared`].
+    pub fn new() -> Self {
+        let mut decode = [0.0; 256];
+        for (i, value) in decode.iter_mut().enumerate() {
+            let encoded = decode_unorm(i as u32, 255);
+            *value = srgb_eotf(Vec3::splat(encoded), WhitePoint::D65).x;
+        }
+
+        let mut thresholds = [0.0; 255];
+        let mut lo = 0.0;
+        for (code, threshold) in thresholds.iter_mut().enumerate() {
+            *threshold = find_threshold(code as u8, lo, 1.0);
+            lo = *threshold;
+        }
+
+        let mut buckets = [0; ENCODE_BUCKETS];
+        for (i, bucket) in buckets.iter_mut().enumerate() {
+            *bucket = encode_exact(i as Float / ENCODE_BUCKETS as Float);
+        }
+
+        Self {
+            decode,
+            thresholds,
+            buckets,
+        }
+    }
+
+    /// Returns tables shared by the whole program, which are built on first
+    /// use.
+    #[cfg(feature = "std")]
+    pub fn shared() -> &'static Self {
+        static SHARED: std::sync::OnceLock<SrgbLut> = std::sync::OnceLock::new();
+        SHARED.get_or_init(Self::new)
+    }
+
+    /// Decodes an 8-bit sRGB value to linear.
+    #[inline]
+    pub fn decode(&self, value: u8) -> Float {
+        self.decode[value as usize]
+    }
+
+    /// Encodes a linear value to 8-bit sRGB, rounding to nearest. Values
+    /// outside of `0.0..=1.0` are clamped and NaN encodes as 0.
+    #[inline]
+    pub fn encode(&self, value: Float) -> u8 {
+        if value.is_nan() || value <= 0.0 {
+            return 0;
+        }
+        let bucket = ((value * ENCODE_BUCKETS as Float) as usize).min(ENCODE_BUCKETS - 1);
+        let mut code = self.buckets[bucket];
+        while code < 255 && value >= self.thresholds[code as usize] {
+            code += 1;
+        }
+        code
+    }
+
+    /// Decodes an 8-bit sRGB color to linear.
+    #[inline]
+    pub fn decode_rgb(&self, color: [u8; 3]) -> Vec3 {
+        Vec3::new(
+            self.decode(color[0]),
+            self.decode(color[1]),
+            self.decode(color[2]),
+        )
+    }
+
+    /// Encodes a linear color to 8-bit sRGB.
+    #[inline]
+    pub fn encode_rgb(&self, color: Vec3) -> [u8; 3] {
+        [
+            self.encode(color.x),
+            self.encode(color.y),
+            self.encode(color.z),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_matches_eotf() {
+        let lut = SrgbLut::new();
+        for i in 0..=255u8 {
+            let expected = srgb_eotf(Vec3::splat(i as Float / 255.0), WhitePoint::D65).x;
+            assert_eq!(lut.decode(i), expected);
+        }
+        assert_eq!(lut.decode_rgb([0, 255, 128]).y, 1.0);
+    }
+
+    #[test]
+    fn encode_matches_oetf() {
+        let lut = SrgbLut::new();
+        for &threshold in lut.thresholds.iter() {
+            for step in -16..=16 {
+                let value = threshold + threshold * Float::EPSILON * step as Float;
+                assert_eq!(lut.encode(value), encode_exact(value), "{}", value);
+            }
+        }
+        let samples = 200_000;
+        for i in 0..=samples {
+            let value = i as Float / samples as Float * 1.2 - 0.1;
+            assert_eq!(lut.encode(value), encode_exact(value), "{}", value);
+        }
+        for i in 0..=255u8 {
+            assert_eq!(lut.encode(lut.decode(i)), i);
+        }
+        assert_eq!(lut.encode(Float::NAN), 0);
+        assert_eq!(lut.encode(Float::INFINITY), 255);
+        assert_eq!(lut.encode_rgb(Vec3::new(-1.0, 0.5, 2.0)), [0, 188, 255]);
+    }
+}
@@ -3,8 +3,8 @@ use super::{
     math::prelude::*,
 };
 use crate::{Float, Mat3, Vec3, PI, TAU};
-#[cfg(all(not(feature = "std"), feature = "libm"))]
-use num_traits::Float;
+#[cfg(all(not(feature = "std"), not(test), feature = "libm"))]
+use num_traits::Float as _;
 
 /// Represents a reference to a function that can apply a [`TransformFn`] or
 /// its inverse.
@@ -168,14 +168,71 @@ pub fn bt601_oetf_inverse(color: Vec3, _wp: WhitePoint) -> Vec3 {
     Vec3::select(cutoff, lower, higher)
 }
 
+/// The parameters `[cutoff, linear_scale, linear_offset, a, b, exponent,
+/// scale, offset]` of the sRGB and BT.601 transfer functions and the CIELAB
+/// non-linearity `f`, or of their inverses when `inverse` is set, as curves
+/// mapping `x` to `x * linear_scale + linear_offset` below `cutoff` and to
+/// `scale * max(a * x + b, 0)^exponent + offset` otherwise.
+///
+/// These are shared by the [`shader`](super::shader) and
+/// [`gpu`](super::gpu) modules.
+#[allow(clippy::excessive_precision)]
+pub(crate) fn piecewise_power_params(transform: TransformFn, inverse: bool) -> Option<[Float; 8]> {
+    Some(match (transform, inverse) {
+        (TransformFn::Srgb, false) => [0.0031308, 12.92, 0.0, 1.0, 0.0, 1.0 / 2.4, 1.055, -0.055],
+        (TransformFn::Srgb, true) => [
+            0.04045,
+            1.0 / 12.92,
+            0.0,
+            1.0 / 1.055,
+            0.055 / 1.055,
+            2.4,
+            1.0,
+            0.0,
+        ],
+        (TransformFn::Bt601, false) => [0.0181, 4.5, 0.0, 1.0, 0.0, 0.45, 1.0993, -0.0993],
+        (TransformFn::Bt601, true) => [
+            0.08145,
+            1.0 / 4.5,
+            0.0,
+            1.0 / 1.0993,
+            0.0993 / 1.0993,
+            1.0 / 0.45,
+            1.0,
+            0.0,
+        ],
+        (TransformFn::CieLab, false) => [
+            0.008856,
+            7.78703703704,
+            0.13793103448,
+            1.0,
+            0.0,
+            1.0 / 3.0,
+            1.0,
+            0.0,
+        ],
+        (TransformFn::CieLab, true) => [
+            0.008856,
+            0.12841854934,
+            -0.12841854934 * 0.13793103448,
+            1.0,
+            0.0,
+            3.0,
+            1.0,
+            0.0,
+        ],
+        _ => return None,
+    })
+}
+
 #[rustfmt::skip]
-const OKLAB_M_1: Mat3 =
+pub(crate) const OKLAB_M_1: Mat3 =
     Mat3::from_cols_array(&[0.8189330101,0.0329845436,0.0482003018,
     0.3618667424,0.9293118715,0.2643662691,
     -0.1288597137,0.0361456387,0.6338517070]);
 
 #[rustfmt::skip]
-const OKLAB_M_2: Mat3 =
+pub(crate) const OKLAB_M_2: Mat3 =
    Mat3::from_cols_array(&[0.2104542553,1.9779984951,0.02599040371,
     0.7936177850,-2.4285922050,0.7827717662,
     -0.0040720468,0.4505937099,-0.8086757660]);
@@ -494,14 +551,14 @@ pub use hlg::*;
 #[allow(non_upper_case_globals)]
 pub mod pq {
     use super::*;
-    const L_p: Float = 10000.0;
-    const M_1: Float = 0.1593017578125;
-    const M_2: Float = 78.84375;
-    const M_1_d: Float = 1.0 / M_1;
-    const M_2_d: Float = 1.0 / M_2;
-    const C_1: Float = 0.8359375;
-    const C_2: Float = 18.8515625;
-    const C_3: Float = 18.6875;
+    pub(crate) const L_p: Float = 10000.0;
+    pub(crate) const M_1: Float = 0.1593017578125;
+    pub(crate) const M_2: Float = 78.84375;
+    pub(crate) const M_1_d: Float = 1.0 / M_1;
+    pub(crate) const M_2_d: Float = 1.0 / M_2;
+    pub(crate) const C_1: Float = 0.8359375;
+    pub(crate) const C_2: Float = 18.8515625;
+    pub(crate) const C_3: Float = 18.6875;
 
     /// SMPTE ST 2084:2014 perceptual electo-optical transfer function inverse.
     #[inline]
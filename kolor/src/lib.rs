@@ -67,8 +67,12 @@
 //! implementations clearly can't be used directly. To implement data-driven
 //! conversions, you can read the required operations for transforming between
 //! spaces from a `ColorConversion` value and run these as appropriate.
-//! Feel free to port the implementations in the
-//! [`transform`](`details::transform`) module to your shaders or other code.
+//! With the `std` feature, the [`shader`](details::shader) module generates
+//! WGSL, GLSL and HLSL functions for a `ColorConversion`, so the
+//! implementations in the [`transform`](`details::transform`) module don't
+//! need to be ported to your shaders by hand. For shaders which choose the
+//! conversion at runtime, [`GpuConversion`](details::gpu::GpuConversion)
+//! describes a conversion as plain data to upload to a buffer.
 //!
 //! ### Gamut-Agnostic Transforms
 //!
@@ -78,6 +82,15 @@
 //! construct these color spaces, refer to associated methods on `ColorSpace`
 //! and use an appropriate reference color space.
 //!
+//! ### Alpha
+//!
+//! [`AlphaColor`] pairs a `Color` with an alpha value, which is either
+//! straight or premultiplied as given by its [`AlphaMode`]. Non-linear
+//! transform functions like the sRGB OETF must be applied to straight colors,
+//! so converting a premultiplied color un-premultiplies it first and
+//! premultiplies the result, while linear conversions are applied to
+//! premultiplied colors directly.
+//!
 //! ### Details
 //!
 //! `kolor` can calculate 3×3 conversion matrices between any linear color space
@@ -100,6 +113,15 @@
 //! on-demand, meaning the creation will be a bit slower to create than if there
 //! is a constant matrix available.
 //!
+//! Any conversion matrix can also be computed at compile time with the
+//! [`conversion_matrix!`] macro or
+//! [`LinearColorConversion::new_const`][details::conversion::LinearColorConversion::new_const],
+//! for `no_std` targets or to fill shader constant tables.
+//!
+//! The `kolor-matrix-gen` tool in the `kolor` repository exports the same
+//! matrices, between built-in or custom primaries and white points, as Rust,
+//! C, GLSL, WGSL, HLSL or JSON constants.
+//!
 //! ### Chromatic Adaptation Transformation (CAT)
 //!
 //! `kolor` implements CAT in the [`cat`](details::cat) module and supports the
@@ -109,7 +131,8 @@
 //! another.
 //!
 //! Use [`ColorSpace::with_whitepoint()`] to change the `WhitePoint` for a color
-//! space.
+//! space. The cone space used for adaptation can be chosen per conversion with
+//! [`ConversionOptions`][details::conversion::ConversionOptions].
 //!
 //! ### XYZ-RGB Conversions
 //!
@@ -118,6 +141,55 @@
 //! create conversion matrices to/from an RGB color space given a set of
 //! primaries and a white point.
 //!
+//! ### Batch Conversions
+//!
+//! [`ColorConversion`] can convert whole buffers at once, interleaved with
+//! [`convert_slice`](ColorConversion::convert_slice) and
+//! [`convert_strided`](ColorConversion::convert_strided), or planar with
+//! [`convert_planar`](ColorConversion::convert_planar). With the `simd`
+//! feature, planar conversions use SIMD for the matrix stage and common
+//! transform functions, see the [`simd`](details::simd) module. With the
+//! `rayon` feature, each of these has a `par_` version which converts chunks
+//! of the buffer in parallel.
+//!
+//! Packed GPU texture formats such as RGBA8, RGB10A2, R11G11B10 float and
+//! RGB9E5 are in the [`pixel_format`](details::pixel_format) module, and can
+//! be converted directly with
+//! [`convert_pixels`](ColorConversion::convert_pixels) and
+//! [`convert_pixels_into`](ColorConversion::convert_pixels_into). For 8-bit
+//! sRGB, the [`srgb_lut`](details::srgb_lut) module decodes and encodes with
+//! lookup tables instead of evaluating the transform functions.
+//!
+//! ### Lookup Tables
+//!
+//! With the `std` feature, the [`lut`](details::lut) module can bake any
+//! `ColorConversion` into a 3D LUT, to export as a `.cube` file for grading
+//! applications or to upload to a 3D texture. It also loads `.cube` and `.3dl`
+//! LUTs, which can be declared as the conversion between two `ColorSpace`s
+//! with [`LutConversion`](details::lut::LutConversion).
+//!
+//! The [`clf`](details::clf) module writes conversions as Academy Common LUT
+//! Format process lists for ACES interchange, and reads and evaluates them.
+//! The [`ocio`](details::ocio) module generates OpenColorIO configs from a set
+//! of `ColorSpace`s, so that OCIO-based applications match kolor.
+//!
+//! Embedded ICC profiles of RGB images can be read with the
+//! [`icc`](details::icc) module, which finds the equivalent built-in
+//! `ColorSpace` when there is one and otherwise converts through CIE XYZ, and
+//! RGB `ColorSpace`s can be written as ICC v4 profiles to embed in exported
+//! images.
+//!
+//! ### Spectral Data
+//!
+//! With the `std` feature, the [`spectral`](details::spectral) module can
+//! convert sampled spectral power distributions and reflectances into CIE XYZ
+//! using the CIE 1931 2° or CIE 1964 10° standard observers. The
+//! [`rgb_to_spectrum`](details::rgb_to_spectrum) module goes the other way,
+//! upsampling RGB reflectances to smooth spectra, and the
+//! [`blackbody`](details::blackbody) module calculates the colors of
+//! blackbody radiators. Monochromatic colors, the spectral locus and dominant
+//! wavelengths can be found in the [`chromaticity`](details::chromaticity) module.
+//!
 //! ## Features
 //!
 //! ### `no_std` & `glam` Support
@@ -156,6 +228,19 @@ macro_rules! const_mat3 {
     };
 }
 
+/// Evaluates the [`Mat3`] converting from one linear [`ColorSpace`] to
+/// another at compile time, using
+/// [`LinearColorConversion::new_const`](details::conversion::LinearColorConversion::new_const),
+/// e.g. `conversion_matrix!(spaces::BT_2020 => spaces::LINEAR_SRGB)`.
+#[macro_export]
+macro_rules! conversion_matrix {
+    ($src:expr => $dst:expr) => {{
+        const MATRIX: $crate::Mat3 =
+            $crate::details::conversion::LinearColorConversion::new_const($src, $dst).matrix();
+        MATRIX
+    }};
+}
+
 #[cfg(not(feature = "f64"))]
 pub(crate) use core::f32::consts::PI;
 #[cfg(not(feature = "f64"))]
@@ -166,13 +251,45 @@ pub(crate) use core::f64::consts::PI;
 pub(crate) use core::f64::consts::TAU;
 
 pub mod details {
+    #[cfg(feature = "std")]
+    #[allow(clippy::excessive_precision)]
+    pub mod blackbody;
     pub mod cat;
+    #[cfg(feature = "std")]
+    pub mod chromaticity;
+    #[cfg(feature = "std")]
+    #[allow(clippy::unnecessary_cast)]
+    pub mod clf;
     pub mod color;
+    pub mod const_math;
     pub mod conversion;
     #[allow(clippy::excessive_precision)]
     #[cfg(feature = "color-matrices")]
     pub mod generated_matrices;
+    #[allow(clippy::unnecessary_cast)]
+    pub mod gpu;
+    #[cfg(feature = "std")]
+    #[allow(clippy::unnecessary_cast)]
+    pub mod icc;
+    #[cfg(feature = "std")]
+    #[allow(clippy::unnecessary_cast)]
+    pub mod lut;
     pub mod math;
+    #[cfg(feature = "std")]
+    pub mod ocio;
+    #[allow(clippy::unnecessary_cast)]
+    pub mod pixel_format;
+    #[cfg(feature = "std")]
+    #[allow(clippy::unnecessary_cast)]
+    pub mod rgb_to_spectrum;
+    #[cfg(feature = "std")]
+    pub mod shader;
+    pub mod simd;
+    #[cfg(feature = "std")]
+    pub mod spectral;
+    #[allow(clippy::excessive_precision)]
+    pub mod spectral_data;
+    pub mod srgb_lut;
     #[allow(clippy::excessive_precision)]
     #[allow(clippy::many_single_char_names)]
     #[allow(non_snake_case)]
@@ -182,6 +299,6 @@ pub mod details {
 #[doc(inline)]
 pub use details::color::color_spaces as spaces;
 #[doc(inline)]
-pub use details::color::{Color, ColorSpace};
+pub use details::color::{AlphaColor, AlphaMode, Color, ColorSpace};
 #[doc(inline)]
 pub use details::conversion::ColorConversion;
@@ -1,7 +1,434 @@
-use kolor::details::conversion::LinearColorConversion;
+use kolor::{
+    details::{
+        cat::{DegreeOfAdaptation, LmsConeSpace},
+        color::{RgbPrimaries, TransformFn, WhitePoint},
+        conversion::{ConversionOptions, LinearColorConversion},
+        xyz::{rgb_to_xyz, xyz_to_rgb},
+    },
+    spaces, ColorSpace, Float, Mat3, Vec3,
+};
 use kolor_64 as kolor;
+use std::convert::TryInto;
 
-fn main() {
+const USAGE: &str = "Usage:
+    kolor-matrix-gen generate [PATH]
+        Regenerates the bundled matrices of the `color-matrices` feature,
+        written to kolor/src/details/generated_matrices.rs by default.
+    kolor-matrix-gen list
+        Lists the built-in color spaces, primaries, white points and
+        chromatic adaptation methods.
+    kolor-matrix-gen export --src SPACE --dst SPACE [OPTIONS]
+        Writes the matrix converting linear SPACE colors from --src to --dst.
+
+SPACE is either the name of a built-in color space, like LINEAR_SRGB, or
+PRIMARIES:WHITE_POINT. PRIMARIES is the name of built-in primaries or six
+comma-separated xy chromaticities of red, green and blue. WHITE_POINT is the
+name of a built-in white point or its comma-separated xy chromaticity. Names
+are case-insensitive. Non-linear built-in color spaces use their linear part.
+
+Export options:
+    --cat METHOD      Chromatic adaptation method [default: Sharp]
+    --format FORMAT   rust, c, glsl, wgsl, hlsl or json [default: rust]
+    --name NAME       Name of the constant, made of ASCII letters, digits and
+                      underscores [default: SRC_TO_DST]
+    --out PATH        Write to PATH instead of stdout";
+
+const BUILT_IN_SPACES: [(&str, ColorSpace); 22] = [
+    ("LINEAR_SRGB", spaces::LINEAR_SRGB),
+    ("ENCODED_SRGB", spaces::ENCODED_SRGB),
+    ("BT_709", spaces::BT_709),
+    ("ENCODED_BT_709", spaces::ENCODED_BT_709),
+    ("BT_2020", spaces::BT_2020),
+    ("ENCODED_BT_2020", spaces::ENCODED_BT_2020),
+    ("ENCODED_BT_2100_PQ", spaces::ENCODED_BT_2100_PQ),
+    ("ACES_CG", spaces::ACES_CG),
+    ("ACES_2065_1", spaces::ACES_2065_1),
+    ("CIE_RGB", spaces::CIE_RGB),
+    ("CIE_XYZ", spaces::CIE_XYZ),
+    ("OK_LAB", spaces::OK_LAB),
+    ("ICT_CP_PQ", spaces::ICT_CP_PQ),
+    ("ICT_CP_HLG", spaces::ICT_CP_HLG),
+    ("PRO_PHOTO", spaces::PRO_PHOTO),
+    ("APPLE", spaces::APPLE),
+    ("P3_D60", spaces::P3_D60),
+    ("P3_THEATER", spaces::P3_THEATER),
+    ("DISPLAY_P3", spaces::DISPLAY_P3),
+    ("ENCODED_DISPLAY_P3", spaces::ENCODED_DISPLAY_P3),
+    ("ADOBE_1998", spaces::ADOBE_1998),
+    ("ADOBE_WIDE", spaces::ADOBE_WIDE),
+];
+
+const PRIMARIES: [RgbPrimaries; 11] = [
+    RgbPrimaries::Bt709,
+    RgbPrimaries::Bt2020,
+    RgbPrimaries::Ap0,
+    RgbPrimaries::Ap1,
+    RgbPrimaries::P3,
+    RgbPrimaries::Adobe1998,
+    RgbPrimaries::AdobeWide,
+    RgbPrimaries::Apple,
+    RgbPrimaries::ProPhoto,
+    RgbPrimaries::CieRgb,
+    RgbPrimaries::CieXyz,
+];
+
+const WHITE_POINTS: [WhitePoint; 13] = [
+    WhitePoint::A,
+    WhitePoint::B,
+    WhitePoint::C,
+    WhitePoint::E,
+    WhitePoint::D50,
+    WhitePoint::D55,
+    WhitePoint::D60,
+    WhitePoint::D65,
+    WhitePoint::D75,
+    WhitePoint::P3Dci,
+    WhitePoint::F2,
+    WhitePoint::F7,
+    WhitePoint::F11,
+];
+
+const ADAPTATION_METHODS: [LmsConeSpace; 11] = [
+    LmsConeSpace::VonKries,
+    LmsConeSpace::Bradford,
+    LmsConeSpace::Sharp,
+    LmsConeSpace::CmcCat2000,
+    LmsConeSpace::Cat02,
+    LmsConeSpace::Cat16,
+    LmsConeSpace::XyzScaling,
+    LmsConeSpace::HuntPointerEstevez,
+    LmsConeSpace::BiancoSchettini,
+    LmsConeSpace::BiancoSchettiniPc,
+    LmsConeSpace::Fairchild2001,
+];
+
+/// A source or destination of an exported matrix.
+#[derive(Debug, Clone, PartialEq)]
+enum Space {
+    /// A space the crate can convert between itself.
+    BuiltIn { name: String, space: ColorSpace },
+    /// Primaries and a white point of which at least one isn't built-in.
+    /// `primaries` is `None` for CIE XYZ.
+    Custom {
+        primaries: Option<[[Float; 2]; 3]>,
+        white_point: [Float; 3],
+    },
+}
+
+impl Space {
+    fn name(&self) -> &str {
+        match self {
+            Space::BuiltIn { name, .. } => name,
+            Space::Custom { .. } => "CUSTOM",
+        }
+    }
+
+    fn parse(arg: &str) -> Result<Self, String> {
+        if let Some((_, space)) = BUILT_IN_SPACES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(arg))
+        {
+            return Ok(Space::BuiltIn {
+                name: arg.to_ascii_uppercase(),
+                space: space.as_linear(),
+            });
+        }
+        let (primaries_arg, white_point_arg) = arg
+            .split_once(':')
+            .ok_or_else(|| format!("unknown color space `{}`", arg))?;
+        let primaries = find_by_name(&PRIMARIES, primaries_arg);
+        let white_point = find_by_name(&WHITE_POINTS, white_point_arg);
+        if let (Some(primaries), Some(white_point)) = (primaries, white_point) {
+            return Ok(Space::BuiltIn {
+                name: format!("{:?}_{:?}", primaries, white_point).to_ascii_uppercase(),
+                space: ColorSpace::new(primaries, white_point, TransformFn::None),
+            });
+        }
+        let primaries = match primaries {
+            Some(RgbPrimaries::CieXyz) => None,
+            Some(primaries) => Some(*primaries.values()),
+            None => {
+                let [xr, yr, xg, yg, xb, yb] = parse_floats(primaries_arg)?;
+                Some([[xr, yr], [xg, yg], [xb, yb]])
+            }
+        };
+        let white_point = match white_point {
+            Some(white_point) => *white_point.values(),
+            None => {
+                let [x, y] = parse_floats(white_point_arg)?;
+                [x / y, 1.0, (1.0 - x - y) / y]
+            }
+        };
+        Ok(Space::Custom {
+            primaries,
+            white_point,
+        })
+    }
+
+    fn primaries(&self) -> Option<[[Float; 2]; 3]> {
+        match self {
+            Space::BuiltIn { space, .. } if space.primaries() == RgbPrimaries::CieXyz => None,
+            Space::BuiltIn { space, .. } => Some(*space.primaries().values()),
+            Space::Custom { primaries, .. } => *primaries,
+        }
+    }
+
+    fn white_point(&self) -> [Float; 3] {
+        match self {
+            Space::BuiltIn { space, .. } => *space.white_point().values(),
+            Space::Custom { white_point, .. } => *white_point,
+        }
+    }
+}
+
+fn find_by_name<T: Copy + core::fmt::Debug>(values: &[T], name: &str) -> Option<T> {
+    values
+        .iter()
+        .copied()
+        .find(|value| format!("{:?}", value).eq_ignore_ascii_case(name))
+}
+
+fn parse_floats<const N: usize>(arg: &str) -> Result<[Float; N], String> {
+    let values = arg
+        .split(',')
+        .map(|value| value.trim().parse::<Float>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("invalid number in `{}`", arg))?;
+    values.try_into().map_err(|_| {
+        format!(
+            "expected a name or {} comma-separated numbers, got `{}`",
+            N, arg
+        )
+    })
+}
+
+/// Calculates the matrix the same way as [`LinearColorConversion`], which is
+/// used directly when both spaces are built-in.
+fn conversion_matrix(src: &Space, dst: &Space, method: LmsConeSpace) -> Mat3 {
+    if let (Space::BuiltIn { space: src, .. }, Space::BuiltIn { space: dst, .. }) = (src, dst) {
+        let options = ConversionOptions::default().with_adaptation_method(method);
+        return LinearColorConversion::new_with_options(*src, *dst, options).matrix();
+    }
+    let src_to_xyz = src.primaries().map_or(Mat3::IDENTITY, |primaries| {
+        rgb_to_xyz(&primaries, &src.white_point())
+    });
+    let xyz_to_dst = dst.primaries().map_or(Mat3::IDENTITY, |primaries| {
+        xyz_to_rgb(&primaries, &dst.white_point())
+    });
+    if src.white_point() != dst.white_point() {
+        let white_point_transform = method.partial_chromatic_adaptation_transform(
+            Vec3::from_slice(&src.white_point()),
+            Vec3::from_slice(&dst.white_point()),
+            DegreeOfAdaptation::Full,
+        );
+        xyz_to_dst * white_point_transform * src_to_xyz
+    } else {
+        xyz_to_dst * src_to_xyz
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Rust,
+    C,
+    Glsl,
+    Wgsl,
+    Hlsl,
+    Json,
+}
+
+impl Format {
+    fn parse(arg: &str) -> Result<Self, String> {
+        match arg.to_ascii_lowercase().as_str() {
+            "rust" => Ok(Format::Rust),
+            "c" => Ok(Format::C),
+            "glsl" => Ok(Format::Glsl),
+            "wgsl" => Ok(Format::Wgsl),
+            "hlsl" => Ok(Format::Hlsl),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format `{}`", arg)),
+        }
+    }
+}
+
+fn join(values: &[Float]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{:?}", value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writes `matrix` as a constant. Shading languages and Rust take columns,
+/// while C and JSON get an array of rows.
+fn format_matrix(
+    matrix: Mat3,
+    name: &str,
+    src: &Space,
+    dst: &Space,
+    method: LmsConeSpace,
+    format: Format,
+) -> String {
+    let columns = [matrix.x_axis, matrix.y_axis, matrix.z_axis].map(|c| c.to_array());
+    let rows = [matrix.row(0), matrix.row(1), matrix.row(2)].map(|r| r.to_array());
+    let comment = format!(
+        "Converts linear {} to linear {} using {:?} chromatic adaptation.",
+        src.name(),
+        dst.name(),
+        method
+    );
+    match format {
+        Format::Rust => format!(
+            "/// {}\n#[rustfmt::skip]\npub const {}: Mat3 = Mat3::from_cols_array(&[\n    {},\n    {},\n    {},\n]);\n",
+            comment,
+            name,
+            join(&columns[0]),
+            join(&columns[1]),
+            join(&columns[2]),
+        ),
+        Format::C => format!(
+            "/* {} Row-major. */\n#ifndef {name}_H\n#define {name}_H\n\nstatic const double {name}[3][3] = {{\n    {{ {} }},\n    {{ {} }},\n    {{ {} }},\n}};\n\n#endif\n",
+            comment,
+            join(&rows[0]),
+            join(&rows[1]),
+            join(&rows[2]),
+            name = name,
+        ),
+        Format::Glsl => format!(
+            "// {}\nconst mat3 {} = mat3(\n    {},\n    {},\n    {}\n);\n",
+            comment,
+            name,
+            join(&columns[0]),
+            join(&columns[1]),
+            join(&columns[2]),
+        ),
+        Format::Wgsl => format!(
+            "// {}\nconst {}: mat3x3<f32> = mat3x3<f32>(\n    vec3<f32>({}),\n    vec3<f32>({}),\n    vec3<f32>({}),\n);\n",
+            comment,
+            name,
+            join(&columns[0]),
+            join(&columns[1]),
+            join(&columns[2]),
+        ),
+        Format::Hlsl => format!(
+            "// {} Use as mul({}, color).\nstatic const float3x3 {} = float3x3(\n    {},\n    {},\n    {}\n);\n",
+            comment,
+            name,
+            name,
+            join(&rows[0]),
+            join(&rows[1]),
+            join(&rows[2]),
+        ),
+        Format::Json => format!(
+            "{{\n  \"name\": \"{}\",\n  \"src\": \"{}\",\n  \"dst\": \"{}\",\n  \"adaptation_method\": \"{:?}\",\n  \"rows\": [\n    [{}],\n    [{}],\n    [{}]\n  ]\n}}\n",
+            name,
+            src.name(),
+            dst.name(),
+            method,
+            join(&rows[0]),
+            join(&rows[1]),
+            join(&rows[2]),
+        ),
+    }
+}
+
+/// Whether `name` can be used as is for a constant in every format.
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let mut src = None;
+    let mut dst = None;
+    let mut method = LmsConeSpace::default();
+    let mut format = Format::Rust;
+    let mut name = None;
+    let mut out = None;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for `{}`", flag))?;
+        match flag.as_str() {
+            "--src" => src = Some(Space::parse(value)?),
+            "--dst" => dst = Some(Space::parse(value)?),
+            "--cat" => {
+                method = find_by_name(&ADAPTATION_METHODS, value)
+                    .ok_or_else(|| format!("unknown adaptation method `{}`", value))?
+            }
+            "--format" => format = Format::parse(value)?,
+            "--name" if is_identifier(value) => name = Some(value.clone()),
+            "--name" => return Err(format!("`{}` isn't a valid identifier", value)),
+            "--out" => out = Some(value.clone()),
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    let src = src.ok_or("missing --src")?;
+    let dst = dst.ok_or("missing --dst")?;
+    let name = name.unwrap_or_else(|| format!("{}_TO_{}", src.name(), dst.name()));
+    let matrix = conversion_matrix(&src, &dst, method);
+    if !matrix.is_finite() {
+        return Err("the color spaces are degenerate".to_string());
+    }
+    let output = format_matrix(matrix, &name, &src, &dst, method, format);
+    match out {
+        Some(path) => std::fs::write(&path, output).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", output);
+            Ok(())
+        }
+    }
+}
+
+fn list() {
+    println!("Color spaces:");
+    for (name, space) in &BUILT_IN_SPACES {
+        println!(
+            "    {:<20} {:?}:{:?} {:?}",
+            name,
+            space.primaries(),
+            space.white_point(),
+            space.transform_function()
+        );
+    }
+    println!("\nPrimaries:");
+    for primaries in &PRIMARIES {
+        let [[xr, yr], [xg, yg], [xb, yb]] = *primaries.values();
+        println!(
+            "    {:<20} {},{},{},{},{},{}",
+            format!("{:?}", primaries),
+            xr,
+            yr,
+            xg,
+            yg,
+            xb,
+            yb
+        );
+    }
+    println!("\nWhite points:");
+    for white_point in &WHITE_POINTS {
+        let [x, y, z] = *white_point.values();
+        let sum = x + y + z;
+        println!(
+            "    {:<20} {:.5},{:.5}",
+            format!("{:?}", white_point),
+            x / sum,
+            y / sum
+        );
+    }
+    println!("\nChromatic adaptation methods:");
+    for method in &ADAPTATION_METHODS {
+        let default = if *method == LmsConeSpace::default() {
+            " (default)"
+        } else {
+            ""
+        };
+        println!("    {:?}{}", method, default);
+    }
+}
+
+fn generate(path: &str) -> Result<(), String> {
     let mut conversions = Vec::new();
     for src in &kolor::spaces::ALL_COLOR_SPACES {
         for dst in &kolor::spaces::ALL_COLOR_SPACES {
@@ -84,5 +511,111 @@ pub fn const_conversion_matrix(
         const_matches
     );
 
-    std::fs::write("../kolor/src/details/generated_matrices.rs", out_str).unwrap();
+    std::fs::write(path, out_str).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("generate") if args.len() <= 2 => generate(args.get(1).map_or(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../kolor/src/details/generated_matrices.rs"
+            ),
+            String::as_str,
+        )),
+        Some("list") if args.len() == 1 => {
+            list();
+            Ok(())
+        }
+        Some("export") => export(&args[1..]),
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_spaces() {
+        assert_eq!(
+            Space::parse("encoded_srgb").unwrap(),
+            Space::BuiltIn {
+                name: "ENCODED_SRGB".to_string(),
+                space: spaces::LINEAR_SRGB
+            }
+        );
+        assert_eq!(
+            Space::parse("Bt2020:d65").unwrap(),
+            Space::BuiltIn {
+                name: "BT2020_D65".to_string(),
+                space: spaces::BT_2020
+            }
+        );
+        assert_eq!(
+            Space::parse("CieXyz:0.3127,0.329").unwrap().primaries(),
+            None
+        );
+        assert!(Space::parse("Bt2020").is_err());
+        assert!(Space::parse("Bt2020:0.3127").is_err());
+        assert!(Space::parse("0.64,0.33,0.3,0.6,0.15:D65").is_err());
+    }
+
+    #[test]
+    fn custom_matches_built_in() {
+        let method = LmsConeSpace::Bradford;
+        for (src, dst) in [("LINEAR_SRGB", "ACES_CG"), ("P3:P3Dci", "CIE_XYZ")] {
+            let built_in = conversion_matrix(
+                &Space::parse(src).unwrap(),
+                &Space::parse(dst).unwrap(),
+                method,
+            );
+            let custom = |space: &str| {
+                let space = Space::parse(space).unwrap();
+                Space::Custom {
+                    primaries: space.primaries(),
+                    white_point: space.white_point(),
+                }
+            };
+            let custom = conversion_matrix(&custom(src), &custom(dst), method);
+            assert!(built_in.abs_diff_eq(custom, 1e-12));
+        }
+
+        let xy = Space::parse("0.64,0.33,0.3,0.6,0.15,0.06:0.3127,0.329").unwrap();
+        let matrix = conversion_matrix(&xy, &Space::parse("LINEAR_SRGB").unwrap(), method);
+        assert!(matrix.abs_diff_eq(Mat3::IDENTITY, 1e-3));
+    }
+
+    #[test]
+    fn formats() {
+        let src = Space::parse("LINEAR_SRGB").unwrap();
+        let dst = Space::parse("BT_2020").unwrap();
+        let method = LmsConeSpace::Sharp;
+        let matrix = conversion_matrix(&src, &dst, method);
+        let output = |format| format_matrix(matrix, "M", &src, &dst, method, format);
+        let first_row = join(&matrix.row(0).to_array());
+        let first_column = join(&matrix.x_axis.to_array());
+        assert!(output(Format::Rust).contains(&first_column));
+        assert!(output(Format::Glsl).contains(&format!("mat3(\n    {},", first_column)));
+        assert!(output(Format::Wgsl).contains(&format!("vec3<f32>({})", first_column)));
+        assert!(output(Format::Hlsl).contains(&format!("float3x3(\n    {},", first_row)));
+        assert!(output(Format::C).contains(&format!("{{ {} }}", first_row)));
+        assert!(output(Format::Json).contains(&format!("[{}]", first_row)));
+
+        assert!(is_identifier("SRGB_TO_BT_2020") && is_identifier("_m2"));
+        for name in ["", "2M", "a\"b", "a\\b", "a b"] {
+            assert!(!is_identifier(name), "{}", name);
+        }
+        let args = ["--src", "LINEAR_SRGB", "--dst", "BT_2020", "--name", "a\"b"];
+        assert!(export(&args.map(String::from)).is_err());
+    }
 }